@@ -1,5 +1,17 @@
 #!/bin/bash
 set -e  # Exit on error
 
-echo "Building WASM module..."
-wasm-pack build crates/wasm --target web --out-dir ../../www/static/wasm
\ No newline at end of file
+# ./build.sh --simd builds with wasm simd128 enabled. Every current browser
+# supports it, but the default build stays scalar for older ones.
+if [ "$1" = "--simd" ]; then
+    export RUSTFLAGS="$RUSTFLAGS -C target-feature=+simd128"
+    # Host `cargo test` only ever sees the scalar fallbacks, so check the
+    # vector kernels against them before shipping.
+    echo "Testing simd128 kernels..."
+    wasm-pack test --node crates/wasm -- --lib dsp::simd
+    echo "Building WASM module (simd128)..."
+else
+    echo "Building WASM module..."
+fi
+
+wasm-pack build crates/wasm --target web --out-dir ../../www/static/wasm
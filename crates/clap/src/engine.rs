@@ -0,0 +1,132 @@
+//! Audio-thread side of the plugin: one instance of every effect, run in
+//! the order [`Shared`] describes.
+
+use crate::params::{Shared, Target};
+use decay_wasm::dsp;
+use decay_wasm::effects::{self, Effect, KINDS};
+
+pub struct Engine {
+    effects: Vec<Box<dyn Effect>>,
+    order: Vec<usize>,
+    /// The order before the last change, to spot effects switched on.
+    previous: Vec<usize>,
+    left: Vec<f32>,
+    right: Vec<f32>,
+}
+
+impl Engine {
+    /// Allocates everything the audio thread will need; call from
+    /// `activate`.
+    pub fn new(sample_rate: f32, max_frames: usize, shared: &Shared) -> Self {
+        let mut engine = Self {
+            effects: KINDS
+                .iter()
+                .map(|kind| effects::create(kind, sample_rate).expect("listed kinds exist"))
+                .collect(),
+            order: Vec::with_capacity(KINDS.len()),
+            previous: Vec::with_capacity(KINDS.len()),
+            left: vec![0.0; max_frames],
+            right: vec![0.0; max_frames],
+        };
+        engine.sync(shared);
+        engine
+    }
+
+    pub fn max_frames(&self) -> usize {
+        self.left.len()
+    }
+
+    /// Pushes every value into the effects and rebuilds the order.
+    pub fn sync(&mut self, shared: &Shared) {
+        for index in 0..shared.params.len() {
+            self.apply(shared, index);
+        }
+    }
+
+    /// Pushes one changed value into its effect.
+    pub fn apply(&mut self, shared: &Shared, index: usize) {
+        let desc = &shared.params[index];
+        match desc.target {
+            Target::Enabled => {
+                std::mem::swap(&mut self.order, &mut self.previous);
+                shared.chain_order(&mut self.order);
+                // A disabled effect isn't processed, so its state is stale;
+                // start it from silence rather than replaying it.
+                for &kind in &self.order {
+                    if !self.previous.contains(&kind) {
+                        self.effects[kind].reset();
+                    }
+                }
+            }
+            Target::Param(info) => {
+                self.effects[desc.kind].set_param(info.name, shared.value(index));
+            }
+        }
+        shared
+            .latency
+            .store(self.latency(), std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn latency(&self) -> u32 {
+        self.order
+            .iter()
+            .map(|&kind| self.effects[kind].latency() as u32)
+            .sum()
+    }
+
+    /// The scratch buffers the host's audio is copied into.
+    pub fn buffers(&mut self, frames: usize) -> (&mut [f32], &mut [f32]) {
+        (&mut self.left[..frames], &mut self.right[..frames])
+    }
+
+    /// Runs the chain over `range` of the scratch buffers.
+    pub fn process(&mut self, range: std::ops::Range<usize>) {
+        let left = &mut self.left[range.clone()];
+        let right = &mut self.right[range];
+        dsp::scrub(left);
+        dsp::scrub(right);
+        for &kind in &self.order {
+            self.effects[kind].process(left, right);
+        }
+        if dsp::scrub(left) | dsp::scrub(right) {
+            self.reset();
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.effects.iter_mut().for_each(|e| e.reset());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use decay_wasm::Preset;
+
+    #[test]
+    fn test_matches_the_web_chain() {
+        let json = r#"{"effects": [
+            {"kind": "ring_mod", "params": {"freq_hz": 300}},
+            {"kind": "distortion", "params": {"drive_db": 20}}
+        ]}"#;
+        let preset = Preset::from_json(json).unwrap();
+        let shared = Shared::new();
+        shared.load_preset(&preset).unwrap();
+        let mut engine = Engine::new(48_000.0, 256, &shared);
+        let mut chain = preset.build(48_000.0).unwrap();
+
+        let input: Vec<f32> = (0..256).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+        let (left, right) = engine.buffers(256);
+        left.copy_from_slice(&input);
+        right.copy_from_slice(&input);
+        engine.process(0..100);
+        engine.process(100..256);
+
+        let mut expected = [input.clone(), input];
+        let [l, r] = &mut expected;
+        chain.process(l, r);
+        let (left, right) = engine.buffers(256);
+        assert_eq!(left, &expected[0][..]);
+        assert_eq!(right, &expected[1][..]);
+    }
+}
@@ -0,0 +1,845 @@
+//! CLAP plugin build of the decay effect chain.
+//!
+//! One plugin, "Decay", exposes every effect in the DSP core through the
+//! parameter list described in [`params`]. Its saved state is preset JSON in
+//! the web app's format, and the preset-load extension reads the same
+//! files, so a preset moves between the browser, the DAW and
+//! `decay-render` unchanged.
+//!
+//! This file is the C ABI glue: the entry point, the factory, the plugin
+//! vtable and the params, audio-ports, latency, state and preset-load
+//! extensions. It follows the CLAP threading rules: `process`, `reset` and
+//! `params.flush` while active run on the audio thread, everything else on
+//! the main thread.
+
+mod engine;
+mod params;
+
+use clap_sys::audio_buffer::clap_audio_buffer;
+use clap_sys::entry::clap_plugin_entry;
+use clap_sys::events::{
+    clap_event_header, clap_event_param_value, clap_input_events, clap_output_events,
+    CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE,
+};
+use clap_sys::ext::audio_ports::{
+    clap_audio_port_info, clap_plugin_audio_ports, CLAP_AUDIO_PORT_IS_MAIN, CLAP_EXT_AUDIO_PORTS,
+    CLAP_PORT_STEREO,
+};
+use clap_sys::ext::latency::{clap_plugin_latency, CLAP_EXT_LATENCY};
+use clap_sys::ext::params::{
+    clap_host_params, clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS,
+    CLAP_PARAM_IS_AUTOMATABLE, CLAP_PARAM_IS_STEPPED, CLAP_PARAM_RESCAN_VALUES,
+};
+use clap_sys::ext::preset_load::{
+    clap_host_preset_load, clap_plugin_preset_load, CLAP_EXT_PRESET_LOAD,
+    CLAP_EXT_PRESET_LOAD_COMPAT,
+};
+use clap_sys::ext::state::{clap_host_state, clap_plugin_state, CLAP_EXT_STATE};
+use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+use clap_sys::factory::preset_discovery::{
+    clap_preset_discovery_location_kind, CLAP_PRESET_DISCOVERY_LOCATION_FILE,
+};
+use clap_sys::host::clap_host;
+use clap_sys::id::{clap_id, CLAP_INVALID_ID};
+use clap_sys::plugin::{clap_plugin, clap_plugin_descriptor};
+use clap_sys::plugin_features::{CLAP_PLUGIN_FEATURE_AUDIO_EFFECT, CLAP_PLUGIN_FEATURE_STEREO};
+use clap_sys::process::{clap_process, clap_process_status, CLAP_PROCESS_CONTINUE};
+use clap_sys::stream::{clap_istream, clap_ostream};
+use clap_sys::version::{clap_version_is_compatible, CLAP_VERSION};
+use decay_wasm::Preset;
+use engine::Engine;
+use params::{Shared, Target};
+use std::cell::UnsafeCell;
+use std::ffi::{c_char, c_void, CStr};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const PLUGIN_ID: &CStr = c"dev.decay.chain";
+
+struct Features([*const c_char; 3]);
+// SAFETY: the pointers are to 'static C string constants.
+unsafe impl Sync for Features {}
+
+static FEATURES: Features = Features([
+    CLAP_PLUGIN_FEATURE_AUDIO_EFFECT.as_ptr(),
+    CLAP_PLUGIN_FEATURE_STEREO.as_ptr(),
+    ptr::null(),
+]);
+
+static DESCRIPTOR: clap_plugin_descriptor = clap_plugin_descriptor {
+    clap_version: CLAP_VERSION,
+    id: PLUGIN_ID.as_ptr(),
+    name: c"Decay".as_ptr(),
+    vendor: c"decay".as_ptr(),
+    url: c"".as_ptr(),
+    manual_url: c"".as_ptr(),
+    support_url: c"".as_ptr(),
+    version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+    description: c"The decay voice effect chain".as_ptr(),
+    features: FEATURES.0.as_ptr(),
+};
+
+struct Plugin {
+    raw: clap_plugin,
+    host: *const clap_host,
+    host_params: *const clap_host_params,
+    host_state: *const clap_host_state,
+    host_preset_load: *const clap_host_preset_load,
+    shared: Shared,
+    /// Only touched by `activate`/`deactivate` on the main thread and by
+    /// the audio thread in between, which CLAP guarantees never overlap.
+    engine: UnsafeCell<Option<Engine>>,
+    /// Latency reported at activation; a different value while running
+    /// asks the host for a restart.
+    reported_latency: u32,
+    restart_requested: AtomicBool,
+}
+
+impl Plugin {
+    /// # Safety
+    /// `plugin` must be a pointer handed out by [`factory_create_plugin`] and not
+    /// yet destroyed.
+    unsafe fn from_raw<'a>(plugin: *const clap_plugin) -> &'a Plugin {
+        &*((*plugin).plugin_data as *const Plugin)
+    }
+
+    /// # Safety
+    /// Only on the audio thread, or on the main thread while inactive.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn engine(&self) -> &mut Option<Engine> {
+        &mut *self.engine.get()
+    }
+
+    /// # Safety
+    /// `T` must be the extension struct the host registers under `id`.
+    unsafe fn host_extension<T>(host: *const clap_host, id: &CStr) -> *const T {
+        match (*host).get_extension {
+            Some(get) => get(host, id.as_ptr()) as *const T,
+            None => ptr::null(),
+        }
+    }
+
+    /// Applies a parameter event to the shared values and, if active, the
+    /// engine. Events for other spaces or types are skipped.
+    ///
+    /// # Safety
+    /// `event` must point to a valid event; audio thread if active.
+    unsafe fn apply_event(&self, event: *const clap_event_header) {
+        if (*event).space_id != CLAP_CORE_EVENT_SPACE_ID || (*event).type_ != CLAP_EVENT_PARAM_VALUE
+        {
+            return;
+        }
+        let event = &*(event as *const clap_event_param_value);
+        let Some(index) = self.shared.index_of(event.param_id) else {
+            return;
+        };
+        self.shared.set(index, event.value);
+        if let Some(engine) = self.engine() {
+            engine.apply(&self.shared, index);
+        }
+    }
+
+    /// Requests a restart once if the chain's latency has moved away from
+    /// what the host was told.
+    unsafe fn check_latency(&self) {
+        let latency = self.shared.latency.load(Ordering::Relaxed);
+        if latency != self.reported_latency && !self.restart_requested.swap(true, Ordering::Relaxed)
+        {
+            if let Some(request_restart) = (*self.host).request_restart {
+                request_restart(self.host);
+            }
+        }
+    }
+
+    /// Main thread: after a load, tells the host the values changed.
+    unsafe fn values_replaced(&self) {
+        if !self.host_params.is_null() {
+            if let Some(rescan) = (*self.host_params).rescan {
+                rescan(self.host, CLAP_PARAM_RESCAN_VALUES);
+            }
+            // If active but not processing, a flush lets the engine resync.
+            if let Some(request_flush) = (*self.host_params).request_flush {
+                request_flush(self.host);
+            }
+        }
+    }
+
+    fn load_json(&self, bytes: &[u8]) -> Result<(), String> {
+        let json = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+        let preset = Preset::from_json(json).map_err(|e| e.to_string())?;
+        self.shared.load_preset(&preset)
+    }
+}
+
+/// Copies `text` into a fixed-size C string buffer, truncating if needed.
+fn write_c_string(buffer: &mut [c_char], text: &str) {
+    let Some(room) = buffer.len().checked_sub(1) else {
+        return;
+    };
+    let bytes = &text.as_bytes()[..text.len().min(room)];
+    for (dst, &src) in buffer.iter_mut().zip(bytes) {
+        *dst = src as c_char;
+    }
+    buffer[bytes.len()] = 0;
+}
+
+// --- Plugin -----------------------------------------------------------------
+
+unsafe extern "C" fn plugin_init(_plugin: *const clap_plugin) -> bool {
+    true
+}
+
+unsafe extern "C" fn plugin_destroy(plugin: *const clap_plugin) {
+    drop(Box::from_raw((*plugin).plugin_data as *mut Plugin));
+}
+
+unsafe extern "C" fn plugin_activate(
+    plugin: *const clap_plugin,
+    sample_rate: f64,
+    _min_frames: u32,
+    max_frames: u32,
+) -> bool {
+    let plugin = (*plugin).plugin_data as *mut Plugin;
+    let engine = Engine::new(sample_rate as f32, max_frames as usize, &(*plugin).shared);
+    (*plugin).shared.take_dirty();
+    (*plugin).reported_latency = engine.latency();
+    (*plugin).restart_requested.store(false, Ordering::Relaxed);
+    *(*plugin).engine() = Some(engine);
+    true
+}
+
+unsafe extern "C" fn plugin_deactivate(plugin: *const clap_plugin) {
+    *Plugin::from_raw(plugin).engine() = None;
+}
+
+unsafe extern "C" fn plugin_start_processing(_plugin: *const clap_plugin) -> bool {
+    true
+}
+
+unsafe extern "C" fn plugin_stop_processing(_plugin: *const clap_plugin) {}
+
+unsafe extern "C" fn plugin_reset(plugin: *const clap_plugin) {
+    if let Some(engine) = Plugin::from_raw(plugin).engine() {
+        engine.reset();
+    }
+}
+
+/// Channel `index` of `buffer`, or its last channel for mono buses.
+unsafe fn channel(buffer: &clap_audio_buffer, index: u32) -> *mut f32 {
+    *buffer
+        .data32
+        .add(index.min(buffer.channel_count - 1) as usize)
+}
+
+unsafe extern "C" fn plugin_process(
+    plugin: *const clap_plugin,
+    process: *const clap_process,
+) -> clap_process_status {
+    let plugin = Plugin::from_raw(plugin);
+    let process = &*process;
+    let Some(engine) = plugin.engine() else {
+        return CLAP_PROCESS_CONTINUE;
+    };
+    if plugin.shared.take_dirty() {
+        engine.sync(&plugin.shared);
+    }
+
+    let frames = (process.frames_count as usize).min(engine.max_frames());
+    let (left, right) = engine.buffers(frames);
+    let input = (process.audio_inputs_count > 0)
+        .then(|| &*process.audio_inputs)
+        .filter(|b| b.channel_count > 0 && !b.data32.is_null());
+    match input {
+        Some(input) => {
+            // Copies rather than slices: hosts may process in place.
+            ptr::copy(channel(input, 0), left.as_mut_ptr(), frames);
+            ptr::copy(channel(input, 1), right.as_mut_ptr(), frames);
+        }
+        None => {
+            left.fill(0.0);
+            right.fill(0.0);
+        }
+    }
+
+    // Split the block at each parameter change for sample accuracy.
+    let events = process.in_events;
+    let count = (*events).size.map_or(0, |size| size(events));
+    let mut position = 0;
+    for i in 0..count {
+        let Some(event) = (*events).get.map(|get| get(events, i)) else {
+            break;
+        };
+        if event.is_null() {
+            continue;
+        }
+        let time = ((*event).time as usize).clamp(position, frames);
+        if time > position {
+            engine.process(position..time);
+            position = time;
+        }
+        plugin.apply_event(event);
+    }
+    engine.process(position..frames);
+    plugin.check_latency();
+
+    if process.audio_outputs_count > 0 {
+        let output = &*process.audio_outputs;
+        if output.channel_count > 0 && !output.data32.is_null() {
+            let (left, right) = engine.buffers(frames);
+            ptr::copy(left.as_ptr(), channel(output, 0), frames);
+            if output.channel_count > 1 {
+                ptr::copy(right.as_ptr(), channel(output, 1), frames);
+            }
+        }
+    }
+    CLAP_PROCESS_CONTINUE
+}
+
+unsafe extern "C" fn plugin_get_extension(
+    _plugin: *const clap_plugin,
+    id: *const c_char,
+) -> *const c_void {
+    let id = CStr::from_ptr(id);
+    if id == CLAP_EXT_PARAMS {
+        &PARAMS as *const _ as *const c_void
+    } else if id == CLAP_EXT_AUDIO_PORTS {
+        &AUDIO_PORTS as *const _ as *const c_void
+    } else if id == CLAP_EXT_LATENCY {
+        &LATENCY as *const _ as *const c_void
+    } else if id == CLAP_EXT_STATE {
+        &STATE as *const _ as *const c_void
+    } else if id == CLAP_EXT_PRESET_LOAD || id == CLAP_EXT_PRESET_LOAD_COMPAT {
+        &PRESET_LOAD as *const _ as *const c_void
+    } else {
+        ptr::null()
+    }
+}
+
+unsafe extern "C" fn plugin_on_main_thread(_plugin: *const clap_plugin) {}
+
+// --- Params -----------------------------------------------------------------
+
+static PARAMS: clap_plugin_params = clap_plugin_params {
+    count: Some(params_count),
+    get_info: Some(params_get_info),
+    get_value: Some(params_get_value),
+    value_to_text: Some(params_value_to_text),
+    text_to_value: Some(params_text_to_value),
+    flush: Some(params_flush),
+};
+
+unsafe extern "C" fn params_count(plugin: *const clap_plugin) -> u32 {
+    Plugin::from_raw(plugin).shared.params.len() as u32
+}
+
+unsafe extern "C" fn params_get_info(
+    plugin: *const clap_plugin,
+    index: u32,
+    info: *mut clap_param_info,
+) -> bool {
+    let shared = &Plugin::from_raw(plugin).shared;
+    let Some(desc) = shared.params.get(index as usize) else {
+        return false;
+    };
+    let info = &mut *info;
+    let (min, max, default) = desc.range();
+    info.id = desc.id;
+    info.flags = CLAP_PARAM_IS_AUTOMATABLE;
+    if desc.target == Target::Enabled {
+        info.flags |= CLAP_PARAM_IS_STEPPED;
+    }
+    info.cookie = ptr::null_mut();
+    write_c_string(&mut info.name, &desc.name);
+    write_c_string(&mut info.module, decay_wasm::effects::KINDS[desc.kind]);
+    info.min_value = min as f64;
+    info.max_value = max as f64;
+    info.default_value = default as f64;
+    true
+}
+
+unsafe extern "C" fn params_get_value(
+    plugin: *const clap_plugin,
+    id: clap_id,
+    value: *mut f64,
+) -> bool {
+    let shared = &Plugin::from_raw(plugin).shared;
+    match shared.index_of(id) {
+        Some(index) => {
+            *value = shared.value(index) as f64;
+            true
+        }
+        None => false,
+    }
+}
+
+unsafe extern "C" fn params_value_to_text(
+    plugin: *const clap_plugin,
+    id: clap_id,
+    value: f64,
+    buffer: *mut c_char,
+    capacity: u32,
+) -> bool {
+    let shared = &Plugin::from_raw(plugin).shared;
+    let Some(index) = shared.index_of(id) else {
+        return false;
+    };
+    let text = match shared.params[index].target {
+        Target::Enabled if value >= 0.5 => "on".to_string(),
+        Target::Enabled => "off".to_string(),
+        Target::Param(_) => format!("{value:.2}"),
+    };
+    write_c_string(
+        std::slice::from_raw_parts_mut(buffer, capacity as usize),
+        &text,
+    );
+    true
+}
+
+unsafe extern "C" fn params_text_to_value(
+    plugin: *const clap_plugin,
+    id: clap_id,
+    text: *const c_char,
+    value: *mut f64,
+) -> bool {
+    let shared = &Plugin::from_raw(plugin).shared;
+    let Some(index) = shared.index_of(id) else {
+        return false;
+    };
+    let text = CStr::from_ptr(text).to_string_lossy();
+    let parsed = match (shared.params[index].target, text.trim()) {
+        (Target::Enabled, "on") => Some(1.0),
+        (Target::Enabled, "off") => Some(0.0),
+        (_, text) => text.parse().ok(),
+    };
+    parsed.map(|v| *value = v).is_some()
+}
+
+unsafe extern "C" fn params_flush(
+    plugin: *const clap_plugin,
+    events: *const clap_input_events,
+    _out: *const clap_output_events,
+) {
+    let plugin = Plugin::from_raw(plugin);
+    if let Some(engine) = plugin.engine() {
+        if plugin.shared.take_dirty() {
+            engine.sync(&plugin.shared);
+        }
+    }
+    let count = (*events).size.map_or(0, |size| size(events));
+    for i in 0..count {
+        if let Some(event) = (*events).get.map(|get| get(events, i)) {
+            if !event.is_null() {
+                plugin.apply_event(event);
+            }
+        }
+    }
+}
+
+// --- Audio ports ------------------------------------------------------------
+
+static AUDIO_PORTS: clap_plugin_audio_ports = clap_plugin_audio_ports {
+    count: Some(audio_ports_count),
+    get: Some(audio_ports_get),
+};
+
+unsafe extern "C" fn audio_ports_count(_plugin: *const clap_plugin, _is_input: bool) -> u32 {
+    1
+}
+
+unsafe extern "C" fn audio_ports_get(
+    _plugin: *const clap_plugin,
+    index: u32,
+    is_input: bool,
+    info: *mut clap_audio_port_info,
+) -> bool {
+    if index != 0 {
+        return false;
+    }
+    let info = &mut *info;
+    info.id = 0;
+    write_c_string(&mut info.name, if is_input { "Input" } else { "Output" });
+    info.flags = CLAP_AUDIO_PORT_IS_MAIN;
+    info.channel_count = 2;
+    info.port_type = CLAP_PORT_STEREO.as_ptr();
+    info.in_place_pair = CLAP_INVALID_ID;
+    true
+}
+
+// --- Latency ----------------------------------------------------------------
+
+static LATENCY: clap_plugin_latency = clap_plugin_latency {
+    get: Some(latency_get),
+};
+
+unsafe extern "C" fn latency_get(plugin: *const clap_plugin) -> u32 {
+    Plugin::from_raw(plugin).reported_latency
+}
+
+// --- State ------------------------------------------------------------------
+
+static STATE: clap_plugin_state = clap_plugin_state {
+    save: Some(state_save),
+    load: Some(state_load),
+};
+
+/// Saved state larger than this is rejected rather than buffered.
+const MAX_STATE_BYTES: usize = 1 << 20;
+
+unsafe extern "C" fn state_save(plugin: *const clap_plugin, stream: *const clap_ostream) -> bool {
+    let json = Plugin::from_raw(plugin).shared.to_preset().to_json();
+    let Some(write) = (*stream).write else {
+        return false;
+    };
+    let mut bytes = json.as_bytes();
+    while !bytes.is_empty() {
+        let written = write(stream, bytes.as_ptr() as *const c_void, bytes.len() as u64);
+        if written <= 0 {
+            return false;
+        }
+        bytes = &bytes[(written as usize).min(bytes.len())..];
+    }
+    true
+}
+
+unsafe extern "C" fn state_load(plugin: *const clap_plugin, stream: *const clap_istream) -> bool {
+    let plugin = Plugin::from_raw(plugin);
+    let Some(read) = (*stream).read else {
+        return false;
+    };
+    let mut bytes = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let count = read(
+            stream,
+            chunk.as_mut_ptr() as *mut c_void,
+            chunk.len() as u64,
+        );
+        if count < 0 || bytes.len() > MAX_STATE_BYTES {
+            return false;
+        }
+        if count == 0 {
+            break;
+        }
+        bytes.extend_from_slice(&chunk[..count as usize]);
+    }
+    let loaded = plugin.load_json(&bytes).is_ok();
+    if loaded {
+        plugin.values_replaced();
+    }
+    loaded
+}
+
+// --- Preset load ------------------------------------------------------------
+
+static PRESET_LOAD: clap_plugin_preset_load = clap_plugin_preset_load {
+    from_location: Some(preset_load_from_location),
+};
+
+unsafe extern "C" fn preset_load_from_location(
+    plugin: *const clap_plugin,
+    location_kind: clap_preset_discovery_location_kind,
+    location: *const c_char,
+    load_key: *const c_char,
+) -> bool {
+    let plugin = Plugin::from_raw(plugin);
+    let result = if location_kind != CLAP_PRESET_DISCOVERY_LOCATION_FILE || location.is_null() {
+        Err("only preset files are supported".to_string())
+    } else {
+        let path = CStr::from_ptr(location).to_string_lossy().into_owned();
+        std::fs::read(&path)
+            .map_err(|e| format!("{path}: {e}"))
+            .and_then(|bytes| plugin.load_json(&bytes))
+    };
+
+    let host_ext = plugin.host_preset_load;
+    match result {
+        Ok(()) => {
+            plugin.values_replaced();
+            // Unlike restoring state, a preset load is an edit to the project.
+            if let Some(mark_dirty) = plugin.host_state.as_ref().and_then(|ext| ext.mark_dirty) {
+                mark_dirty(plugin.host);
+            }
+            if let Some(loaded) = host_ext.as_ref().and_then(|ext| ext.loaded) {
+                loaded(plugin.host, location_kind, location, load_key);
+            }
+            true
+        }
+        Err(message) => {
+            if let Some(on_error) = host_ext.as_ref().and_then(|ext| ext.on_error) {
+                let message = std::ffi::CString::new(message).unwrap_or_default();
+                on_error(
+                    plugin.host,
+                    location_kind,
+                    location,
+                    load_key,
+                    0,
+                    message.as_ptr(),
+                );
+            }
+            false
+        }
+    }
+}
+
+// --- Factory and entry point ------------------------------------------------
+
+static FACTORY: clap_plugin_factory = clap_plugin_factory {
+    get_plugin_count: Some(factory_get_plugin_count),
+    get_plugin_descriptor: Some(factory_get_plugin_descriptor),
+    create_plugin: Some(factory_create_plugin),
+};
+
+unsafe extern "C" fn factory_get_plugin_count(_factory: *const clap_plugin_factory) -> u32 {
+    1
+}
+
+unsafe extern "C" fn factory_get_plugin_descriptor(
+    _factory: *const clap_plugin_factory,
+    index: u32,
+) -> *const clap_plugin_descriptor {
+    if index == 0 {
+        &DESCRIPTOR
+    } else {
+        ptr::null()
+    }
+}
+
+unsafe extern "C" fn factory_create_plugin(
+    _factory: *const clap_plugin_factory,
+    host: *const clap_host,
+    plugin_id: *const c_char,
+) -> *const clap_plugin {
+    if host.is_null()
+        || !clap_version_is_compatible((*host).clap_version)
+        || CStr::from_ptr(plugin_id) != PLUGIN_ID
+    {
+        return ptr::null();
+    }
+    let plugin = Box::into_raw(Box::new(Plugin {
+        raw: clap_plugin {
+            desc: &DESCRIPTOR,
+            plugin_data: ptr::null_mut(),
+            init: Some(plugin_init),
+            destroy: Some(plugin_destroy),
+            activate: Some(plugin_activate),
+            deactivate: Some(plugin_deactivate),
+            start_processing: Some(plugin_start_processing),
+            stop_processing: Some(plugin_stop_processing),
+            reset: Some(plugin_reset),
+            process: Some(plugin_process),
+            get_extension: Some(plugin_get_extension),
+            on_main_thread: Some(plugin_on_main_thread),
+        },
+        host,
+        host_params: Plugin::host_extension(host, CLAP_EXT_PARAMS),
+        host_state: Plugin::host_extension(host, CLAP_EXT_STATE),
+        host_preset_load: Plugin::host_extension(host, CLAP_EXT_PRESET_LOAD),
+        shared: Shared::new(),
+        engine: UnsafeCell::new(None),
+        reported_latency: 0,
+        restart_requested: AtomicBool::new(false),
+    }));
+    (*plugin).raw.plugin_data = plugin as *mut c_void;
+    &(*plugin).raw
+}
+
+unsafe extern "C" fn entry_init(_plugin_path: *const c_char) -> bool {
+    true
+}
+
+unsafe extern "C" fn entry_deinit() {}
+
+unsafe extern "C" fn entry_get_factory(factory_id: *const c_char) -> *const c_void {
+    if CStr::from_ptr(factory_id) == CLAP_PLUGIN_FACTORY_ID {
+        &FACTORY as *const _ as *const c_void
+    } else {
+        ptr::null()
+    }
+}
+
+#[allow(non_upper_case_globals)]
+#[no_mangle]
+pub static clap_entry: clap_plugin_entry = clap_plugin_entry {
+    clap_version: CLAP_VERSION,
+    init: Some(entry_init),
+    deinit: Some(entry_deinit),
+    get_factory: Some(entry_get_factory),
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use decay_wasm::effects::KINDS;
+
+    unsafe extern "C" fn no_extension(
+        _host: *const clap_host,
+        _id: *const c_char,
+    ) -> *const c_void {
+        ptr::null()
+    }
+
+    fn host() -> clap_host {
+        clap_host {
+            clap_version: CLAP_VERSION,
+            host_data: ptr::null_mut(),
+            name: c"test".as_ptr(),
+            vendor: c"".as_ptr(),
+            url: c"".as_ptr(),
+            version: c"".as_ptr(),
+            get_extension: Some(no_extension),
+            request_restart: None,
+            request_process: None,
+            request_callback: None,
+        }
+    }
+
+    unsafe extern "C" fn events_size(list: *const clap_input_events) -> u32 {
+        (*((*list).ctx as *const Vec<clap_event_param_value>)).len() as u32
+    }
+
+    unsafe extern "C" fn events_get(
+        list: *const clap_input_events,
+        index: u32,
+    ) -> *const clap_event_header {
+        let events = &*((*list).ctx as *const Vec<clap_event_param_value>);
+        &events[index as usize].header
+    }
+
+    unsafe extern "C" fn stream_write(
+        stream: *const clap_ostream,
+        buffer: *const c_void,
+        size: u64,
+    ) -> i64 {
+        let bytes = std::slice::from_raw_parts(buffer as *const u8, size as usize);
+        (*((*stream).ctx as *mut Vec<u8>)).extend_from_slice(bytes);
+        size as i64
+    }
+
+    unsafe extern "C" fn stream_read(
+        stream: *const clap_istream,
+        buffer: *mut c_void,
+        size: u64,
+    ) -> i64 {
+        let source = &mut *((*stream).ctx as *mut &[u8]);
+        let count = source.len().min(size as usize);
+        ptr::copy_nonoverlapping(source.as_ptr(), buffer as *mut u8, count);
+        *source = &source[count..];
+        count as i64
+    }
+
+    unsafe fn create(host: &clap_host) -> *const clap_plugin {
+        let factory =
+            entry_get_factory(CLAP_PLUGIN_FACTORY_ID.as_ptr()) as *const clap_plugin_factory;
+        let plugin = (*factory).create_plugin.unwrap()(factory, host, PLUGIN_ID.as_ptr());
+        assert!(!plugin.is_null());
+        assert!((*plugin).init.unwrap()(plugin));
+        plugin
+    }
+
+    #[test]
+    fn test_host_round_trip() {
+        let host = host();
+        unsafe {
+            let plugin = create(&host);
+            assert!((*plugin).activate.unwrap()(plugin, 48_000.0, 1, 256));
+
+            // Switch ring_mod on halfway through a block.
+            let ring_mod = KINDS.iter().position(|k| *k == "ring_mod").unwrap() as u32;
+            let enable = |time: u32, value: f64| clap_event_param_value {
+                header: clap_event_header {
+                    size: std::mem::size_of::<clap_event_param_value>() as u32,
+                    time,
+                    space_id: CLAP_CORE_EVENT_SPACE_ID,
+                    type_: CLAP_EVENT_PARAM_VALUE,
+                    flags: 0,
+                },
+                param_id: ring_mod * params::IDS_PER_KIND,
+                cookie: ptr::null_mut(),
+                note_id: -1,
+                port_index: -1,
+                channel: -1,
+                key: -1,
+                value,
+            };
+            let mut events = vec![enable(64, 1.0)];
+            let in_events = clap_input_events {
+                ctx: &events as *const _ as *mut c_void,
+                size: Some(events_size),
+                get: Some(events_get),
+            };
+
+            let mut input: Vec<f32> = (0..128).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+            let mut left = vec![0.0f32; 128];
+            let mut right = vec![0.0f32; 128];
+            let mut in_channels = [input.as_mut_ptr()];
+            let mut out_channels = [left.as_mut_ptr(), right.as_mut_ptr()];
+            let audio_in = clap_audio_buffer {
+                data32: in_channels.as_mut_ptr(),
+                data64: ptr::null_mut(),
+                channel_count: 1,
+                latency: 0,
+                constant_mask: 0,
+            };
+            let mut audio_out = clap_audio_buffer {
+                data32: out_channels.as_mut_ptr(),
+                data64: ptr::null_mut(),
+                channel_count: 2,
+                latency: 0,
+                constant_mask: 0,
+            };
+            let process = clap_process {
+                steady_time: 0,
+                frames_count: 128,
+                transport: ptr::null(),
+                audio_inputs: &audio_in,
+                audio_outputs: &mut audio_out,
+                audio_inputs_count: 1,
+                audio_outputs_count: 1,
+                in_events: &in_events,
+                out_events: ptr::null(),
+            };
+            (*plugin).process.unwrap()(plugin, &process);
+            assert_eq!(left[..64], input[..64]);
+            assert_ne!(left[64..], input[64..]);
+            assert_eq!(left, right);
+
+            // Off and on again: it starts over rather than carrying on from
+            // where it was switched off.
+            let first = left.clone();
+            events.clear();
+            events.extend([enable(0, 0.0), enable(64, 1.0)]);
+            (*plugin).process.unwrap()(plugin, &process);
+            assert_eq!(left[..64], input[..64]);
+            assert_eq!(left, first);
+
+            let state = (*plugin).get_extension.unwrap()(plugin, CLAP_EXT_STATE.as_ptr())
+                as *const clap_plugin_state;
+            let mut saved = Vec::<u8>::new();
+            let ostream = clap_ostream {
+                ctx: &mut saved as *mut _ as *mut c_void,
+                write: Some(stream_write),
+            };
+            assert!((*state).save.unwrap()(plugin, &ostream));
+            let preset = Preset::from_json(std::str::from_utf8(&saved).unwrap()).unwrap();
+            assert_eq!(preset.effects.len(), 1);
+            assert_eq!(preset.effects[0].kind, "ring_mod");
+            (*plugin).deactivate.unwrap()(plugin);
+            (*plugin).destroy.unwrap()(plugin);
+
+            let other = create(&host);
+            let mut source = &saved[..];
+            let istream = clap_istream {
+                ctx: &mut source as *mut _ as *mut c_void,
+                read: Some(stream_read),
+            };
+            assert!((*state).load.unwrap()(other, &istream));
+            let params = (*other).get_extension.unwrap()(other, CLAP_EXT_PARAMS.as_ptr())
+                as *const clap_plugin_params;
+            let mut value = 0.0;
+            let id = ring_mod * params::IDS_PER_KIND;
+            assert!((*params).get_value.unwrap()(other, id, &mut value));
+            assert_eq!(value, 1.0);
+            (*other).destroy.unwrap()(other);
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! The plugin's fixed parameter list and the values shared between the
+//! host's threads.
+//!
+//! CLAP wants one parameter list for the life of an instance, while a web
+//! preset is an ordered list of effects. The plugin therefore carries one
+//! instance of every registered effect: each gets an `enabled` toggle plus
+//! its own parameters, and a rank that decides where it sits in the chain.
+//! Loading a preset enables the effects it names, in its order; enabling one
+//! by hand appends it to the end.
+
+use decay_wasm::effects::{self, ParamInfo, KINDS};
+use decay_wasm::preset::{EffectPreset, Preset};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// Parameter ids are `kind * IDS_PER_KIND + slot`, slot 0 being the toggle
+/// and slot `1 + i` the effect's `i`th parameter. Hosts store automation
+/// by id, so kinds and effect parameters may only ever be appended.
+pub const IDS_PER_KIND: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Enabled,
+    Param(&'static ParamInfo),
+}
+
+#[derive(Debug, Clone)]
+pub struct ParamDesc {
+    pub id: u32,
+    pub kind: usize,
+    pub target: Target,
+    /// e.g. `Pitch Shift: semitones`.
+    pub name: String,
+}
+
+impl ParamDesc {
+    pub fn range(&self) -> (f32, f32, f32) {
+        match self.target {
+            Target::Enabled => (0.0, 1.0, 0.0),
+            Target::Param(info) => (info.min, info.max, info.default),
+        }
+    }
+}
+
+/// `pitch_shift` -> `Pitch Shift`.
+fn title(kind: &str) -> String {
+    kind.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or(String::new(), |first| {
+                first.to_ascii_uppercase().to_string() + chars.as_str()
+            })
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn layout() -> Vec<ParamDesc> {
+    let mut params = Vec::new();
+    for (kind_index, kind) in KINDS.iter().enumerate() {
+        let base = kind_index as u32 * IDS_PER_KIND;
+        let table = effects::create(kind, 48_000.0)
+            .expect("every listed kind can be created")
+            .params();
+        debug_assert!(table.len() < IDS_PER_KIND as usize);
+        params.push(ParamDesc {
+            id: base,
+            kind: kind_index,
+            target: Target::Enabled,
+            name: format!("{}: enabled", title(kind)),
+        });
+        for (i, info) in table.iter().enumerate() {
+            params.push(ParamDesc {
+                id: base + 1 + i as u32,
+                kind: kind_index,
+                target: Target::Param(info),
+                name: format!("{}: {}", title(kind), info.name),
+            });
+        }
+    }
+    params
+}
+
+/// Parameter values and chain order, readable from any thread.
+///
+/// The audio thread writes values as automation arrives; the main thread
+/// reads them for the host and replaces them wholesale when a preset or
+/// saved state is loaded, setting `dirty` so the audio thread resyncs.
+pub struct Shared {
+    pub params: Vec<ParamDesc>,
+    values: Vec<AtomicU32>,
+    ranks: Vec<AtomicU32>,
+    next_rank: AtomicU32,
+    dirty: AtomicBool,
+    /// Latency of the current chain in samples, kept by the audio thread.
+    pub latency: AtomicU32,
+}
+
+impl Default for Shared {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shared {
+    pub fn new() -> Self {
+        let params = layout();
+        let values = params
+            .iter()
+            .map(|p| AtomicU32::new(p.range().2.to_bits()))
+            .collect();
+        Self {
+            params,
+            values,
+            ranks: KINDS.iter().map(|_| AtomicU32::new(0)).collect(),
+            next_rank: AtomicU32::new(0),
+            dirty: AtomicBool::new(true),
+            latency: AtomicU32::new(0),
+        }
+    }
+
+    pub fn index_of(&self, id: u32) -> Option<usize> {
+        self.params.binary_search_by_key(&id, |p| p.id).ok()
+    }
+
+    pub fn value(&self, index: usize) -> f32 {
+        f32::from_bits(self.values[index].load(Ordering::Relaxed))
+    }
+
+    /// Clamps and stores a value, returning what was stored. Turning an
+    /// effect on moves it to the end of the chain.
+    pub fn set(&self, index: usize, value: f64) -> f32 {
+        let desc = &self.params[index];
+        let value = match desc.target {
+            Target::Enabled => {
+                let on = value >= 0.5;
+                if on && self.value(index) < 0.5 {
+                    let rank = self.next_rank.fetch_add(1, Ordering::Relaxed);
+                    self.ranks[desc.kind].store(rank, Ordering::Relaxed);
+                }
+                if on {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Target::Param(info) => info.clamp(value as f32),
+        };
+        self.values[index].store(value.to_bits(), Ordering::Relaxed);
+        value
+    }
+
+    fn toggle_index(&self, kind: usize) -> usize {
+        self.index_of(kind as u32 * IDS_PER_KIND)
+            .expect("every kind has a toggle")
+    }
+
+    pub fn is_enabled(&self, kind: usize) -> bool {
+        self.value(self.toggle_index(kind)) >= 0.5
+    }
+
+    /// Fills `order` with the enabled kinds, first in the chain first.
+    /// Doesn't allocate once `order` has room for every kind.
+    pub fn chain_order(&self, order: &mut Vec<usize>) {
+        order.clear();
+        order.extend((0..KINDS.len()).filter(|&kind| self.is_enabled(kind)));
+        order.sort_unstable_by_key(|&kind| self.ranks[kind].load(Ordering::Relaxed));
+    }
+
+    /// Returns whether a load happened since the last call.
+    pub fn take_dirty(&self) -> bool {
+        self.dirty.swap(false, Ordering::Acquire)
+    }
+
+    /// The enabled effects and their values, in the web app's format.
+    pub fn to_preset(&self) -> Preset {
+        let mut order = Vec::with_capacity(KINDS.len());
+        self.chain_order(&mut order);
+        let effects = order
+            .iter()
+            .map(|&kind| EffectPreset {
+                kind: KINDS[kind].to_string(),
+                params: self
+                    .params
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, p)| p.kind == kind)
+                    .filter_map(|(index, p)| match p.target {
+                        Target::Param(info) => Some((info.name.to_string(), self.value(index))),
+                        Target::Enabled => None,
+                    })
+                    .collect(),
+            })
+            .collect();
+        Preset {
+            name: String::new(),
+            effects,
+            midi: Vec::new(),
+            network: Vec::new(),
+            age: None,
+        }
+    }
+
+    /// Replaces every value with the ones in `preset`. Effects it doesn't
+    /// mention are switched off and go back to their defaults.
+    pub fn load_preset(&self, preset: &Preset) -> Result<(), String> {
+        // Same validation as the web app, so a preset fails in both or
+        // neither.
+        preset.build(48_000.0).map_err(|e| e.to_string())?;
+        let mut positions = vec![None; KINDS.len()];
+        for (position, entry) in preset.effects.iter().enumerate() {
+            let kind = KINDS
+                .iter()
+                .position(|k| *k == entry.kind)
+                .expect("validated above");
+            if positions[kind].replace(position).is_some() {
+                return Err(format!(
+                    "`{}` appears twice; the plugin runs each effect once",
+                    entry.kind
+                ));
+            }
+        }
+
+        for (index, desc) in self.params.iter().enumerate() {
+            let value = match (desc.target, positions[desc.kind]) {
+                (Target::Enabled, position) => f32::from(u8::from(position.is_some())),
+                (Target::Param(info), Some(position)) => preset.effects[position]
+                    .params
+                    .get(info.name)
+                    .map_or(info.default, |&v| info.clamp(v)),
+                (Target::Param(info), None) => info.default,
+            };
+            self.values[index].store(value.to_bits(), Ordering::Relaxed);
+        }
+        for (kind, position) in positions.iter().enumerate() {
+            let rank = position.unwrap_or(0) as u32;
+            self.ranks[kind].store(rank, Ordering::Relaxed);
+        }
+        self.next_rank
+            .store(preset.effects.len() as u32, Ordering::Relaxed);
+        self.dirty.store(true, Ordering::Release);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_covers_every_effect_parameter() {
+        let shared = Shared::new();
+        for (kind_index, kind) in KINDS.iter().enumerate() {
+            let table = effects::create(kind, 48_000.0).unwrap().params();
+            let mine: Vec<_> = shared
+                .params
+                .iter()
+                .filter(|p| p.kind == kind_index)
+                .collect();
+            assert_eq!(mine.len(), table.len() + 1, "{kind}");
+            assert_eq!(mine[0].target, Target::Enabled);
+        }
+        assert_eq!(shared.params[1].name, "Pitch Shift: semitones");
+        assert!(shared.params.windows(2).all(|w| w[0].id < w[1].id));
+    }
+
+    #[test]
+    fn test_presets_round_trip_with_order() {
+        let shared = Shared::new();
+        let json = r#"{"effects": [
+            {"kind": "reverb", "params": {"mix": 0.6}},
+            {"kind": "pitch_shift", "params": {"semitones": 7}}
+        ]}"#;
+        shared
+            .load_preset(&Preset::from_json(json).unwrap())
+            .unwrap();
+        assert!(shared.take_dirty());
+
+        let saved = shared.to_preset();
+        let kinds: Vec<_> = saved.effects.iter().map(|e| e.kind.as_str()).collect();
+        assert_eq!(kinds, ["reverb", "pitch_shift"]);
+        assert_eq!(saved.effects[0].params["mix"], 0.6);
+        assert_eq!(saved.effects[1].params["semitones"], 7.0);
+
+        // Switching another effect on appends it.
+        let ring_mod = KINDS.iter().position(|k| *k == "ring_mod").unwrap();
+        shared.set(shared.toggle_index(ring_mod), 1.0);
+        let kinds: Vec<_> = shared
+            .to_preset()
+            .effects
+            .into_iter()
+            .map(|e| e.kind)
+            .collect();
+        assert_eq!(kinds, ["reverb", "pitch_shift", "ring_mod"]);
+
+        let twice = r#"{"effects": [{"kind": "reverb"}, {"kind": "reverb"}]}"#;
+        assert!(shared
+            .load_preset(&Preset::from_json(twice).unwrap())
+            .is_err());
+    }
+}
@@ -0,0 +1,113 @@
+//! Reading WAV/FLAC into planar float buffers and writing WAV back out.
+
+use std::error::Error;
+use std::path::Path;
+
+/// Decoded audio, one `Vec` per channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Audio {
+    pub sample_rate: u32,
+    pub channels: Vec<Vec<f32>>,
+}
+
+impl Audio {
+    pub fn frames(&self) -> usize {
+        self.channels.first().map_or(0, Vec::len)
+    }
+}
+
+/// Output sample format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Bits {
+    #[value(name = "16")]
+    Pcm16,
+    #[value(name = "24")]
+    Pcm24,
+    #[value(name = "32f")]
+    Float32,
+}
+
+/// Reads a `.wav` or `.flac` file with the same decoder the browser uses.
+pub fn read(path: &Path) -> Result<Audio, Box<dyn Error>> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    if !matches!(extension.as_deref(), Some("wav" | "flac")) {
+        return Err(format!("{}: expected a .wav or .flac file", path.display()).into());
+    }
+    let sample = decay_wasm::samples::decode(&std::fs::read(path)?)
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(Audio {
+        sample_rate: sample.sample_rate as u32,
+        channels: sample.channels,
+    })
+}
+
+/// Writes `audio` as an interleaved WAV file.
+pub fn write_wav(path: &Path, audio: &Audio, bits: Bits) -> Result<(), Box<dyn Error>> {
+    let (bits_per_sample, sample_format) = match bits {
+        Bits::Pcm16 => (16, hound::SampleFormat::Int),
+        Bits::Pcm24 => (24, hound::SampleFormat::Int),
+        Bits::Float32 => (32, hound::SampleFormat::Float),
+    };
+    let spec = hound::WavSpec {
+        channels: audio.channels.len() as u16,
+        sample_rate: audio.sample_rate,
+        bits_per_sample,
+        sample_format,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    let full_scale = (1i32 << (bits_per_sample - 1)) as f32;
+    for frame in 0..audio.frames() {
+        for channel in &audio.channels {
+            let sample = channel[frame];
+            match bits {
+                Bits::Float32 => writer.write_sample(sample)?,
+                _ => {
+                    let value = (sample * full_scale).round();
+                    writer.write_sample(value.clamp(-full_scale, full_scale - 1.0) as i32)?
+                }
+            }
+        }
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wav_round_trip() {
+        let audio = Audio {
+            sample_rate: 44_100,
+            channels: vec![vec![0.0, 0.5, -0.5, 0.25], vec![1.0, -1.0, 0.125, 0.0]],
+        };
+        let dir = std::env::temp_dir();
+        for bits in [Bits::Pcm16, Bits::Pcm24, Bits::Float32] {
+            let path = dir.join(format!("decay-render-{}-{bits:?}.wav", std::process::id()));
+            write_wav(&path, &audio, bits).unwrap();
+            let read_back = read(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(read_back.sample_rate, 44_100);
+            assert_eq!(read_back.frames(), 4);
+            for (a, b) in audio
+                .channels
+                .iter()
+                .flatten()
+                .zip(read_back.channels.iter().flatten())
+            {
+                // Full scale +1.0 clips to the largest positive integer.
+                assert!((a - b).abs() < 1e-4, "{bits:?}: {a} vs {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_unknown_extension_is_rejected() {
+        assert!(read(Path::new("take.mp3")).is_err());
+    }
+}
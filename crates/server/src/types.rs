@@ -21,6 +21,11 @@ pub enum Message {
         from_id: usize,
         to_id: usize,
         offer: String,
+        /// When the offerer started the session, in milliseconds since the
+        /// Unix epoch. Both peers age the patch from it; older clients
+        /// leave it out.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        session_start: Option<f64>,
     },
     RTCAnswer {
         from_id: usize,
@@ -0,0 +1,144 @@
+//! Per-block cost of the audio path, as a share of the real-time budget.
+//!
+//! The worklet gets one 128-frame quantum at a time and at 48 kHz has
+//! 2.67 ms to produce it. Besides criterion's usual output every benchmark
+//! prints a line such as
+//!
+//! ```text
+//! budget: effect/reverb/128             1.84% of a 128-frame quantum
+//! ```
+//!
+//! normalised to one quantum, so the numbers for one peer and its effects
+//! can be added up to see what fits in the audio thread. Run with
+//! `cargo bench -p decay-wasm`; the numbers are native, and wasm in a
+//! browser is typically 1.5-2x slower.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use decay_wasm::effects::{self, KINDS};
+use decay_wasm::{AudioProcessor, Mixer};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const SAMPLE_RATE: f32 = 48_000.0;
+const QUANTUM: usize = 128;
+const WARM_UP: Duration = Duration::from_millis(500);
+
+fn budget() -> Duration {
+    Duration::from_secs_f64(QUANTUM as f64 / SAMPLE_RATE as f64)
+}
+
+fn fill_noise(buffer: &mut [f32], seed: &mut u32) {
+    for sample in buffer.iter_mut() {
+        *seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        *sample = (*seed >> 8) as f32 / (1 << 24) as f32 - 0.5;
+    }
+}
+
+/// Benchmarks `process`, which handles `frames` frames per call, and prints
+/// its median cost per 128-frame quantum against the budget.
+fn bench_budget(c: &mut Criterion, id: &str, frames: usize, mut process: impl FnMut()) {
+    let mut samples = Vec::new();
+    let began = Instant::now();
+    c.bench_function(id, |b| {
+        b.iter_custom(|iterations| {
+            let start = Instant::now();
+            for _ in 0..iterations {
+                process();
+            }
+            let elapsed = start.elapsed();
+            // Leave criterion's warm-up calls out of the median.
+            if began.elapsed() > WARM_UP {
+                samples.push(elapsed.as_secs_f64() / iterations as f64);
+            }
+            elapsed
+        })
+    });
+    if samples.is_empty() {
+        // Filtered out on the command line.
+        return;
+    }
+    samples.sort_by(f64::total_cmp);
+    let per_quantum = samples[samples.len() / 2] * QUANTUM as f64 / frames as f64;
+    println!(
+        "budget: {id:<32} {:>6.2}% of a {QUANTUM}-frame quantum",
+        100.0 * per_quantum / budget().as_secs_f64()
+    );
+}
+
+/// Each effect alone on a stereo pair, at the worklet's quantum and at the
+/// smaller and larger blocks other hosts use.
+fn effect_benches(c: &mut Criterion) {
+    for kind in KINDS {
+        for frames in [32, QUANTUM, 512] {
+            let mut effect = effects::create(kind, SAMPLE_RATE).unwrap();
+            effect.trigger("record");
+            let mut seed = 1;
+            let mut input = vec![0.0; frames];
+            fill_noise(&mut input, &mut seed);
+            let mut left = input.clone();
+            let mut right = input.clone();
+            bench_budget(c, &format!("effect/{kind}/{frames}"), frames, || {
+                left.copy_from_slice(&input);
+                right.copy_from_slice(&input);
+                effect.process(black_box(&mut left), black_box(&mut right));
+            });
+        }
+    }
+}
+
+/// The whole processor as the worklet calls it: the default chain, and a
+/// worst case with one of every effect.
+fn processor_benches(c: &mut Criterion) {
+    for (name, all) in [("default", false), ("every_effect", true)] {
+        let mut processor = AudioProcessor::new();
+        if all {
+            processor.clear_effects();
+            for kind in KINDS {
+                processor.add_effect(kind);
+            }
+        }
+        let mut seed = 1;
+        bench_budget(c, &format!("processor/{name}"), QUANTUM, || {
+            fill_noise(processor.input_mut(), &mut seed);
+            processor.process_audio(0, QUANTUM);
+        });
+    }
+}
+
+/// The mixer at growing peer counts, with a reverb on a send, either
+/// panned, placed binaurally or placed in a room with early reflections.
+fn mixer_benches(c: &mut Criterion) {
+    for placement in ["panned", "binaural", "room"] {
+        for peers in [1, 4, 8, 16] {
+            let mut mixer = Mixer::new();
+            let ids: Vec<String> = (0..peers).map(|i| format!("peer{i}")).collect();
+            mixer.add_send_effect(0, "reverb");
+            if placement == "room" {
+                mixer.set_room(8.0, 6.0, 3.0, 0.3);
+            }
+            for (i, id) in ids.iter().enumerate() {
+                mixer.add_peer(id);
+                mixer.set_peer_send(id, 0, 0.3);
+                if placement != "panned" {
+                    mixer.set_peer_position(id, i as f32 * 360.0 / peers as f32, 0.0, 2.0);
+                }
+            }
+            let mut seed = 1;
+            bench_budget(c, &format!("mixer/{placement}/{peers}"), QUANTUM, || {
+                for id in &ids {
+                    fill_noise(mixer.peer_input_mut(id).unwrap(), &mut seed);
+                }
+                mixer.process_audio(0, QUANTUM);
+            });
+        }
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default()
+        .warm_up_time(WARM_UP)
+        .measurement_time(Duration::from_secs(2));
+    targets = effect_benches, processor_benches, mixer_benches
+}
+criterion_main!(benches);
@@ -0,0 +1,175 @@
+//! Arbitrary peer, routing and placement changes interleaved with
+//! `process_audio` calls on one `Mixer`: nothing may panic and the output
+//! must stay finite.
+
+#![no_main]
+
+use decay_wasm::effects::{self, KINDS};
+use decay_wasm::Mixer;
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+
+/// A handful of ids so ops keep hitting peers that exist.
+const PEERS: &[&str] = &["a", "b", "c", "d"];
+
+fn peer(index: u8) -> &'static str {
+    PEERS[index as usize % PEERS.len()]
+}
+
+#[derive(Debug, Arbitrary)]
+enum Op {
+    Process {
+        offset: usize,
+        length: usize,
+    },
+    Block {
+        offset: u8,
+        length: u8,
+    },
+    AddPeer(u8),
+    RemovePeer(u8),
+    Input {
+        peer: u8,
+        seed: u32,
+        scale: f32,
+    },
+    Gain(u8, f32),
+    Pan(u8, f32),
+    Position {
+        peer: u8,
+        azimuth: f32,
+        elevation: f32,
+        distance: f32,
+    },
+    ClearPosition(u8),
+    RoomLevel(f32),
+    Room {
+        width: f32,
+        depth: f32,
+        height: f32,
+        absorption: f32,
+    },
+    Listener(f32, f32, f32),
+    ClearRoom,
+    Mute(u8, bool),
+    Solo(u8, bool),
+    /// Send indices run one past the last bus on purpose.
+    Send {
+        peer: u8,
+        send: u8,
+        level: f32,
+    },
+    SendLevel(u8, f32),
+    AddSendEffect {
+        send: u8,
+        kind: u8,
+    },
+    ClearSendEffects(u8),
+    /// `param` picks from every parameter name any effect has.
+    SendParam {
+        send: u8,
+        index: u8,
+        param: u16,
+        value: f32,
+    },
+    MasterGain(f32),
+    SetSampleRate(f32),
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    let names: Vec<&str> = KINDS
+        .iter()
+        .flat_map(|kind| effects::create(kind, 48_000.0).unwrap().params())
+        .map(|info| info.name)
+        .collect();
+    let mut mixer = Mixer::new();
+    for op in ops {
+        match op {
+            Op::Process { offset, length } => mixer.process_audio(offset, length),
+            Op::Block { offset, length } => mixer.process_audio(offset as usize, length as usize),
+            Op::AddPeer(p) => {
+                mixer.add_peer(peer(p));
+            }
+            Op::RemovePeer(p) => {
+                mixer.remove_peer(peer(p));
+            }
+            Op::Input {
+                peer: p,
+                mut seed,
+                scale,
+            } => {
+                if let Some(input) = mixer.peer_input_mut(peer(p)) {
+                    for sample in input.iter_mut() {
+                        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                        *sample = ((seed >> 8) as f32 / (1 << 24) as f32 - 0.5) * scale;
+                    }
+                }
+            }
+            Op::Gain(p, gain) => {
+                mixer.set_peer_gain(peer(p), gain);
+            }
+            Op::Pan(p, pan) => {
+                mixer.set_peer_pan(peer(p), pan);
+            }
+            Op::Position {
+                peer: p,
+                azimuth,
+                elevation,
+                distance,
+            } => {
+                mixer.set_peer_position(peer(p), azimuth, elevation, distance);
+            }
+            Op::ClearPosition(p) => {
+                mixer.clear_peer_position(peer(p));
+            }
+            Op::RoomLevel(level) => mixer.set_room_level(level),
+            Op::Room {
+                width,
+                depth,
+                height,
+                absorption,
+            } => mixer.set_room(width, depth, height, absorption),
+            Op::Listener(x, y, z) => {
+                mixer.set_listener_position(x, y, z);
+            }
+            Op::ClearRoom => mixer.clear_room(),
+            Op::Mute(p, mute) => {
+                mixer.set_peer_mute(peer(p), mute);
+            }
+            Op::Solo(p, solo) => {
+                mixer.set_peer_solo(peer(p), solo);
+            }
+            Op::Send {
+                peer: p,
+                send,
+                level,
+            } => {
+                mixer.set_peer_send(peer(p), send as usize % 5, level);
+            }
+            Op::SendLevel(send, level) => {
+                mixer.set_send_level(send as usize % 5, level);
+            }
+            Op::AddSendEffect { send, kind } => {
+                let kind = KINDS.get(kind as usize).copied().unwrap_or("unknown");
+                mixer.add_send_effect(send as usize % 5, kind);
+            }
+            Op::ClearSendEffects(send) => {
+                mixer.clear_send_effects(send as usize % 5);
+            }
+            Op::SendParam {
+                send,
+                index,
+                param,
+                value,
+            } => {
+                let name = names[param as usize % names.len()];
+                mixer.set_send_effect_param(send as usize % 5, index as usize, name, value);
+            }
+            Op::MasterGain(gain) => mixer.set_master_gain(gain),
+            Op::SetSampleRate(rate) => mixer.set_sample_rate(rate),
+        }
+        let (left, right) = mixer.output();
+        assert!(left.iter().chain(right).all(|s| s.is_finite()));
+        assert!(mixer.output_level().is_finite());
+    }
+});
@@ -0,0 +1,43 @@
+//! Arbitrary preset JSON: parsing and building must fail cleanly, and any
+//! chain that does build must process without panicking or producing
+//! non-finite samples.
+
+#![no_main]
+
+use decay_wasm::Preset;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|json: &str| {
+    let Ok(preset) = Preset::from_json(json) else {
+        return;
+    };
+    // Cap the chain so huge arrays of effects don't just measure memory.
+    if preset.effects.len() > 32 {
+        return;
+    }
+    let Ok(mut chain) = preset.build(48_000.0) else {
+        return;
+    };
+
+    let mut left = [0.0f32; 128];
+    let mut right = [0.0f32; 128];
+    for block in 0..8 {
+        for (i, (l, r)) in left.iter_mut().zip(right.iter_mut()).enumerate() {
+            *l = if (block * 128 + i) % 97 == 0 {
+                1.0
+            } else {
+                0.0
+            };
+            *r = -*l;
+        }
+        chain.process(&mut left, &mut right);
+        // The processor scrubs once more after the chain; a NaN getting this
+        // far is a bug in an effect, so fail on it rather than scrub.
+        assert!(left.iter().chain(&right).all(|s| s.is_finite()));
+    }
+
+    // Whatever built must also survive a save and reload.
+    let saved = Preset::from_chain(&chain);
+    let reloaded = Preset::from_json(&saved.to_json()).expect("saved presets parse");
+    reloaded.build(48_000.0).expect("saved presets build");
+});
@@ -0,0 +1,184 @@
+//! Arbitrary sequences of worklet calls and chain edits on one
+//! `AudioProcessor`: nothing may panic and every processed block must come
+//! out finite.
+
+#![no_main]
+
+use decay_wasm::effects::KINDS;
+use decay_wasm::{AudioProcessor, Preset};
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+
+const EVENTS: &[&str] = &["record", "play", "overdub", "stop", "undo", "clear"];
+const METRICS: &[&str] = &["rtt", "jitter", "loss", "bitrate"];
+/// Cap the chain so long runs of `AddEffect` don't just measure memory.
+const MAX_EFFECTS: usize = 32;
+
+#[derive(Debug, Arbitrary)]
+enum Op {
+    /// Raw offsets and lengths, as a misbehaving worklet could pass them.
+    Process {
+        offset: usize,
+        length: usize,
+    },
+    /// A plausible sub-block.
+    Block {
+        offset: u8,
+        length: u8,
+    },
+    Input {
+        seed: u32,
+        scale: f32,
+    },
+    Enable(bool),
+    AddEffect(u8),
+    RemoveEffect(u8),
+    MoveEffect(u8, u8),
+    ClearEffects,
+    /// `param` indexes the effect's own table, so most writes hit a real
+    /// parameter; past the end it becomes `name`.
+    SetParam {
+        index: u8,
+        param: u8,
+        name: String,
+        value: f32,
+    },
+    Trigger {
+        index: u8,
+        event: u8,
+    },
+    SetSampleRate(f32),
+    LoadPreset(String),
+    MidiMessage(Vec<u8>),
+    MidiLearn {
+        index: u8,
+        param: u8,
+        name: String,
+    },
+    NetworkStats {
+        rtt_ms: f32,
+        jitter_ms: f32,
+        loss: f32,
+        bitrate_kbps: f32,
+    },
+    NetworkMap {
+        metric: u8,
+        from: f32,
+        to: f32,
+        index: u8,
+        param: u8,
+        name: String,
+    },
+    SessionStart(f64),
+    SessionTime(f64),
+    SetAge(f32),
+    LoadSample {
+        name: String,
+        bytes: Vec<u8>,
+    },
+}
+
+/// The name of parameter `param` of the effect at `index`, or `name` if
+/// there is no such parameter.
+fn param_name(processor: &AudioProcessor, index: usize, param: u8, name: String) -> String {
+    processor
+        .effect_kind(index)
+        .and_then(|kind| decay_wasm::effects::create(&kind, 48_000.0))
+        .and_then(|effect| effect.params().get(param as usize).map(|p| p.name))
+        .map_or(name, str::to_string)
+}
+
+fn fill(buffer: &mut [f32], mut seed: u32, scale: f32) {
+    for sample in buffer.iter_mut() {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        *sample = ((seed >> 8) as f32 / (1 << 24) as f32 - 0.5) * scale;
+    }
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    let mut processor = AudioProcessor::new();
+    for op in ops {
+        match op {
+            Op::Process { offset, length } => processor.process_audio(offset, length),
+            Op::Block { offset, length } => {
+                processor.process_audio(offset as usize, length as usize)
+            }
+            Op::Input { seed, scale } => fill(processor.input_mut(), seed, scale),
+            Op::Enable(enabled) => processor.enable_processing(enabled),
+            Op::AddEffect(kind) => {
+                if processor.effect_count() < MAX_EFFECTS {
+                    let kind = KINDS.get(kind as usize).copied().unwrap_or("unknown");
+                    processor.add_effect(kind);
+                }
+            }
+            Op::RemoveEffect(index) => {
+                processor.remove_effect(index as usize);
+            }
+            Op::MoveEffect(from, to) => {
+                processor.move_effect(from as usize, to as usize);
+            }
+            Op::ClearEffects => processor.clear_effects(),
+            Op::SetParam {
+                index,
+                param,
+                name,
+                value,
+            } => {
+                let index = index as usize;
+                let name = param_name(&processor, index, param, name);
+                processor.set_effect_param(index, &name, value);
+            }
+            Op::Trigger { index, event } => {
+                let event = EVENTS.get(event as usize).copied().unwrap_or("bogus");
+                processor.trigger_effect(index as usize, event);
+            }
+            Op::SetSampleRate(rate) => processor.set_sample_rate(rate),
+            Op::LoadPreset(json) => {
+                let too_long =
+                    Preset::from_json(&json).is_ok_and(|preset| preset.effects.len() > MAX_EFFECTS);
+                if !too_long {
+                    processor.load_preset(&json);
+                }
+            }
+            Op::MidiMessage(bytes) => {
+                processor.midi_message(&bytes);
+            }
+            Op::MidiLearn { index, param, name } => {
+                let index = index as usize;
+                let name = param_name(&processor, index, param, name);
+                processor.midi_learn(index, &name);
+            }
+            Op::NetworkStats {
+                rtt_ms,
+                jitter_ms,
+                loss,
+                bitrate_kbps,
+            } => {
+                processor.network_stats(rtt_ms, jitter_ms, loss, bitrate_kbps);
+            }
+            Op::NetworkMap {
+                metric,
+                from,
+                to,
+                index,
+                param,
+                name,
+            } => {
+                let metric = METRICS.get(metric as usize).copied().unwrap_or("bogus");
+                let index = index as usize;
+                let name = param_name(&processor, index, param, name);
+                processor.network_map(metric, from, to, index, &name);
+            }
+            Op::SessionStart(start_ms) => processor.set_session_start(start_ms),
+            Op::SessionTime(now_ms) => {
+                processor.session_time(now_ms);
+            }
+            Op::SetAge(age) => processor.set_age(age),
+            Op::LoadSample { name, bytes } => {
+                processor.load_sample(&name, &bytes);
+            }
+        }
+        let (left, right) = processor.output();
+        assert!(left.iter().chain(right).all(|s| s.is_finite()));
+    }
+});
@@ -0,0 +1,228 @@
+//! The "age" macro: a patch that decays over the course of a session.
+//!
+//! A preset may carry an `age` section describing where its effects drift
+//! to. Age runs from 0, the preset as written, to 1, the `decayed` values,
+//! over `duration_s` of session time along a [`Curve`]:
+//!
+//! ```json
+//! { "effects": [{ "kind": "transmission", "params": { "loss": 0.0 } }],
+//!   "age": { "duration_s": 1800, "curve": "exponential",
+//!            "decayed": [{ "kind": "transmission", "params": { "loss": 0.3 } }] } }
+//! ```
+//!
+//! `decayed` lists the same effects in the same order; parameters it leaves
+//! out don't age. Age depends only on the time since the session started,
+//! so two peers given the same start time hear the same evolution, however
+//! often or irregularly each one updates it.
+//!
+//! An aging parameter can still be moved by hand, by MIDI or by a network
+//! mapping. The move re-bases it: fresh and decayed shift by the same
+//! amount, so it keeps aging from where it was put and the saved preset
+//! keeps the edit.
+
+use crate::effects::{self, EffectChain, ParamInfo};
+use crate::midi::Curve;
+use crate::preset::{EffectPreset, Preset};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Age {
+    /// Session time until fully decayed.
+    pub duration_s: f32,
+    #[serde(default)]
+    pub curve: Curve,
+    pub decayed: Vec<EffectPreset>,
+}
+
+/// One parameter that ages.
+#[derive(Debug, Clone, PartialEq)]
+struct AgeTarget {
+    effect: usize,
+    info: ParamInfo,
+    fresh: f32,
+    decayed: f32,
+    /// What the parameter read after it was last aged; anything else means
+    /// it was moved since.
+    applied: f32,
+}
+
+impl AgeTarget {
+    /// Shifts fresh and decayed so the envelope passes through `current`,
+    /// the parameter's value now, at the age it was last applied.
+    fn rebase(&mut self, current: f32) {
+        if current != self.applied {
+            let offset = current - self.applied;
+            self.fresh = self.info.clamp(self.fresh + offset);
+            self.decayed = self.info.clamp(self.decayed + offset);
+            self.applied = current;
+        }
+    }
+}
+
+/// An [`Age`] resolved against a built chain.
+#[derive(Debug, Clone, Default)]
+pub struct AgeEnvelope {
+    targets: Vec<AgeTarget>,
+    duration_s: f32,
+    curve: Curve,
+    age: f32,
+}
+
+impl AgeEnvelope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolves `age` against `chain`, freshly built from the preset, so
+    /// parameters the preset leaves at their defaults age from those.
+    /// [`Preset::build`] has already checked that `age` matches the chain.
+    pub fn from_age(age: &Age, chain: &EffectChain) -> Self {
+        let mut targets = Vec::new();
+        for (index, entry) in age.decayed.iter().enumerate() {
+            let Some(effect) = chain.get(index) else {
+                continue;
+            };
+            for (name, &decayed) in &entry.params {
+                let (Some(&info), Some(fresh)) = (
+                    effects::find_param(effect.params(), name),
+                    effect.get_param(name),
+                ) else {
+                    continue;
+                };
+                targets.push(AgeTarget {
+                    effect: index,
+                    info,
+                    fresh,
+                    decayed: info.clamp(decayed),
+                    applied: fresh,
+                });
+            }
+        }
+        Self {
+            targets,
+            duration_s: age.duration_s,
+            curve: age.curve,
+            age: 0.0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    pub fn age(&self) -> f32 {
+        self.age
+    }
+
+    /// Age after `elapsed_s` seconds of session.
+    pub fn age_at(&self, elapsed_s: f64) -> f32 {
+        if self.duration_s <= 0.0 {
+            return 1.0;
+        }
+        let position = (elapsed_s / f64::from(self.duration_s)).clamp(0.0, 1.0);
+        self.curve.apply(position as f32)
+    }
+
+    /// Moves every aging parameter to `age`, from 0 (fresh) to 1 (decayed),
+    /// first re-basing any that were moved since the last call.
+    pub fn apply(&mut self, age: f32, chain: &mut EffectChain) {
+        self.age = age.clamp(0.0, 1.0);
+        for target in &mut self.targets {
+            let Some(effect) = chain.get_mut(target.effect) else {
+                continue;
+            };
+            let name = target.info.name;
+            if let Some(current) = effect.get_param(name) {
+                target.rebase(current);
+            }
+            effect.set_param(
+                name,
+                target.fresh + (target.decayed - target.fresh) * self.age,
+            );
+            target.applied = effect.get_param(name).unwrap_or(target.applied);
+        }
+    }
+
+    /// Puts the fresh values back into `preset`, captured from a chain that
+    /// may have aged, and adds the `age` section describing the rest.
+    pub fn save_into(&self, preset: &mut Preset) {
+        if self.targets.is_empty() {
+            return;
+        }
+        let mut decayed: Vec<EffectPreset> = preset
+            .effects
+            .iter()
+            .map(|entry| EffectPreset {
+                kind: entry.kind.clone(),
+                params: Default::default(),
+            })
+            .collect();
+        for target in &self.targets {
+            if let (Some(fresh), Some(aged)) = (
+                preset.effects.get_mut(target.effect),
+                decayed.get_mut(target.effect),
+            ) {
+                // Moved since the last aging: save it re-based.
+                let mut target = target.clone();
+                if let Some(&current) = fresh.params.get(target.info.name) {
+                    target.rebase(current);
+                }
+                fresh
+                    .params
+                    .insert(target.info.name.to_string(), target.fresh);
+                aged.params
+                    .insert(target.info.name.to_string(), target.decayed);
+            }
+        }
+        preset.age = Some(Age {
+            duration_s: self.duration_s,
+            curve: self.curve,
+            decayed,
+        });
+    }
+
+    /// Keeps targets on the same effects after `index` is removed from the
+    /// chain; targets on the removed effect are dropped.
+    pub fn effect_removed(&mut self, index: usize) {
+        self.targets.retain_mut(|t| {
+            effects::index_after_removal(t.effect, index)
+                .map(|i| t.effect = i)
+                .is_some()
+        });
+    }
+
+    /// Follows an [`EffectChain::move_effect`] from `from` to `to`.
+    pub fn effect_moved(&mut self, from: usize, to: usize) {
+        for target in &mut self.targets {
+            target.effect = effects::index_after_move(target.effect, from, to);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ages_with_session_time_and_saves_fresh_values() {
+        let json = r#"{
+            "effects": [{ "kind": "reverb", "params": { "mix": 0.2 } }],
+            "age": { "duration_s": 100, "decayed": [{ "kind": "reverb", "params": { "mix": 0.6 } }] }
+        }"#;
+        let preset = Preset::from_json(json).unwrap();
+        let mut chain = preset.build(48_000.0).unwrap();
+        let mut envelope = AgeEnvelope::from_age(preset.age.as_ref().unwrap(), &chain);
+        assert_eq!(envelope.age_at(-5.0), 0.0);
+        assert_eq!(envelope.age_at(50.0), 0.5);
+        assert_eq!(envelope.age_at(1000.0), 1.0);
+
+        envelope.apply(envelope.age_at(50.0), &mut chain);
+        let mix = chain.get(0).unwrap().get_param("mix").unwrap();
+        assert!((mix - 0.4).abs() < 1e-6);
+
+        let mut saved = Preset::from_chain(&chain);
+        envelope.save_into(&mut saved);
+        assert_eq!(saved.effects[0].params["mix"], 0.2);
+        assert_eq!(saved.age, preset.age);
+    }
+}
@@ -1,23 +1,54 @@
 // src/audio_processor.rs
+use crate::age::AgeEnvelope;
+use crate::dsp;
+use crate::effects::{self, EffectChain, PitchShift};
+use crate::midi::MidiMap;
+use crate::network::{NetworkMap, NetworkMapping, NetworkMetric, NetworkStats};
+use crate::preset::Preset;
+use crate::samples::SamplePool;
 use wasm_bindgen::prelude::*;
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
     fn log(s: &str);
 }
 
+// Imported JS functions panic when called natively, so tests log nowhere.
+#[cfg(not(target_arch = "wasm32"))]
+fn log(_s: &str) {}
+
 macro_rules! console_log {
     ($($t:tt)*) => (log(&format_args!($($t)*).to_string()))
 }
 
-const BUFFER_SIZE: usize = 128;
+pub(crate) const BUFFER_SIZE: usize = 128;
+pub(crate) const DEFAULT_SAMPLE_RATE: f32 = 48_000.0;
+/// Rates outside this range are ignored; effects size their buffers from
+/// the rate, so a garbage value would otherwise allocate without bound.
+pub(crate) const SAMPLE_RATES: std::ops::RangeInclusive<f32> = 8_000.0..=384_000.0;
 
 #[wasm_bindgen]
 pub struct AudioProcessor {
     input_buffer: Vec<f32>,
     output_buffer: Vec<f32>,
+    output_buffer_right: Vec<f32>,
     processing_enabled: bool,
+    sample_rate: f32,
+    chain: EffectChain,
+    samples: SamplePool,
+    midi: MidiMap,
+    network: NetworkMap,
+    age: AgeEnvelope,
+    /// When the session started, in milliseconds; drives `age`.
+    session_start_ms: Option<f64>,
+}
+
+impl Default for AudioProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
@@ -25,10 +56,21 @@ impl AudioProcessor {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
         console_log!("Creating new AudioProcessor");
+        let mut chain = EffectChain::new();
+        chain.push(Box::new(PitchShift::new(DEFAULT_SAMPLE_RATE)));
+
         Self {
             input_buffer: vec![0.0; BUFFER_SIZE],
             output_buffer: vec![0.0; BUFFER_SIZE],
+            output_buffer_right: vec![0.0; BUFFER_SIZE],
             processing_enabled: true,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            chain,
+            samples: SamplePool::new(DEFAULT_SAMPLE_RATE),
+            midi: MidiMap::new(),
+            network: NetworkMap::new(),
+            age: AgeEnvelope::new(),
+            session_start_ms: None,
         }
     }
 
@@ -42,30 +84,36 @@ impl AudioProcessor {
         self.output_buffer.as_mut_ptr()
     }
 
+    #[wasm_bindgen]
+    pub fn get_output_right_buffer_ptr(&mut self) -> *mut f32 {
+        self.output_buffer_right.as_mut_ptr()
+    }
+
     #[wasm_bindgen]
     pub fn process_audio(&mut self, offset: usize, length: usize) {
-        if !self.processing_enabled || offset + length > BUFFER_SIZE {
-            return;
-        }
+        // Both values come straight from JS, so the sum may overflow.
+        let end = match offset.checked_add(length) {
+            Some(end) if end <= BUFFER_SIZE => end,
+            _ => return,
+        };
+
+        let range = offset..end;
+        let input = &self.input_buffer[range.clone()];
+        let left = &mut self.output_buffer[range.clone()];
+        let right = &mut self.output_buffer_right[range];
+        left.copy_from_slice(input);
+        right.copy_from_slice(input);
+        dsp::scrub(left);
+        dsp::scrub(right);
 
-        // Process each sample individually for a simple pitch shift down one octave
-        for i in 0..length {
-            let input_index = (offset + i) / 2; // Read at half speed for pitch shift
-            if input_index < BUFFER_SIZE {
-                self.output_buffer[offset + i] = self.input_buffer[input_index];
-            } else {
-                self.output_buffer[offset + i] = 0.0; // Handle out of bounds
+        if self.processing_enabled {
+            self.chain.process(left, right);
+            // A NaN or Inf out of a feedback path would otherwise recirculate
+            // forever; silence it and start the chain from clean state.
+            if dsp::scrub(left) | dsp::scrub(right) {
+                self.chain.reset();
             }
         }
-
-        let output_level = self.output_buffer[offset..offset + length]
-            .iter()
-            .map(|&x| x.abs())
-            .fold(0.0_f32, f32::max);
-        console_log!(
-            "Post-processing level: {}, pitch shifted down one octave",
-            output_level
-        );
     }
 
     #[wasm_bindgen]
@@ -73,6 +121,298 @@ impl AudioProcessor {
         self.processing_enabled = enabled;
         console_log!("Processing enabled: {}", enabled);
     }
+
+    /// Rebuilds every effect for a new sample rate, keeping its parameters.
+    #[wasm_bindgen]
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if !SAMPLE_RATES.contains(&sample_rate) || sample_rate == self.sample_rate {
+            return;
+        }
+        self.sample_rate = sample_rate;
+        self.chain.rebuild(sample_rate);
+        self.samples.set_sample_rate(sample_rate);
+        console_log!("Sample rate set to {}", sample_rate);
+    }
+
+    /// Appends an effect by kind; returns `false` if the kind is unknown.
+    #[wasm_bindgen]
+    pub fn add_effect(&mut self, kind: &str) -> bool {
+        match effects::create(kind, self.sample_rate) {
+            Some(effect) => {
+                self.chain.push(effect);
+                true
+            }
+            None => {
+                console_log!("Unknown effect kind: {}", kind);
+                false
+            }
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn remove_effect(&mut self, index: usize) -> bool {
+        let removed = self.chain.remove(index).is_some();
+        if removed {
+            self.midi.effect_removed(index);
+            self.network.effect_removed(index);
+            self.age.effect_removed(index);
+        }
+        removed
+    }
+
+    #[wasm_bindgen]
+    pub fn move_effect(&mut self, from: usize, to: usize) -> bool {
+        let moved = self.chain.move_effect(from, to);
+        if moved {
+            self.midi.effect_moved(from, to);
+            self.network.effect_moved(from, to);
+            self.age.effect_moved(from, to);
+        }
+        moved
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_effects(&mut self) {
+        self.chain.clear();
+        self.midi.set_mappings(Vec::new());
+        self.network.set_mappings(Vec::new());
+        self.age = AgeEnvelope::new();
+    }
+
+    #[wasm_bindgen]
+    pub fn effect_count(&self) -> usize {
+        self.chain.len()
+    }
+
+    #[wasm_bindgen]
+    pub fn effect_kind(&self, index: usize) -> Option<String> {
+        self.chain.get(index).map(|e| e.kind().to_string())
+    }
+
+    #[wasm_bindgen]
+    pub fn set_effect_param(&mut self, index: usize, name: &str, value: f32) -> bool {
+        self.chain
+            .get_mut(index)
+            .is_some_and(|e| e.set_param(name, value))
+    }
+
+    #[wasm_bindgen]
+    pub fn get_effect_param(&self, index: usize, name: &str) -> Option<f32> {
+        self.chain.get(index).and_then(|e| e.get_param(name))
+    }
+
+    /// Sends a one-shot event (e.g. a looper's `record`) to an effect.
+    #[wasm_bindgen]
+    pub fn trigger_effect(&mut self, index: usize, event: &str) -> bool {
+        self.chain.get_mut(index).is_some_and(|e| e.trigger(event))
+    }
+
+    /// Total delay of the effect chain in samples.
+    #[wasm_bindgen]
+    pub fn latency(&self) -> usize {
+        self.chain.latency()
+    }
+
+    /// Replaces the chain with the one described by a preset JSON string.
+    /// On error the current chain is left untouched.
+    #[wasm_bindgen]
+    pub fn load_preset(&mut self, json: &str) -> bool {
+        let built = Preset::from_json(json).and_then(|p| Ok((p.build(self.sample_rate)?, p)));
+        match built {
+            Ok((chain, preset)) => {
+                self.chain = chain;
+                self.midi.set_mappings(preset.midi);
+                self.network.set_mappings(preset.network);
+                self.age = match &preset.age {
+                    Some(age) => AgeEnvelope::from_age(age, &self.chain),
+                    None => AgeEnvelope::new(),
+                };
+                true
+            }
+            Err(error) => {
+                console_log!("Could not load preset: {}", error);
+                false
+            }
+        }
+    }
+
+    /// The current chain as preset JSON.
+    #[wasm_bindgen]
+    pub fn preset_json(&self) -> String {
+        let mut preset = Preset::from_chain(&self.chain);
+        preset.midi = self.midi.mappings().to_vec();
+        preset.network = self.network.mappings().to_vec();
+        self.age.save_into(&mut preset);
+        preset.to_json()
+    }
+
+    /// Feeds raw bytes from a Web MIDI `midimessage` event. Returns `true`
+    /// if a parameter moved or a mapping was learned.
+    #[wasm_bindgen]
+    pub fn midi_message(&mut self, bytes: &[u8]) -> bool {
+        self.midi.handle(bytes, &mut self.chain)
+    }
+
+    /// Maps the next CC or note received to a parameter of an effect.
+    #[wasm_bindgen]
+    pub fn midi_learn(&mut self, index: usize, name: &str) -> bool {
+        let valid = self
+            .chain
+            .get(index)
+            .is_some_and(|e| e.params().iter().any(|p| p.name == name));
+        if valid {
+            self.midi.learn(index, name);
+        }
+        valid
+    }
+
+    #[wasm_bindgen]
+    pub fn midi_cancel_learn(&mut self) {
+        self.midi.cancel_learn();
+    }
+
+    #[wasm_bindgen]
+    pub fn midi_is_learning(&self) -> bool {
+        self.midi.is_learning()
+    }
+
+    #[wasm_bindgen]
+    pub fn midi_unmap(&mut self, index: usize, name: &str) -> bool {
+        self.midi.unmap(index, name)
+    }
+
+    /// Feeds a reading of the connection, as polled from WebRTC
+    /// `getStats()`, to the network mappings. `loss` is the fraction of
+    /// packets lost since the last reading. Returns `true` if a parameter
+    /// moved.
+    #[wasm_bindgen]
+    pub fn network_stats(
+        &mut self,
+        rtt_ms: f32,
+        jitter_ms: f32,
+        loss: f32,
+        bitrate_kbps: f32,
+    ) -> bool {
+        let stats = NetworkStats {
+            rtt_ms,
+            jitter_ms,
+            loss,
+            bitrate_kbps,
+        };
+        self.network.update(stats, &mut self.chain)
+    }
+
+    /// Drives a parameter from a connection metric (`rtt`, `jitter`,
+    /// `loss` or `bitrate`): the metric at `from` sets the parameter's
+    /// minimum and at `to` its maximum. Replaces any mapping the parameter
+    /// already had.
+    #[wasm_bindgen]
+    pub fn network_map(
+        &mut self,
+        metric: &str,
+        from: f32,
+        to: f32,
+        index: usize,
+        name: &str,
+    ) -> bool {
+        let Some(metric) = NetworkMetric::from_name(metric) else {
+            return false;
+        };
+        let mapping = NetworkMapping::new(metric, from, to, index, name);
+        let valid = mapping.is_valid_for(&self.chain);
+        if valid {
+            self.network.map(mapping);
+        }
+        valid
+    }
+
+    #[wasm_bindgen]
+    pub fn network_unmap(&mut self, index: usize, name: &str) -> bool {
+        self.network.unmap(index, name)
+    }
+
+    /// Starts the session clock that ages the patch. Peers given the same
+    /// start, in milliseconds on a shared clock such as `Date.now()`, age
+    /// identically.
+    #[wasm_bindgen]
+    pub fn set_session_start(&mut self, start_ms: f64) {
+        self.session_start_ms = Some(start_ms);
+    }
+
+    /// Ages the patch to the time `now_ms` on the session clock and returns
+    /// the age, from 0 (fresh) to 1 (fully decayed). Does nothing before
+    /// [`set_session_start`] or without an `age` section in the preset.
+    ///
+    /// [`set_session_start`]: AudioProcessor::set_session_start
+    #[wasm_bindgen]
+    pub fn session_time(&mut self, now_ms: f64) -> f32 {
+        if let Some(start_ms) = self.session_start_ms.filter(|_| !self.age.is_empty()) {
+            let age = self.age.age_at((now_ms - start_ms) / 1000.0);
+            self.age.apply(age, &mut self.chain);
+        }
+        self.age.age()
+    }
+
+    /// Sets the age macro by hand, from 0 (fresh) to 1 (fully decayed).
+    #[wasm_bindgen]
+    pub fn set_age(&mut self, age: f32) {
+        self.age.apply(age, &mut self.chain);
+    }
+
+    #[wasm_bindgen]
+    pub fn age(&self) -> f32 {
+        self.age.age()
+    }
+
+    /// Decodes a WAV or FLAC file (e.g. a `Uint8Array` of a fetched
+    /// `ArrayBuffer`) into the sample pool under `name`, resampled to the
+    /// current rate. Returns `false` if the file can't be decoded.
+    #[wasm_bindgen]
+    pub fn load_sample(&mut self, name: &str, bytes: &[u8]) -> bool {
+        match self.samples.load(name, bytes) {
+            Ok(sample) => {
+                console_log!(
+                    "Loaded sample {}: {} channels, {} frames",
+                    name,
+                    sample.channels.len(),
+                    sample.frames()
+                );
+                true
+            }
+            Err(error) => {
+                console_log!("Could not load sample {}: {}", name, error);
+                false
+            }
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn remove_sample(&mut self, name: &str) -> bool {
+        self.samples.remove(name)
+    }
+
+    #[wasm_bindgen]
+    pub fn sample_names(&self) -> Vec<String> {
+        self.samples.names().map(str::to_string).collect()
+    }
+
+    /// Length of a pooled sample in frames at the current rate.
+    #[wasm_bindgen]
+    pub fn sample_frames(&self, name: &str) -> Option<usize> {
+        self.samples.get(name).map(|s| s.frames())
+    }
+}
+
+impl AudioProcessor {
+    /// The input block the worklet writes into, for native callers.
+    pub fn input_mut(&mut self) -> &mut [f32] {
+        &mut self.input_buffer
+    }
+
+    /// Left and right output blocks.
+    pub fn output(&self) -> (&[f32], &[f32]) {
+        (&self.output_buffer, &self.output_buffer_right)
+    }
 }
 
 #[cfg(test)]
@@ -104,7 +444,7 @@ mod tests {
 
         // Fill input with test data
         for i in 0..BUFFER_SIZE {
-            processor.input_buffer[i] = (i as f32 / BUFFER_SIZE * 2.0 - 1.0) * 0.99;
+            processor.input_buffer[i] = (i as f32 / BUFFER_SIZE as f32 * 2.0 - 1.0) * 0.99;
         }
 
         processor.process_audio(0, BUFFER_SIZE);
@@ -114,4 +454,187 @@ mod tests {
             assert_eq!(processor.input_buffer[i], processor.output_buffer[i]);
         }
     }
+
+    #[test]
+    fn test_chain_editing() {
+        let mut processor = AudioProcessor::new();
+        assert_eq!(processor.effect_count(), 1);
+
+        assert!(processor.add_effect("pitch_correction"));
+        assert!(!processor.add_effect("unknown"));
+        assert!(processor.set_effect_param(1, "key", 9.0));
+        assert_eq!(processor.get_effect_param(1, "key"), Some(9.0));
+
+        processor.set_sample_rate(44_100.0);
+        assert_eq!(processor.get_effect_param(1, "key"), Some(9.0));
+        assert_eq!(
+            processor.effect_kind(1).as_deref(),
+            Some("pitch_correction")
+        );
+    }
+
+    #[test]
+    fn test_presets_replace_the_chain() {
+        let mut processor = AudioProcessor::new();
+        assert!(processor.load_preset(r#"{"effects": [{"kind": "reverb"}, {"kind": "ring_mod"}]}"#));
+        assert_eq!(processor.effect_count(), 2);
+        assert!(!processor.load_preset(r#"{"effects": [{"kind": "nope"}]}"#));
+        assert_eq!(processor.effect_count(), 2);
+
+        let saved = processor.preset_json();
+        processor.clear_effects();
+        assert!(processor.load_preset(&saved));
+        assert_eq!(processor.effect_kind(1).as_deref(), Some("ring_mod"));
+    }
+
+    #[test]
+    fn test_samples_follow_the_sample_rate() {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 48_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut bytes, spec).unwrap();
+        (0..480).for_each(|i| writer.write_sample((i % 100) as i16).unwrap());
+        writer.finalize().unwrap();
+
+        let mut processor = AudioProcessor::new();
+        assert!(processor.load_sample("click", bytes.get_ref()));
+        assert!(!processor.load_sample("junk", &[1, 2, 3]));
+        assert_eq!(processor.sample_names(), ["click"]);
+        assert_eq!(processor.sample_frames("click"), Some(480));
+
+        processor.set_sample_rate(24_000.0);
+        assert_eq!(processor.sample_frames("click"), Some(240));
+        assert!(processor.remove_sample("click"));
+        assert_eq!(processor.sample_frames("click"), None);
+    }
+
+    #[test]
+    fn test_midi_mappings_follow_the_chain() {
+        let mut processor = AudioProcessor::new();
+        assert!(processor.add_effect("reverb"));
+        assert!(!processor.midi_learn(1, "nope"));
+        assert!(processor.midi_learn(1, "mix"));
+        assert!(processor.midi_message(&[0xB0, 1, 0]));
+        assert!(processor.midi_message(&[0xB0, 1, 127]));
+        assert_eq!(processor.get_effect_param(1, "mix"), Some(1.0));
+
+        // The mapping moves with its effect and is saved in the preset.
+        assert!(processor.move_effect(1, 0));
+        let saved = processor.preset_json();
+        assert!(processor.load_preset(&saved));
+        assert!(processor.midi_message(&[0xB0, 1, 0]));
+        assert_eq!(processor.get_effect_param(0, "mix"), Some(0.0));
+
+        assert!(processor.remove_effect(0));
+        assert!(!processor.midi_message(&[0xB0, 1, 127]));
+    }
+
+    #[test]
+    fn test_network_mappings_follow_the_chain() {
+        let mut processor = AudioProcessor::new();
+        assert!(processor.add_effect("transmission"));
+        assert!(!processor.network_map("latency", 50.0, 500.0, 1, "loss"));
+        assert!(!processor.network_map("rtt", 50.0, 500.0, 1, "nope"));
+        assert!(processor.network_map("rtt", 50.0, 500.0, 1, "jitter_ms"));
+        assert!(processor.network_stats(500.0, 0.0, 0.0, 32.0));
+        assert_eq!(processor.get_effect_param(1, "jitter_ms"), Some(40.0));
+
+        assert!(processor.move_effect(1, 0));
+        let saved = processor.preset_json();
+        assert!(saved.contains("\"network\""));
+        processor.clear_effects();
+        assert!(processor.load_preset(&saved));
+        assert!(processor.network_stats(50.0, 0.0, 0.0, 32.0));
+        assert_eq!(processor.get_effect_param(0, "jitter_ms"), Some(0.0));
+
+        assert!(processor.network_unmap(0, "jitter_ms"));
+        assert!(!processor.network_stats(500.0, 0.0, 0.0, 32.0));
+    }
+
+    #[test]
+    fn test_age_follows_the_session_clock() {
+        let preset = r#"{
+            "effects": [{ "kind": "reverb" }, { "kind": "transmission", "params": { "loss": 0 } }],
+            "age": { "duration_s": 600, "decayed": [
+                { "kind": "reverb" }, { "kind": "transmission", "params": { "loss": 0.4 } }
+            ] }
+        }"#;
+        let mut processor = AudioProcessor::new();
+        assert!(processor.load_preset(preset));
+        assert_eq!(processor.session_time(1_000.0), 0.0);
+
+        processor.set_session_start(1_000.0);
+        assert_eq!(processor.session_time(301_000.0), 0.5);
+        let loss = processor.get_effect_param(1, "loss").unwrap();
+        assert!((loss - 0.2).abs() < 1e-6);
+
+        // Saving while aged keeps the fresh preset.
+        assert!(processor.move_effect(1, 0));
+        let saved = processor.preset_json();
+        assert!(processor.load_preset(&saved));
+        assert_eq!(processor.get_effect_param(0, "loss"), Some(0.0));
+        processor.set_age(1.0);
+        assert_eq!(processor.get_effect_param(0, "loss"), Some(0.4));
+    }
+
+    #[test]
+    fn test_edits_to_aging_parameters_are_kept() {
+        let preset = r#"{
+            "effects": [{ "kind": "transmission", "params": { "loss": 0 } }],
+            "age": { "duration_s": 600, "decayed": [
+                { "kind": "transmission", "params": { "loss": 0.4 } }
+            ] }
+        }"#;
+        let mut processor = AudioProcessor::new();
+        assert!(processor.load_preset(preset));
+        processor.set_session_start(0.0);
+        processor.session_time(300_000.0);
+
+        // Moved by hand at age 0.5: the next tick ages on from the edit.
+        assert!(processor.set_effect_param(0, "loss", 0.3));
+        processor.session_time(300_000.0);
+        let loss = processor.get_effect_param(0, "loss").unwrap();
+        assert!((loss - 0.3).abs() < 1e-6, "{loss}");
+        processor.session_time(600_000.0);
+        let loss = processor.get_effect_param(0, "loss").unwrap();
+        assert!((loss - 0.5).abs() < 1e-6, "{loss}");
+
+        // A MIDI move is kept the same way, and saved re-based.
+        assert!(processor.midi_learn(0, "loss"));
+        assert!(processor.midi_message(&[0xB0, 20, 127]));
+        assert!(processor.midi_message(&[0xB0, 20, 127]));
+        assert!(processor.midi_message(&[0xB0, 20, 0]));
+        assert_eq!(processor.get_effect_param(0, "loss"), Some(0.0));
+        let saved = Preset::from_json(&processor.preset_json()).unwrap();
+        assert_eq!(saved.effects[0].params["loss"], 0.0);
+        assert_eq!(saved.age.unwrap().decayed[0].params["loss"], 0.0);
+        processor.session_time(600_000.0);
+        assert_eq!(processor.get_effect_param(0, "loss"), Some(0.0));
+    }
+
+    #[test]
+    fn test_non_finite_samples_are_scrubbed() {
+        let mut processor = AudioProcessor::new();
+        processor.input_mut().fill(0.1);
+        processor.input_mut()[3] = f32::NAN;
+        processor.input_mut()[7] = f32::INFINITY;
+        processor.process_audio(0, BUFFER_SIZE);
+
+        let (left, right) = processor.output();
+        assert!(left.iter().chain(right).all(|s| s.is_finite()));
+    }
+
+    #[test]
+    fn test_out_of_range_blocks_are_ignored() {
+        let mut processor = AudioProcessor::new();
+        processor.input_mut().fill(0.5);
+        processor.process_audio(usize::MAX, 2);
+        processor.process_audio(1, usize::MAX);
+        processor.process_audio(100, 29);
+        assert!(processor.output().0.iter().all(|&s| s == 0.0));
+    }
 }
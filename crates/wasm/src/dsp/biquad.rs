@@ -0,0 +1,164 @@
+use super::flush_denormal;
+use std::f32::consts::PI;
+
+/// Filter response designed by [`Biquad::design`], after the RBJ cookbook.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterKind {
+    LowPass,
+    HighPass,
+    /// Constant 0 dB peak gain.
+    BandPass,
+    Notch,
+    /// `gain_db` boost or cut around the centre frequency.
+    Peak,
+    LowShelf,
+    HighShelf,
+}
+
+/// Second-order IIR section in transposed direct form II.
+#[derive(Debug, Clone, Copy)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Default for Biquad {
+    /// A pass-through section.
+    fn default() -> Self {
+        Self {
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+}
+
+impl Biquad {
+    pub fn new(kind: FilterKind, sample_rate: f32, freq: f32, q: f32, gain_db: f32) -> Self {
+        let mut filter = Self::default();
+        filter.design(kind, sample_rate, freq, q, gain_db);
+        filter
+    }
+
+    /// Recomputes coefficients, keeping the filter state.
+    pub fn design(&mut self, kind: FilterKind, sample_rate: f32, freq: f32, q: f32, gain_db: f32) {
+        let freq = freq.clamp(1.0, sample_rate * 0.49);
+        let q = q.max(0.01);
+        let w0 = 2.0 * PI * freq / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        let a = 10.0_f32.powf(gain_db / 40.0);
+
+        let (b0, b1, b2, a0, a1, a2) = match kind {
+            FilterKind::LowPass => {
+                let b = (1.0 - cos) * 0.5;
+                (b, 1.0 - cos, b, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+            }
+            FilterKind::HighPass => {
+                let b = (1.0 + cos) * 0.5;
+                (b, -(1.0 + cos), b, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+            }
+            FilterKind::BandPass => (alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            FilterKind::Notch => (1.0, -2.0 * cos, 1.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            FilterKind::Peak => (
+                1.0 + alpha * a,
+                -2.0 * cos,
+                1.0 - alpha * a,
+                1.0 + alpha / a,
+                -2.0 * cos,
+                1.0 - alpha / a,
+            ),
+            FilterKind::LowShelf => {
+                let s = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) - (a - 1.0) * cos + s),
+                    2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                    a * ((a + 1.0) - (a - 1.0) * cos - s),
+                    (a + 1.0) + (a - 1.0) * cos + s,
+                    -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                    (a + 1.0) + (a - 1.0) * cos - s,
+                )
+            }
+            FilterKind::HighShelf => {
+                let s = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) + (a - 1.0) * cos + s),
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                    a * ((a + 1.0) + (a - 1.0) * cos - s),
+                    (a + 1.0) - (a - 1.0) * cos + s,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                    (a + 1.0) - (a - 1.0) * cos - s,
+                )
+            }
+        };
+
+        self.b0 = b0 / a0;
+        self.b1 = b1 / a0;
+        self.b2 = b2 / a0;
+        self.a1 = a1 / a0;
+        self.a2 = a2 / a0;
+    }
+
+    /// Normalised coefficients as `[b0, b1, b2, a1, a2]`.
+    pub fn coefficients(&self) -> [f32; 5] {
+        [self.b0, self.b1, self.b2, self.a1, self.a2]
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = self.b0 * input + self.z1;
+        self.z1 = flush_denormal(self.b1 * input - self.a1 * output + self.z2);
+        self.z2 = flush_denormal(self.b2 * input - self.a2 * output);
+        output
+    }
+
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gain_at(filter: &mut Biquad, freq: f32, sample_rate: f32) -> f32 {
+        let mut peak = 0.0_f32;
+        for i in 0..(sample_rate as usize) {
+            let x = (2.0 * PI * freq * i as f32 / sample_rate).sin();
+            let y = filter.process(x);
+            if i > sample_rate as usize / 2 {
+                peak = peak.max(y.abs());
+            }
+        }
+        peak
+    }
+
+    #[test]
+    fn test_lowpass_passes_lows_and_cuts_highs() {
+        let sr = 48_000.0;
+        let mut low = Biquad::new(FilterKind::LowPass, sr, 1000.0, 0.707, 0.0);
+        let mut high = Biquad::new(FilterKind::LowPass, sr, 1000.0, 0.707, 0.0);
+        assert!((gain_at(&mut low, 100.0, sr) - 1.0).abs() < 0.02);
+        assert!(gain_at(&mut high, 10_000.0, sr) < 0.02);
+    }
+
+    #[test]
+    fn test_peak_boost_at_centre() {
+        let sr = 48_000.0;
+        let mut filter = Biquad::new(FilterKind::Peak, sr, 2000.0, 1.0, 6.0);
+        let gain = gain_at(&mut filter, 2000.0, sr);
+        assert!(
+            (gain - 10.0_f32.powf(6.0 / 20.0)).abs() < 0.05,
+            "gain {gain}"
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use super::DelayLine;
+
+/// Stereo ring buffer of the most recent input, kept across blocks.
+///
+/// Effects that replay "what just happened" (stutters, reverses, tape
+/// stops) read back from here by delay, so a slice can be far longer than
+/// the 128-frame render quantum it is triggered in.
+pub struct CaptureBuffer {
+    channels: [DelayLine; 2],
+}
+
+impl CaptureBuffer {
+    pub fn new(max_frames: usize) -> Self {
+        Self {
+            channels: [DelayLine::new(max_frames), DelayLine::new(max_frames)],
+        }
+    }
+
+    /// Longest delay that can be read back.
+    pub fn max_delay(&self) -> usize {
+        self.channels[0].max_delay()
+    }
+
+    pub fn push(&mut self, frame: [f32; 2]) {
+        for (channel, sample) in self.channels.iter_mut().zip(frame) {
+            channel.push(sample);
+        }
+    }
+
+    /// Frame pushed `delay` frames ago; `read(0)` is the latest.
+    pub fn read(&self, delay: usize) -> [f32; 2] {
+        [self.channels[0].read(delay), self.channels[1].read(delay)]
+    }
+
+    pub fn read_frac(&self, delay: f32) -> [f32; 2] {
+        [
+            self.channels[0].read_frac(delay),
+            self.channels[1].read_frac(delay),
+        ]
+    }
+
+    /// Copies the last `left.len()` frames, oldest first, into `left` and
+    /// `right`.
+    pub fn copy_recent(&self, left: &mut [f32], right: &mut [f32]) {
+        let frames = left.len().min(right.len());
+        for (i, (l, r)) in left.iter_mut().zip(right.iter_mut()).enumerate() {
+            [*l, *r] = self.read(frames - 1 - i);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.channels.iter_mut().for_each(DelayLine::clear);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_frames_come_back_in_order() {
+        let mut capture = CaptureBuffer::new(1000);
+        for i in 0..3000 {
+            capture.push([i as f32, -(i as f32)]);
+        }
+        assert_eq!(capture.read(0), [2999.0, -2999.0]);
+
+        let mut left = [0.0; 4];
+        let mut right = [0.0; 4];
+        capture.copy_recent(&mut left, &mut right);
+        assert_eq!(left, [2996.0, 2997.0, 2998.0, 2999.0]);
+        assert_eq!(right, [-2996.0, -2997.0, -2998.0, -2999.0]);
+    }
+}
@@ -0,0 +1,60 @@
+/// Logarithmic 8-bit companding laws from G.711 telephony.
+///
+/// Both squeeze the signal through a log curve before quantizing to a
+/// signed byte, spending resolution on quiet passages at the expense of
+/// loud ones. These use the continuous curves rather than G.711's
+/// segmented approximation; the grain is the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Companding {
+    MuLaw,
+    ALaw,
+}
+
+const MU: f32 = 255.0;
+const A: f32 = 87.6;
+
+impl Companding {
+    pub fn encode(self, x: f32) -> i8 {
+        let magnitude = x.abs().min(1.0);
+        let y = match self {
+            Companding::MuLaw => (MU * magnitude).ln_1p() / MU.ln_1p(),
+            Companding::ALaw if magnitude < 1.0 / A => A * magnitude / (1.0 + A.ln()),
+            Companding::ALaw => (1.0 + (A * magnitude).ln()) / (1.0 + A.ln()),
+        };
+        (y.copysign(x) * 127.0).round() as i8
+    }
+
+    pub fn decode(self, code: i8) -> f32 {
+        let y = code as f32 / 127.0;
+        let magnitude = y.abs();
+        let x = match self {
+            Companding::MuLaw => (magnitude * MU.ln_1p()).exp_m1() / MU,
+            Companding::ALaw if magnitude < 1.0 / (1.0 + A.ln()) => magnitude * (1.0 + A.ln()) / A,
+            Companding::ALaw => (magnitude * (1.0 + A.ln()) - 1.0).exp() / A,
+        };
+        x.copysign(y)
+    }
+
+    /// Encodes and decodes `x`, leaving only the quantization error.
+    pub fn process(self, x: f32) -> f32 {
+        self.decode(self.encode(x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_scales_with_level() {
+        for law in [Companding::MuLaw, Companding::ALaw] {
+            for x in [0.9f32, -0.5, 0.05, -0.003] {
+                let error = (law.process(x) - x).abs();
+                // Roughly constant relative error, unlike linear 8-bit.
+                assert!(error < x.abs() * 0.05 + 1e-4, "{law:?} {x}: {error}");
+            }
+            assert_eq!(law.process(0.0), 0.0);
+            assert!((law.process(1.0) - 1.0).abs() < 1e-5);
+        }
+    }
+}
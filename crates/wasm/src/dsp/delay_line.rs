@@ -0,0 +1,67 @@
+/// Fixed-capacity ring buffer with integer and fractional reads.
+///
+/// The capacity is rounded up to a power of two so wrapping is a mask.
+pub struct DelayLine {
+    buffer: Vec<f32>,
+    mask: usize,
+    write_pos: usize,
+}
+
+impl DelayLine {
+    pub fn new(max_delay: usize) -> Self {
+        let size = (max_delay + 2).next_power_of_two();
+        Self {
+            buffer: vec![0.0; size],
+            mask: size - 1,
+            write_pos: 0,
+        }
+    }
+
+    /// Longest delay that can be read back without wrapping onto new data.
+    pub fn max_delay(&self) -> usize {
+        self.buffer.len() - 2
+    }
+
+    pub fn push(&mut self, sample: f32) {
+        self.buffer[self.write_pos] = sample;
+        self.write_pos = (self.write_pos + 1) & self.mask;
+    }
+
+    /// Sample written `delay` pushes ago; `read(0)` is the latest sample.
+    pub fn read(&self, delay: usize) -> f32 {
+        let delay = delay.min(self.max_delay());
+        self.buffer[(self.write_pos + self.mask - delay) & self.mask]
+    }
+
+    /// Linearly interpolated read at a fractional delay.
+    pub fn read_frac(&self, delay: f32) -> f32 {
+        let delay = delay.clamp(0.0, self.max_delay() as f32 - 1.0);
+        let whole = delay as usize;
+        let frac = delay - whole as f32;
+        let a = self.read(whole);
+        let b = self.read(whole + 1);
+        a + (b - a) * frac
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.iter_mut().for_each(|s| *s = 0.0);
+        self.write_pos = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integer_and_fractional_reads() {
+        let mut line = DelayLine::new(8);
+        for i in 0..8 {
+            line.push(i as f32);
+        }
+
+        assert_eq!(line.read(0), 7.0);
+        assert_eq!(line.read(3), 4.0);
+        assert!((line.read_frac(2.5) - 4.5).abs() < 1e-6);
+    }
+}
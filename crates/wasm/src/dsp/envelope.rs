@@ -0,0 +1,66 @@
+use super::{flush_denormal, one_pole_coeff};
+
+/// Peak envelope follower with separate attack and release times.
+#[derive(Debug, Clone)]
+pub struct EnvelopeFollower {
+    sample_rate: f32,
+    attack: f32,
+    release: f32,
+    value: f32,
+}
+
+impl EnvelopeFollower {
+    pub fn new(sample_rate: f32, attack_ms: f32, release_ms: f32) -> Self {
+        let mut follower = Self {
+            sample_rate,
+            attack: 0.0,
+            release: 0.0,
+            value: 0.0,
+        };
+        follower.set_times(attack_ms, release_ms);
+        follower
+    }
+
+    pub fn set_times(&mut self, attack_ms: f32, release_ms: f32) {
+        self.attack = one_pole_coeff(attack_ms, self.sample_rate);
+        self.release = one_pole_coeff(release_ms, self.sample_rate);
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let level = input.abs();
+        let coeff = if level > self.value {
+            self.attack
+        } else {
+            self.release
+        };
+        self.value = flush_denormal(level + (self.value - level) * coeff);
+        self.value
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    pub fn reset(&mut self) {
+        self.value = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fast_attack_slow_release() {
+        let mut env = EnvelopeFollower::new(48_000.0, 1.0, 100.0);
+        for _ in 0..480 {
+            env.process(1.0);
+        }
+        assert!(env.value() > 0.99);
+
+        for _ in 0..480 {
+            env.process(0.0);
+        }
+        assert!(env.value() > 0.8, "released too fast: {}", env.value());
+    }
+}
@@ -0,0 +1,163 @@
+use std::f32::consts::PI;
+use std::ops::{Add, Mul, Sub};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Complex {
+    pub re: f32,
+    pub im: f32,
+}
+
+impl Complex {
+    pub const ZERO: Self = Self { re: 0.0, im: 0.0 };
+
+    pub const fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    pub fn from_polar(magnitude: f32, phase: f32) -> Self {
+        let (sin, cos) = phase.sin_cos();
+        Self::new(magnitude * cos, magnitude * sin)
+    }
+
+    pub fn norm(self) -> f32 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn arg(self) -> f32 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    pub fn scale(self, factor: f32) -> Self {
+        Self::new(self.re * factor, self.im * factor)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+/// In-place radix-2 FFT of a fixed power-of-two size.
+///
+/// Twiddles and the bit-reversal permutation are computed up front, so
+/// transforms don't allocate.
+pub struct Fft {
+    twiddles: Vec<Complex>,
+    reversed: Vec<usize>,
+}
+
+impl Fft {
+    pub fn new(size: usize) -> Self {
+        assert!(size.is_power_of_two(), "FFT size must be a power of two");
+        let bits = size.trailing_zeros();
+        Self {
+            twiddles: (0..size / 2)
+                .map(|k| Complex::from_polar(1.0, -2.0 * PI * k as f32 / size as f32))
+                .collect(),
+            reversed: (0..size)
+                .map(|i| {
+                    i.reverse_bits()
+                        .checked_shr(usize::BITS - bits)
+                        .unwrap_or(0)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.reversed.len()
+    }
+
+    /// Forward transform, unnormalized.
+    pub fn forward(&self, data: &mut [Complex]) {
+        self.transform(data, false);
+    }
+
+    /// Inverse transform, scaled by `1 / size` so it undoes [`forward`].
+    ///
+    /// [`forward`]: Fft::forward
+    pub fn inverse(&self, data: &mut [Complex]) {
+        self.transform(data, true);
+        let scale = 1.0 / self.size() as f32;
+        data.iter_mut().for_each(|x| *x = x.scale(scale));
+    }
+
+    fn transform(&self, data: &mut [Complex], inverse: bool) {
+        let size = self.size();
+        assert_eq!(data.len(), size);
+        for (i, &j) in self.reversed.iter().enumerate() {
+            if i < j {
+                data.swap(i, j);
+            }
+        }
+        let mut half = 1;
+        while half < size {
+            let stride = size / (2 * half);
+            for start in (0..size).step_by(2 * half) {
+                for k in 0..half {
+                    let twiddle = self.twiddles[k * stride];
+                    let twiddle = if inverse { twiddle.conj() } else { twiddle };
+                    let odd = data[start + k + half] * twiddle;
+                    let even = data[start + k];
+                    data[start + k] = even + odd;
+                    data[start + k + half] = even - odd;
+                }
+            }
+            half *= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_naive_dft_and_inverts() {
+        let size = 16;
+        let input: Vec<Complex> = (0..size)
+            .map(|i| Complex::new((i as f32 * 0.7).sin(), (i as f32 * 0.3).cos()))
+            .collect();
+        let mut data = input.clone();
+        let fft = Fft::new(size);
+        fft.forward(&mut data);
+
+        for (k, bin) in data.iter().enumerate() {
+            let expected = input
+                .iter()
+                .enumerate()
+                .fold(Complex::ZERO, |acc, (n, &x)| {
+                    acc + x * Complex::from_polar(1.0, -2.0 * PI * (k * n) as f32 / size as f32)
+                });
+            assert!((*bin - expected).norm() < 1e-4, "bin {k}");
+        }
+
+        fft.inverse(&mut data);
+        for (a, b) in data.iter().zip(&input) {
+            assert!((*a - *b).norm() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use super::lpc::{autocorrelate, levinson, Lattice};
+use super::{flush_denormal, hann, DelayLine};
+
+const ORDER: usize = 18;
+const PRE_EMPHASIS: f32 = 0.9;
+
+/// Moves the spectral envelope of a signal without touching its pitch.
+///
+/// Every hop the recent input is analysed twice with LPC: once as-is, to
+/// whiten the signal down to its excitation, and once after resampling the
+/// frame by the shift factor, which scales the envelope along the frequency
+/// axis. The excitation is then re-coloured with the shifted envelope.
+/// Reflection coefficients are interpolated across each hop so envelope
+/// updates don't click.
+pub struct FormantShifter {
+    history: DelayLine,
+    window: Vec<f32>,
+    frame: Vec<f32>,
+    autocorr: Vec<f32>,
+    scratch: Vec<f32>,
+    analysis_k: Vec<f32>,
+    analysis_target: Vec<f32>,
+    synthesis_k: Vec<f32>,
+    synthesis_target: Vec<f32>,
+    gain: f32,
+    gain_target: f32,
+    analysis: Lattice,
+    synthesis: Lattice,
+    factor: f32,
+    hop: usize,
+    counter: usize,
+    last_input: f32,
+    last_output: f32,
+}
+
+impl FormantShifter {
+    pub fn new(sample_rate: f32) -> Self {
+        let frame_len = ((sample_rate * 0.025) as usize).next_power_of_two();
+        Self {
+            history: DelayLine::new(frame_len * 2 + 2),
+            window: (0..frame_len)
+                .map(|i| hann(i as f32 / frame_len as f32))
+                .collect(),
+            frame: vec![0.0; frame_len],
+            autocorr: vec![0.0; ORDER + 1],
+            scratch: vec![0.0; 2 * (ORDER + 1)],
+            analysis_k: vec![0.0; ORDER],
+            analysis_target: vec![0.0; ORDER],
+            synthesis_k: vec![0.0; ORDER],
+            synthesis_target: vec![0.0; ORDER],
+            gain: 1.0,
+            gain_target: 1.0,
+            analysis: Lattice::new(ORDER),
+            synthesis: Lattice::new(ORDER),
+            factor: 1.0,
+            hop: frame_len / 8,
+            counter: 0,
+            last_input: 0.0,
+            last_output: 0.0,
+        }
+    }
+
+    /// Envelope scale factor; 2.0 moves every formant up an octave.
+    pub fn set_factor(&mut self, factor: f32) {
+        self.factor = factor.clamp(0.5, 2.0);
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let emphasized = input - PRE_EMPHASIS * self.last_input;
+        self.last_input = input;
+        self.history.push(emphasized);
+
+        self.counter += 1;
+        if self.counter >= self.hop {
+            self.counter = 0;
+            self.analyze();
+        }
+
+        let step = 1.0 / self.hop as f32;
+        let pairs = [
+            (&mut self.analysis_k, &self.analysis_target),
+            (&mut self.synthesis_k, &self.synthesis_target),
+        ];
+        for (current, target) in pairs {
+            for (k, t) in current.iter_mut().zip(target.iter()) {
+                *k += (t - *k) * step;
+            }
+        }
+        self.gain += (self.gain_target - self.gain) * step;
+
+        let excitation = self.analysis.analyze(&self.analysis_k, emphasized);
+        let colored = self
+            .synthesis
+            .synthesize(&self.synthesis_k, excitation * self.gain);
+
+        self.last_output = flush_denormal(colored + PRE_EMPHASIS * self.last_output);
+        self.last_output
+    }
+
+    pub fn reset(&mut self) {
+        self.history.clear();
+        for coeffs in [
+            &mut self.analysis_k,
+            &mut self.analysis_target,
+            &mut self.synthesis_k,
+            &mut self.synthesis_target,
+        ] {
+            coeffs.iter_mut().for_each(|k| *k = 0.0);
+        }
+        self.analysis.reset();
+        self.synthesis.reset();
+        self.gain = 1.0;
+        self.gain_target = 1.0;
+        self.counter = 0;
+        self.last_input = 0.0;
+        self.last_output = 0.0;
+    }
+
+    fn analyze(&mut self) {
+        let len = self.frame.len();
+
+        for i in 0..len {
+            self.frame[i] = self.history.read(len - 1 - i) * self.window[i];
+        }
+        let analysis_error = self.fit(true);
+        let analysis_power = self.autocorr[0];
+
+        // Reading the frame `factor` times faster scales its spectrum by
+        // `factor`, so the LPC fit of it is the shifted envelope.
+        for i in 0..len {
+            let delay = (len - 1 - i) as f32 * self.factor;
+            self.frame[i] = self.history.read_frac(delay) * self.window[i];
+        }
+        let synthesis_error = self.fit(false);
+        let synthesis_power = self.autocorr[0];
+
+        self.gain_target = if analysis_error > 0.0 && synthesis_error > 0.0 {
+            let flatness_a = analysis_error / analysis_power;
+            let flatness_s = synthesis_error / synthesis_power;
+            (flatness_s / flatness_a).sqrt().clamp(0.1, 10.0)
+        } else {
+            1.0
+        };
+    }
+
+    /// Fits the current frame, storing the coefficients as analysis or
+    /// synthesis targets, and returns the prediction error power.
+    fn fit(&mut self, analysis: bool) -> f32 {
+        autocorrelate(&self.frame, &mut self.autocorr);
+        // Slight white-noise floor and lag window keep the fit well behaved
+        // on near-silent or very tonal frames.
+        self.autocorr[0] *= 1.0001;
+        for (lag, r) in self.autocorr.iter_mut().enumerate().skip(1) {
+            let w = 0.002 * lag as f32;
+            *r *= (-0.5 * w * w).exp();
+        }
+
+        let target = if analysis {
+            &mut self.analysis_target
+        } else {
+            &mut self.synthesis_target
+        };
+        levinson(&self.autocorr, target, &mut self.scratch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unity_factor_is_transparent() {
+        let mut shifter = FormantShifter::new(48_000.0);
+        let input: Vec<f32> = (0..9600)
+            .map(|i| (i as f32 * 0.05).sin() * 0.5 + (i as f32 * 0.23).sin() * 0.2)
+            .collect();
+        let output: Vec<f32> = input.iter().map(|&x| shifter.process(x)).collect();
+
+        let error = input[4800..]
+            .iter()
+            .zip(&output[4800..])
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0_f32, f32::max);
+        assert!(error < 0.02, "max error {error}");
+    }
+
+    #[test]
+    fn test_shifted_output_is_finite_and_bounded() {
+        let mut shifter = FormantShifter::new(48_000.0);
+        shifter.set_factor(1.5);
+        let mut seed = 12345_u32;
+        for i in 0..48_000 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let noise = (seed >> 16) as f32 / 32_768.0 - 1.0;
+            let pulse = if i % 200 == 0 { 1.0 } else { 0.0 };
+            let y = shifter.process(pulse * 0.8 + noise * 0.05);
+            assert!(y.is_finite() && y.abs() < 20.0);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Linear prediction helpers: autocorrelation, Levinson-Durbin and lattice
+//! filters driven by reflection coefficients.
+//!
+//! Lattice form is used throughout because reflection coefficients with
+//! magnitude below one always give a stable synthesis filter, and stay that
+//! way when interpolated between analysis frames.
+
+use super::flush_denormal;
+
+/// Autocorrelation of `frame` for lags `0..out.len()`.
+pub fn autocorrelate(frame: &[f32], out: &mut [f32]) {
+    for (lag, value) in out.iter_mut().enumerate() {
+        *value = frame[lag.min(frame.len())..]
+            .iter()
+            .zip(frame.iter())
+            .map(|(a, b)| a * b)
+            .sum();
+    }
+}
+
+/// Levinson-Durbin recursion for `A(z) = 1 + Σ a_k z^-k`.
+///
+/// Writes `r.len() - 1` reflection coefficients into `reflection` and
+/// returns the final prediction error power. `scratch` needs `2 * r.len()`
+/// elements. A silent frame yields all-zero coefficients.
+pub fn levinson(r: &[f32], reflection: &mut [f32], scratch: &mut [f32]) -> f32 {
+    let order = r.len() - 1;
+    let (a, prev) = scratch.split_at_mut(r.len());
+    a.iter_mut().for_each(|v| *v = 0.0);
+    reflection.iter_mut().for_each(|v| *v = 0.0);
+    a[0] = 1.0;
+
+    let mut error = r[0];
+    if error <= f32::EPSILON {
+        return 0.0;
+    }
+
+    for i in 1..=order {
+        let acc = r[i] + (1..i).map(|j| a[j] * r[i - j]).sum::<f32>();
+        let k = (-acc / error).clamp(-0.999, 0.999);
+        reflection[i - 1] = k;
+
+        prev[..=i].copy_from_slice(&a[..=i]);
+        for j in 1..i {
+            a[j] = prev[j] + k * prev[i - j];
+        }
+        a[i] = k;
+
+        error *= 1.0 - k * k;
+        if error <= f32::EPSILON {
+            break;
+        }
+    }
+    error
+}
+
+/// Lattice realisation of the whitening filter `A(z)` and its inverse.
+pub struct Lattice {
+    state: Vec<f32>,
+}
+
+impl Lattice {
+    pub fn new(order: usize) -> Self {
+        Self {
+            state: vec![0.0; order],
+        }
+    }
+
+    /// Prediction error of `input` under `A(z)` (FIR, always stable).
+    pub fn analyze(&mut self, reflection: &[f32], input: f32) -> f32 {
+        let mut forward = input;
+        let mut backward_in = input;
+        for (k, delayed) in reflection.iter().zip(self.state.iter_mut()) {
+            let f = forward + k * *delayed;
+            let b = k * forward + *delayed;
+            *delayed = backward_in;
+            forward = f;
+            backward_in = b;
+        }
+        forward
+    }
+
+    /// Output of `1 / A(z)` driven by `excitation` (all-pole).
+    pub fn synthesize(&mut self, reflection: &[f32], excitation: f32) -> f32 {
+        let order = reflection.len();
+        let mut forward = excitation;
+        for i in (0..order).rev() {
+            forward -= reflection[i] * self.state[i];
+            if i + 1 < order {
+                self.state[i + 1] = flush_denormal(reflection[i] * forward + self.state[i]);
+            }
+        }
+        // All-pole, so after the input stops the state decays forever.
+        self.state[0] = flush_denormal(forward);
+        forward
+    }
+
+    pub fn reset(&mut self) {
+        self.state.iter_mut().for_each(|s| *s = 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analysis_then_synthesis_is_identity() {
+        let signal: Vec<f32> = (0..512)
+            .map(|i| (i as f32 * 0.07).sin() + 0.3 * (i as f32 * 0.31).sin())
+            .collect();
+
+        let mut r = [0.0; 9];
+        autocorrelate(&signal, &mut r);
+        let mut k = [0.0; 8];
+        let mut scratch = [0.0; 18];
+        let error = levinson(&r, &mut k, &mut scratch);
+        assert!(error > 0.0 && error < r[0]);
+
+        let mut analysis = Lattice::new(8);
+        let mut synthesis = Lattice::new(8);
+        for &x in &signal {
+            let e = analysis.analyze(&k, x);
+            let y = synthesis.synthesize(&k, e);
+            assert!((y - x).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_synthesis_rings_down_to_exact_zero() {
+        let k = [0.6, -0.4, 0.3, -0.2];
+        let mut synthesis = Lattice::new(4);
+        synthesis.synthesize(&k, 1.0);
+        for _ in 0..20_000 {
+            synthesis.synthesize(&k, 0.0);
+        }
+        assert!(
+            synthesis.state.iter().all(|&s| s == 0.0),
+            "{:?}",
+            synthesis.state
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use super::fft::{Complex, Fft};
+use std::f32::consts::PI;
+
+/// Streaming modified discrete cosine transform with overlap-add
+/// resynthesis, the critically sampled transform behind most lossy audio
+/// codecs.
+///
+/// Every `hop` samples the last `2 * hop` are windowed with a sine window
+/// and transformed into `hop` real coefficients, which the caller may
+/// quantize or otherwise edit. The inverse transform's time-domain aliasing
+/// cancels against the neighbouring frames, so unedited coefficients come
+/// back out as the input delayed by [`latency`] samples.
+///
+/// [`latency`]: Mdct::latency
+pub struct Mdct {
+    fft: Fft,
+    hop: usize,
+    window: Vec<f32>,
+    /// `e^(-iπ(n + n0) / size)`, folding the MDCT's half-sample offsets
+    /// into a plain FFT.
+    pre: Vec<Complex>,
+    /// `e^(-iπ n0 k / hop)` for each coefficient.
+    post: Vec<Complex>,
+    input: Vec<f32>,
+    output: Vec<f32>,
+    overlap: Vec<f32>,
+    frame: Vec<Complex>,
+    coefficients: Vec<f32>,
+    /// Next write position in `input`, from `hop` up to `2 * hop`.
+    position: usize,
+}
+
+impl Mdct {
+    /// `hop` must be a power of two.
+    pub fn new(hop: usize) -> Self {
+        let size = 2 * hop;
+        let n0 = 0.5 + hop as f32 / 2.0;
+        Self {
+            fft: Fft::new(size),
+            hop,
+            window: (0..size)
+                .map(|n| (PI * (n as f32 + 0.5) / size as f32).sin())
+                .collect(),
+            pre: (0..size)
+                .map(|n| Complex::from_polar(1.0, -PI * (n as f32 + n0) / size as f32))
+                .collect(),
+            post: (0..hop)
+                .map(|k| Complex::from_polar(1.0, -PI * n0 * k as f32 / hop as f32))
+                .collect(),
+            input: vec![0.0; size],
+            output: vec![0.0; hop],
+            overlap: vec![0.0; hop],
+            frame: vec![Complex::ZERO; size],
+            coefficients: vec![0.0; hop],
+            position: hop,
+        }
+    }
+
+    pub fn hop(&self) -> usize {
+        self.hop
+    }
+
+    /// Delay between a sample going in and coming back out: a whole frame
+    /// of `2 * hop` samples.
+    pub fn latency(&self) -> usize {
+        2 * self.hop
+    }
+
+    /// Runs `buffer` through in place, calling `edit` with the coefficients
+    /// of every completed frame.
+    pub fn process(&mut self, buffer: &mut [f32], mut edit: impl FnMut(&mut [f32])) {
+        for sample in buffer.iter_mut() {
+            self.input[self.position] = *sample;
+            *sample = self.output[self.position - self.hop];
+            self.position += 1;
+            if self.position == 2 * self.hop {
+                self.position = self.hop;
+                self.run_frame(&mut edit);
+            }
+        }
+    }
+
+    fn run_frame(&mut self, edit: &mut impl FnMut(&mut [f32])) {
+        let hop = self.hop;
+        for (((bin, &x), &w), &pre) in self
+            .frame
+            .iter_mut()
+            .zip(&self.input)
+            .zip(&self.window)
+            .zip(&self.pre)
+        {
+            *bin = pre.scale(x * w);
+        }
+        self.fft.forward(&mut self.frame);
+        for ((c, &bin), &post) in self
+            .coefficients
+            .iter_mut()
+            .zip(&self.frame)
+            .zip(&self.post)
+        {
+            *c = (bin * post).re;
+        }
+
+        edit(&mut self.coefficients);
+
+        for (k, bin) in self.frame.iter_mut().enumerate() {
+            *bin = match self.coefficients.get(k) {
+                Some(&c) => self.post[k].conj().scale(c),
+                None => Complex::ZERO,
+            };
+        }
+        self.fft.inverse(&mut self.frame);
+        // Undoing the inverse FFT's 1 / size scaling, with another factor of
+        // two because the aliasing cancellation halves each frame.
+        for (n, (bin, &pre)) in self.frame.iter().zip(&self.pre).enumerate() {
+            let y = 4.0 * (*bin * pre.conj()).re * self.window[n];
+            if n < hop {
+                self.output[n] = self.overlap[n] + y;
+            } else {
+                self.overlap[n - hop] = y;
+            }
+        }
+        self.input.copy_within(hop.., 0);
+    }
+
+    pub fn reset(&mut self) {
+        self.input.fill(0.0);
+        self.output.fill(0.0);
+        self.overlap.fill(0.0);
+        self.position = self.hop;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconstructs_delayed_input() {
+        let mut mdct = Mdct::new(64);
+        let input: Vec<f32> = (0..1000)
+            .map(|i| (i as f32 * 0.37).sin() * 0.5 + ((i * 7919) % 101) as f32 / 404.0)
+            .collect();
+        let mut buffer = input.clone();
+        for block in buffer.chunks_mut(37) {
+            mdct.process(block, |_| {});
+        }
+        let latency = mdct.latency();
+        for (out, x) in buffer[latency..].iter().zip(&input) {
+            assert!((out - x).abs() < 1e-4, "{out} vs {x}");
+        }
+    }
+}
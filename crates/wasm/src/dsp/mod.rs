@@ -0,0 +1,100 @@
+//! Signal-processing building blocks shared by the effects. Nothing in here
+//! knows about parameters or the effect chain.
+
+pub mod biquad;
+pub mod capture;
+pub mod companding;
+pub mod delay_line;
+pub mod envelope;
+pub mod fft;
+pub mod formant;
+pub mod hrtf;
+pub mod lpc;
+pub mod mdct;
+pub mod oscillator;
+pub mod oversampler;
+pub mod pitch_detector;
+pub mod pitch_shifter;
+pub mod psola;
+pub mod resample;
+pub mod reverb;
+pub mod room;
+pub mod scale;
+pub mod simd;
+pub mod stft;
+pub mod waveshaper;
+
+pub use biquad::{Biquad, FilterKind};
+pub use capture::CaptureBuffer;
+pub use companding::Companding;
+pub use delay_line::DelayLine;
+pub use envelope::EnvelopeFollower;
+pub use fft::{Complex, Fft};
+pub use formant::FormantShifter;
+pub use hrtf::{BinauralPanner, HrirSet};
+pub use mdct::Mdct;
+pub use oscillator::{Noise, Oscillator, Waveform};
+pub use oversampler::Oversampler;
+pub use pitch_detector::PitchDetector;
+pub use pitch_shifter::PitchShifter;
+pub use psola::Psola;
+pub use resample::resample;
+pub use reverb::Fdn;
+pub use room::{EarlyReflections, RoomGeometry};
+pub use scale::Scale;
+pub use simd::Biquad4;
+pub use stft::{Stft, Window};
+pub use waveshaper::{Curve, Waveshaper};
+
+/// Converts a frequency in Hz to a (fractional) MIDI note number.
+pub fn hz_to_midi(hz: f32) -> f32 {
+    69.0 + 12.0 * (hz / 440.0).log2()
+}
+
+/// Converts a (fractional) MIDI note number to a frequency in Hz.
+pub fn midi_to_hz(note: f32) -> f32 {
+    440.0 * 2.0_f32.powf((note - 69.0) / 12.0)
+}
+
+/// Converts a pitch offset in semitones to a playback ratio.
+pub fn semitones_to_ratio(semitones: f32) -> f32 {
+    2.0_f32.powf(semitones / 12.0)
+}
+
+/// Coefficient for a one-pole smoother reaching ~63% of a step after `time_ms`.
+pub fn one_pole_coeff(time_ms: f32, sample_rate: f32) -> f32 {
+    if time_ms <= 0.0 {
+        return 0.0;
+    }
+    (-1000.0 / (time_ms * sample_rate)).exp()
+}
+
+/// Periodic Hann window value for position `phase` in `[0, 1)`.
+pub fn hann(phase: f32) -> f32 {
+    0.5 - 0.5 * (2.0 * std::f32::consts::PI * phase).cos()
+}
+
+/// Flushes values too small to hear to zero.
+///
+/// Decaying feedback state otherwise ends up subnormal, which is very slow
+/// on most CPUs, and wasm has no flush-to-zero mode to fall back on.
+pub fn flush_denormal(x: f32) -> f32 {
+    if x.abs() < 1e-20 {
+        0.0
+    } else {
+        x
+    }
+}
+
+/// Replaces NaN and infinite samples with silence. Returns `true` if any
+/// were found, so the caller can reset whatever produced them.
+pub fn scrub(buffer: &mut [f32]) -> bool {
+    let mut found = false;
+    for sample in buffer.iter_mut() {
+        if !sample.is_finite() {
+            *sample = 0.0;
+            found = true;
+        }
+    }
+    found
+}
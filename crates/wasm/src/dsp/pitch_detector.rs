@@ -0,0 +1,206 @@
+/// Monophonic pitch tracker based on the YIN algorithm.
+///
+/// Samples are pushed one at a time. Above 32 kHz the input is decimated by
+/// two before analysis, which keeps the O(n²) difference function cheap
+/// enough for the audio thread while still resolving low voices.
+pub struct PitchDetector {
+    sample_rate: f32,
+    decimation: usize,
+    decim_acc: f32,
+    decim_count: usize,
+    ring: Vec<f32>,
+    write_pos: usize,
+    filled: usize,
+    frame: Vec<f32>,
+    diff: Vec<f32>,
+    min_lag: usize,
+    max_lag: usize,
+    hop: usize,
+    since_analysis: usize,
+    threshold: f32,
+    silence: f32,
+    frequency: f32,
+    confidence: f32,
+    voiced: bool,
+}
+
+impl PitchDetector {
+    pub fn new(sample_rate: f32, min_hz: f32, max_hz: f32) -> Self {
+        let decimation = if sample_rate > 32_000.0 { 2 } else { 1 };
+        let rate = sample_rate / decimation as f32;
+        let max_lag = (rate / min_hz).ceil() as usize;
+        let min_lag = ((rate / max_hz).floor() as usize).max(2);
+        let len = max_lag * 2;
+
+        Self {
+            sample_rate,
+            decimation,
+            decim_acc: 0.0,
+            decim_count: 0,
+            ring: vec![0.0; len],
+            write_pos: 0,
+            filled: 0,
+            frame: vec![0.0; len],
+            diff: vec![0.0; max_lag + 1],
+            min_lag,
+            max_lag,
+            hop: (max_lag / 2).max(64),
+            since_analysis: 0,
+            threshold: 0.15,
+            silence: 1e-4,
+            frequency: 0.0,
+            confidence: 0.0,
+            voiced: false,
+        }
+    }
+
+    /// Latency between a sample entering and it influencing an estimate.
+    pub fn latency(&self) -> usize {
+        self.ring.len() * self.decimation / 2
+    }
+
+    /// Feeds one sample; returns `true` when a fresh estimate was produced.
+    pub fn push(&mut self, sample: f32) -> bool {
+        self.decim_acc += sample;
+        self.decim_count += 1;
+        if self.decim_count < self.decimation {
+            return false;
+        }
+
+        let value = self.decim_acc / self.decimation as f32;
+        self.decim_acc = 0.0;
+        self.decim_count = 0;
+
+        self.ring[self.write_pos] = value;
+        self.write_pos = (self.write_pos + 1) % self.ring.len();
+        self.filled = (self.filled + 1).min(self.ring.len());
+        self.since_analysis += 1;
+
+        if self.filled < self.ring.len() || self.since_analysis < self.hop {
+            return false;
+        }
+        self.since_analysis = 0;
+        self.analyze();
+        true
+    }
+
+    /// Detected fundamental in Hz, if the last frame was voiced.
+    pub fn frequency(&self) -> Option<f32> {
+        self.voiced.then_some(self.frequency)
+    }
+
+    /// Detected period in samples at the input sample rate.
+    pub fn period(&self) -> Option<f32> {
+        self.frequency().map(|hz| self.sample_rate / hz)
+    }
+
+    /// Periodicity of the last frame, from 0 (noise) to 1 (pure tone).
+    pub fn confidence(&self) -> f32 {
+        self.confidence
+    }
+
+    pub fn reset(&mut self) {
+        self.ring.iter_mut().for_each(|s| *s = 0.0);
+        self.write_pos = 0;
+        self.filled = 0;
+        self.since_analysis = 0;
+        self.decim_acc = 0.0;
+        self.decim_count = 0;
+        self.voiced = false;
+        self.confidence = 0.0;
+    }
+
+    fn analyze(&mut self) {
+        let len = self.ring.len();
+        for i in 0..len {
+            self.frame[i] = self.ring[(self.write_pos + i) % len];
+        }
+
+        let window = self.max_lag;
+        let energy = self.frame[..window].iter().map(|x| x * x).sum::<f32>() / window as f32;
+        if energy < self.silence * self.silence {
+            self.voiced = false;
+            self.confidence = 0.0;
+            return;
+        }
+
+        // Difference function followed by the cumulative mean normalisation.
+        self.diff[0] = 1.0;
+        let mut running = 0.0;
+        for tau in 1..=self.max_lag {
+            let mut sum = 0.0;
+            for j in 0..window {
+                let d = self.frame[j] - self.frame[j + tau];
+                sum += d * d;
+            }
+            running += sum;
+            self.diff[tau] = if running > 0.0 {
+                sum * tau as f32 / running
+            } else {
+                1.0
+            };
+        }
+
+        let mut best = None;
+        let mut tau = self.min_lag;
+        while tau < self.max_lag {
+            if self.diff[tau] < self.threshold {
+                while tau + 1 < self.max_lag && self.diff[tau + 1] < self.diff[tau] {
+                    tau += 1;
+                }
+                best = Some(tau);
+                break;
+            }
+            tau += 1;
+        }
+
+        let Some(tau) = best else {
+            self.voiced = false;
+            self.confidence = 0.0;
+            return;
+        };
+
+        // Parabolic interpolation around the dip for sub-sample accuracy.
+        let (a, b, c) = (self.diff[tau - 1], self.diff[tau], self.diff[tau + 1]);
+        let denom = a - 2.0 * b + c;
+        let offset = if denom.abs() > f32::EPSILON {
+            (0.5 * (a - c) / denom).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+
+        let lag = (tau as f32 + offset) * self.decimation as f32;
+        self.frequency = self.sample_rate / lag;
+        self.confidence = (1.0 - b).clamp(0.0, 1.0);
+        self.voiced = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_detects_sine_frequency() {
+        let sample_rate = 48_000.0;
+        let mut detector = PitchDetector::new(sample_rate, 70.0, 1000.0);
+
+        for i in 0..8192 {
+            let t = i as f32 / sample_rate;
+            detector.push((2.0 * PI * 220.0 * t).sin() * 0.5);
+        }
+
+        let hz = detector.frequency().expect("sine should be voiced");
+        assert!((hz - 220.0).abs() < 2.0, "detected {hz} Hz");
+    }
+
+    #[test]
+    fn test_silence_is_unvoiced() {
+        let mut detector = PitchDetector::new(48_000.0, 70.0, 1000.0);
+        for _ in 0..8192 {
+            detector.push(0.0);
+        }
+        assert!(detector.frequency().is_none());
+    }
+}
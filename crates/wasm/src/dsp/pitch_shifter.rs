@@ -0,0 +1,100 @@
+use super::DelayLine;
+use std::f32::consts::PI;
+
+/// Delay-line pitch shifter with two crossfaded read taps.
+///
+/// Each tap sweeps through a window of the delay line at the shift rate and
+/// the taps sit half a window apart, so one is always fading in while the
+/// other fades out. Works on any material (no pitch tracking needed), at the
+/// cost of shifting formants along with pitch.
+pub struct PitchShifter {
+    line: DelayLine,
+    window: f32,
+    phase: f32,
+    ratio: f32,
+}
+
+impl PitchShifter {
+    /// `max_window` bounds the longest window `set_window` will accept.
+    pub fn new(max_window: usize) -> Self {
+        Self {
+            line: DelayLine::new(max_window + 2),
+            window: max_window as f32,
+            phase: 0.0,
+            ratio: 1.0,
+        }
+    }
+
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.ratio = ratio.clamp(0.25, 4.0);
+    }
+
+    pub fn ratio(&self) -> f32 {
+        self.ratio
+    }
+
+    /// Sets the sweep window in samples. Shorter windows track transients
+    /// better, longer ones sound smoother on sustained tones.
+    pub fn set_window(&mut self, samples: f32) {
+        self.window = samples.clamp(16.0, self.line.max_delay() as f32 - 2.0);
+    }
+
+    /// Average delay through the shifter, in samples.
+    pub fn latency(&self) -> usize {
+        (self.window * 0.5) as usize
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.line.push(input);
+
+        self.phase += (1.0 - self.ratio) / self.window;
+        self.phase -= self.phase.floor();
+
+        let phase_b = (self.phase + 0.5) % 1.0;
+        let gain_a = (PI * self.phase).sin().powi(2);
+        let gain_b = (PI * phase_b).sin().powi(2);
+
+        let tap_a = self.line.read_frac(1.0 + self.phase * self.window);
+        let tap_b = self.line.read_frac(1.0 + phase_b * self.window);
+
+        tap_a * gain_a + tap_b * gain_b
+    }
+
+    pub fn reset(&mut self) {
+        self.line.clear();
+        self.phase = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zero_crossings(samples: &[f32]) -> usize {
+        samples
+            .windows(2)
+            .filter(|w| w[0] <= 0.0 && w[1] > 0.0)
+            .count()
+    }
+
+    #[test]
+    fn test_octave_down_halves_frequency() {
+        let sample_rate = 48_000.0;
+        let mut shifter = PitchShifter::new(2048);
+        shifter.set_ratio(0.5);
+
+        let output: Vec<f32> = (0..48_000)
+            .map(|i| {
+                let t = i as f32 / sample_rate;
+                shifter.process((2.0 * std::f32::consts::PI * 440.0 * t).sin())
+            })
+            .collect();
+
+        let crossings = zero_crossings(&output[4800..]);
+        let expected = 220.0 * 0.9;
+        assert!(
+            (crossings as f32 - expected).abs() < 15.0,
+            "got {crossings} crossings"
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::f32::consts::PI;
+
+/// Time-domain pitch-synchronous overlap-add (TD-PSOLA) shifter.
+///
+/// Grains two analysis periods long are cut from the input at period
+/// spacing and overlap-added at the *synthesis* period, which changes the
+/// pitch without resampling the grains, so the spectral envelope (the
+/// formants) stays put. The formant factor optionally resamples each grain
+/// to move the envelope independently of pitch.
+///
+/// The caller supplies the analysis period, usually from a
+/// [`PitchDetector`](super::PitchDetector).
+pub struct Psola {
+    input: Vec<f32>,
+    output: Vec<f32>,
+    norm: Vec<f32>,
+    mask: usize,
+    clock: i64,
+    max_period: f32,
+    max_half: f32,
+    max_stretch: f32,
+    lookahead: i64,
+    latency: i64,
+    period: f32,
+    ratio: f32,
+    formant: f32,
+    next_synthesis: f64,
+    analysis_mark: f64,
+}
+
+impl Psola {
+    /// `max_period` is the longest period (in samples) that will be supplied;
+    /// `max_stretch` bounds both the pitch ratio and the formant factor to
+    /// `[1 / max_stretch, max_stretch]`. Larger values cost latency.
+    pub fn new(max_period: usize, max_stretch: f32) -> Self {
+        let max_stretch = max_stretch.max(1.0);
+        let max_half = (max_period as f32 * max_stretch).ceil();
+        let lookahead = (max_half * max_stretch).ceil() as i64 + 2;
+        let latency = lookahead + 2 * max_half as i64 + 2;
+        let size = ((latency + lookahead) as usize + max_period + 4).next_power_of_two();
+
+        Self {
+            input: vec![0.0; size],
+            output: vec![0.0; size],
+            norm: vec![0.0; size],
+            mask: size - 1,
+            clock: 0,
+            max_period: max_period as f32,
+            max_half,
+            max_stretch,
+            lookahead,
+            latency,
+            period: max_period as f32 * 0.5,
+            ratio: 1.0,
+            formant: 1.0,
+            next_synthesis: 0.0,
+            analysis_mark: 0.0,
+        }
+    }
+
+    pub fn latency(&self) -> usize {
+        self.latency as usize
+    }
+
+    /// Sets the analysis period of the incoming signal in samples.
+    pub fn set_period(&mut self, period: f32) {
+        self.period = period.clamp(2.0, self.max_period);
+    }
+
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.ratio = ratio.clamp(1.0 / self.max_stretch, self.max_stretch);
+    }
+
+    /// Scales the spectral envelope: 1.0 preserves formants, values above one
+    /// move them up, below one move them down.
+    pub fn set_formant(&mut self, factor: f32) {
+        self.formant = factor.clamp(1.0 / self.max_stretch, self.max_stretch);
+    }
+
+    pub fn process(&mut self, sample: f32) -> f32 {
+        self.input[self.clock as usize & self.mask] = sample;
+
+        while self.next_synthesis + self.lookahead as f64 <= self.clock as f64 {
+            self.place_grain();
+        }
+
+        // Nothing is due until the first input sample has been through the
+        // whole latency; reading early would drain slots still being built.
+        let out = if self.clock < self.latency {
+            0.0
+        } else {
+            let emit = (self.clock - self.latency) as usize & self.mask;
+            let out = self.output[emit] / self.norm[emit].max(1.0);
+            self.output[emit] = 0.0;
+            self.norm[emit] = 0.0;
+            out
+        };
+
+        self.clock += 1;
+        out
+    }
+
+    pub fn reset(&mut self) {
+        self.input.iter_mut().for_each(|s| *s = 0.0);
+        self.output.iter_mut().for_each(|s| *s = 0.0);
+        self.norm.iter_mut().for_each(|s| *s = 0.0);
+        self.clock = 0;
+        self.next_synthesis = 0.0;
+        self.analysis_mark = 0.0;
+    }
+
+    fn place_grain(&mut self) {
+        let synthesis = self.next_synthesis;
+        let period = self.period as f64;
+
+        // Analysis marks advance one input period at a time; the grain comes
+        // from the latest mark at or before the synthesis position.
+        if self.analysis_mark > synthesis {
+            self.analysis_mark = synthesis;
+        }
+        while self.analysis_mark + period <= synthesis {
+            self.analysis_mark += period;
+        }
+
+        let spacing = self.period / self.ratio;
+        let half = self.period.max(spacing).min(self.max_half);
+        let reach = half as i64;
+        let centre = synthesis.round() as i64;
+
+        for j in -reach..=reach {
+            let index = centre + j;
+            if index < 0 {
+                continue;
+            }
+            let weight = 0.5 + 0.5 * (PI * j as f32 / half).cos();
+            let source = self.analysis_mark + (j as f32 * self.formant) as f64;
+            let value = self.read_input(source);
+            let slot = index as usize & self.mask;
+            self.output[slot] += value * weight;
+            self.norm[slot] += weight;
+        }
+
+        self.next_synthesis += spacing as f64;
+    }
+
+    fn read_input(&self, position: f64) -> f32 {
+        if position < 0.0 {
+            return 0.0;
+        }
+        let whole = position.floor();
+        let frac = (position - whole) as f32;
+        let i = whole as usize;
+        let a = self.input[i & self.mask];
+        let b = self.input[(i + 1) & self.mask];
+        a + (b - a) * frac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unity_ratio_reproduces_delayed_input() {
+        let period = 100.0;
+        let mut psola = Psola::new(200, 1.25);
+        psola.set_period(period);
+
+        let input: Vec<f32> = (0..20_000)
+            .map(|i| (2.0 * PI * i as f32 / period).sin())
+            .collect();
+        let output: Vec<f32> = input.iter().map(|&x| psola.process(x)).collect();
+
+        let latency = psola.latency();
+        let error = (10_000..19_000)
+            .map(|i| (output[i] - input[i - latency]).abs())
+            .fold(0.0_f32, f32::max);
+        assert!(error < 0.05, "max error {error}");
+    }
+
+    #[test]
+    fn test_impulse_comes_out_after_exactly_the_latency() {
+        let mut psola = Psola::new(600, 1.25);
+        for _ in 0..2 {
+            let latency = psola.latency();
+            let output: Vec<f32> = (0..latency + 1000)
+                .map(|i| psola.process(if i == 0 { 1.0 } else { 0.0 }))
+                .collect();
+            let first = output.iter().position(|s| s.abs() > 1e-6);
+            assert_eq!(first, Some(latency));
+            assert!((output[latency] - 1.0).abs() < 1e-3, "{}", output[latency]);
+            // Same again from a clean start.
+            psola.reset();
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use super::{flush_denormal, DelayLine};
+
+/// Base delay lengths in milliseconds, mutually prime-ish so the modes of
+/// the network don't pile up on the same frequencies.
+const LINE_MS: [f32; 8] = [29.7, 37.1, 41.1, 43.7, 53.3, 59.9, 67.1, 73.4];
+/// Largest `size` scale applied to [`LINE_MS`].
+const MAX_SCALE: f32 = 1.6;
+
+/// Eight-line feedback delay network reverb with a Householder mixing
+/// matrix and per-line damping.
+///
+/// Mono in, stereo out. Used directly by the reverb effect and as the late
+/// tail wherever something needs a diffuse room.
+pub struct Fdn {
+    sample_rate: f32,
+    lines: Vec<DelayLine>,
+    lengths: [usize; 8],
+    gains: [f32; 8],
+    lowpass: [f32; 8],
+    size: f32,
+    decay: f32,
+    damping: f32,
+}
+
+impl Fdn {
+    pub fn new(sample_rate: f32) -> Self {
+        let max_len = (LINE_MS[7] * MAX_SCALE * 0.001 * sample_rate).ceil() as usize;
+        let mut fdn = Self {
+            sample_rate,
+            lines: (0..8).map(|_| DelayLine::new(max_len + 1)).collect(),
+            lengths: [1; 8],
+            gains: [0.0; 8],
+            lowpass: [0.0; 8],
+            size: 0.5,
+            decay: 2.0,
+            damping: 0.3,
+        };
+        fdn.update();
+        fdn
+    }
+
+    /// Room size from 0 (small) to 1 (large); scales every line length.
+    pub fn set_size(&mut self, size: f32) {
+        self.size = size.clamp(0.0, 1.0);
+        self.update();
+    }
+
+    /// Time for the tail to fall by 60 dB, in seconds.
+    pub fn set_decay(&mut self, seconds: f32) {
+        self.decay = seconds.max(0.05);
+        self.update();
+    }
+
+    /// High-frequency damping from 0 (bright) to 1 (dark).
+    pub fn set_damping(&mut self, damping: f32) {
+        self.damping = damping.clamp(0.0, 0.95);
+    }
+
+    pub fn process(&mut self, input: f32) -> (f32, f32) {
+        let mut taps = [0.0; 8];
+        for (i, tap) in taps.iter_mut().enumerate() {
+            let raw = self.lines[i].read(self.lengths[i] - 1);
+            self.lowpass[i] = flush_denormal(raw + (self.lowpass[i] - raw) * self.damping);
+            *tap = self.lowpass[i] * self.gains[i];
+        }
+
+        // Householder reflection: lossless, and every line feeds every other.
+        let reflect = taps.iter().sum::<f32>() * 0.25;
+        let feed = input * 0.35;
+        for (line, tap) in self.lines.iter_mut().zip(taps.iter()) {
+            line.push(tap - reflect + feed);
+        }
+
+        let left = taps[0] - taps[2] + taps[4] - taps[6];
+        let right = taps[1] - taps[3] + taps[5] - taps[7];
+        (left * 0.5, right * 0.5)
+    }
+
+    pub fn reset(&mut self) {
+        self.lines.iter_mut().for_each(|l| l.clear());
+        self.lowpass = [0.0; 8];
+    }
+
+    fn update(&mut self) {
+        let scale = 0.3 + self.size * (MAX_SCALE - 0.3);
+        for (i, ms) in LINE_MS.iter().enumerate() {
+            let len = (ms * scale * 0.001 * self.sample_rate) as usize;
+            self.lengths[i] = len.clamp(2, self.lines[i].max_delay());
+            let seconds = self.lengths[i] as f32 / self.sample_rate;
+            self.gains[i] = 10.0_f32.powf(-3.0 * seconds / self.decay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn energy(fdn: &mut Fdn, samples: usize) -> f32 {
+        (0..samples)
+            .map(|_| {
+                let (l, r) = fdn.process(0.0);
+                l * l + r * r
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_impulse_tail_decays() {
+        let mut fdn = Fdn::new(48_000.0);
+        fdn.set_decay(1.0);
+        fdn.process(1.0);
+
+        let early = energy(&mut fdn, 12_000);
+        let _ = energy(&mut fdn, 36_000);
+        let late = energy(&mut fdn, 12_000);
+
+        assert!(early > 0.0);
+        assert!(late < early * 0.01, "early {early}, late {late}");
+    }
+}
@@ -0,0 +1,321 @@
+use super::{flush_denormal, hrtf, DelayLine, Fdn};
+use std::f32::consts::{FRAC_PI_4, PI};
+
+const SPEED_OF_SOUND: f32 = 343.0;
+/// Highest reflection order traced; the FDN tail takes over after that.
+const ORDER: i32 = 3;
+/// Image sources up to [`ORDER`], not counting the direct path.
+const MAX_TAPS: usize = 62;
+/// Reflections arriving later than this after the direct sound are left to
+/// the tail.
+const MAX_REFLECTION_MS: f32 = 250.0;
+const MIN_SIDE_M: f32 = 1.0;
+const MAX_SIDE_M: f32 = 100.0;
+/// Keeps sources and listener off the walls, so no image lands on top of
+/// the listener.
+const WALL_MARGIN_M: f32 = 0.1;
+
+/// A shoebox room: width (x, left to right), depth (y, back to front) and
+/// height (z, floor to ceiling) in metres, plus the share of sound energy
+/// every surface absorbs on each bounce.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoomGeometry {
+    pub size: [f32; 3],
+    pub absorption: f32,
+}
+
+impl RoomGeometry {
+    pub fn new(width: f32, depth: f32, height: f32, absorption: f32) -> Self {
+        // NaN falls back to the smallest room and hardest walls.
+        let bounded = |x: f32, min: f32, max: f32| if x.is_nan() { min } else { x.clamp(min, max) };
+        Self {
+            size: [width, depth, height].map(|side| bounded(side, MIN_SIDE_M, MAX_SIDE_M)),
+            absorption: bounded(absorption, 0.01, 1.0),
+        }
+    }
+
+    /// Sabine reverberation time in seconds.
+    pub fn rt60(&self) -> f32 {
+        0.161 * self.volume() / (self.surface() * self.absorption)
+    }
+
+    /// Average distance sound travels between two bounces.
+    pub fn mean_free_path(&self) -> f32 {
+        4.0 * self.volume() / self.surface()
+    }
+
+    /// Sets up `fdn` as this room's late tail: the decay follows
+    /// [`rt60`](Self::rt60), line lengths follow the mean free path and
+    /// softer walls damp the highs harder.
+    pub fn configure_tail(&self, fdn: &mut Fdn) {
+        fdn.set_decay(self.rt60().min(10.0));
+        fdn.set_size((self.mean_free_path() / 10.0).clamp(0.0, 1.0));
+        fdn.set_damping(0.2 + 0.6 * self.absorption);
+    }
+
+    /// Centre of the floor plan at standing ear height.
+    pub fn centre(&self) -> [f32; 3] {
+        let [width, depth, _] = self.size;
+        self.clamp([width * 0.5, depth * 0.5, 1.6])
+    }
+
+    /// Moves `point` inside the room.
+    pub fn clamp(&self, point: [f32; 3]) -> [f32; 3] {
+        // `max` and `min` rather than `clamp` so NaN lands on a wall too.
+        std::array::from_fn(|i| {
+            point[i]
+                .max(WALL_MARGIN_M)
+                .min(self.size[i] - WALL_MARGIN_M)
+        })
+    }
+
+    fn volume(&self) -> f32 {
+        self.size.iter().product()
+    }
+
+    fn surface(&self) -> f32 {
+        let [w, d, h] = self.size;
+        2.0 * (w * d + w * h + d * h)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Tap {
+    /// Samples after the direct sound.
+    delay: f32,
+    gains: [f32; 2],
+}
+
+/// Early reflections of one source in a [`RoomGeometry`], traced with the
+/// image-source method up to third order.
+///
+/// Each mirror image of the source becomes a delay tap timed relative to
+/// the direct sound, attenuated by its path length and by the walls it
+/// bounced off, and panned by the side it arrives from. The direct sound
+/// itself is left out: it's rendered by whatever places the source (the
+/// [`BinauralPanner`](super::BinauralPanner) in the mixer). Mono in,
+/// stereo out; a mono sum of the output is the natural feed for the
+/// room's [`Fdn`] tail.
+pub struct EarlyReflections {
+    sample_rate: f32,
+    room: RoomGeometry,
+    listener: [f32; 3],
+    /// Source relative to the listener, as for the binaural panner.
+    azimuth: f32,
+    elevation: f32,
+    distance: f32,
+    taps: [Tap; MAX_TAPS],
+    count: usize,
+    previous: [Tap; MAX_TAPS],
+    previous_count: usize,
+    crossfade: bool,
+    delay: DelayLine,
+    /// Surfaces soak up the highs first.
+    lowpass: [f32; 2],
+    lowpass_coeff: f32,
+}
+
+impl EarlyReflections {
+    pub fn new(sample_rate: f32, room: RoomGeometry) -> Self {
+        let max_delay = MAX_REFLECTION_MS * 0.001 * sample_rate;
+        let mut early = Self {
+            sample_rate,
+            room,
+            listener: room.centre(),
+            azimuth: 0.0,
+            elevation: 0.0,
+            distance: 1.0,
+            taps: [Tap::default(); MAX_TAPS],
+            count: 0,
+            previous: [Tap::default(); MAX_TAPS],
+            previous_count: 0,
+            crossfade: false,
+            delay: DelayLine::new(max_delay.ceil() as usize + 2),
+            lowpass: [0.0; 2],
+            lowpass_coeff: 1.0,
+        };
+        early.update();
+        early.reset();
+        early
+    }
+
+    pub fn set_room(&mut self, room: RoomGeometry) {
+        self.room = room;
+        self.update();
+    }
+
+    /// Listener position in metres from the back left corner at floor
+    /// level; clamped inside the room.
+    pub fn set_listener(&mut self, position: [f32; 3]) {
+        self.listener = position;
+        self.update();
+    }
+
+    /// Source position relative to the listener: azimuth in degrees
+    /// clockwise from the front, elevation in degrees, distance in metres.
+    /// A source placed beyond a wall is pulled back inside.
+    pub fn set_source(&mut self, azimuth_deg: f32, elevation_deg: f32, distance_m: f32) {
+        self.azimuth = azimuth_deg;
+        self.elevation = elevation_deg;
+        self.distance = distance_m.clamp(0.1, 1000.0);
+        self.update();
+    }
+
+    /// Number of reflections currently traced.
+    pub fn reflections(&self) -> usize {
+        self.count
+    }
+
+    /// Renders the reflections of `input` into `left` and `right`. Changes
+    /// since the last call are crossfaded over this block.
+    pub fn process(&mut self, input: &[f32], left: &mut [f32], right: &mut [f32]) {
+        let step = 1.0 / input.len().max(1) as f32;
+        for (i, ((&x, l), r)) in input
+            .iter()
+            .zip(left.iter_mut())
+            .zip(right.iter_mut())
+            .enumerate()
+        {
+            self.delay.push(x);
+            let mut wet = render(&self.delay, &self.taps[..self.count]);
+            if self.crossfade {
+                let old = render(&self.delay, &self.previous[..self.previous_count]);
+                let fade = (i + 1) as f32 * step;
+                for (wet, old) in wet.iter_mut().zip(old) {
+                    *wet = old + (*wet - old) * fade;
+                }
+            }
+            for (state, wet) in self.lowpass.iter_mut().zip(wet) {
+                *state = flush_denormal(*state + (wet - *state) * self.lowpass_coeff);
+            }
+            *l = self.lowpass[0];
+            *r = self.lowpass[1];
+        }
+        self.crossfade = false;
+    }
+
+    /// Clears the signal state and jumps straight to the current taps.
+    pub fn reset(&mut self) {
+        self.delay.clear();
+        self.lowpass = [0.0; 2];
+        self.crossfade = false;
+    }
+
+    fn update(&mut self) {
+        // Several changes within one block all fade from what was heard last.
+        if !self.crossfade {
+            self.previous = self.taps;
+            self.previous_count = self.count;
+            self.crossfade = true;
+        }
+
+        let room = self.room;
+        let listener = room.clamp(self.listener);
+        let toward = hrtf::direction(self.azimuth, self.elevation);
+        let source = room.clamp([0, 1, 2].map(|i| listener[i] + toward[i] * self.distance));
+        let direct = length(sub(source, listener));
+        let reflection = (1.0 - room.absorption).sqrt();
+        let max_delay = MAX_REFLECTION_MS * 0.001 * self.sample_rate;
+
+        self.count = 0;
+        for i in -ORDER..=ORDER {
+            for j in -ORDER..=ORDER {
+                for k in -ORDER..=ORDER {
+                    let order = i.abs() + j.abs() + k.abs();
+                    if order == 0 || order > ORDER {
+                        continue;
+                    }
+                    let image = [
+                        image(i, source[0], room.size[0]),
+                        image(j, source[1], room.size[1]),
+                        image(k, source[2], room.size[2]),
+                    ];
+                    let path = sub(image, listener);
+                    let distance = length(path);
+                    let delay = (distance - direct) / SPEED_OF_SOUND * self.sample_rate;
+                    if delay > max_delay {
+                        continue;
+                    }
+                    // Same 1 m reference as the panner's direct sound.
+                    let gain = reflection.powi(order) / distance.max(0.5);
+                    let angle = (path[0] / distance + 1.0) * FRAC_PI_4;
+                    self.taps[self.count] = Tap {
+                        delay,
+                        gains: [gain * angle.cos(), gain * angle.sin()],
+                    };
+                    self.count += 1;
+                }
+            }
+        }
+
+        let cutoff = (12_000.0 - 9_000.0 * room.absorption).min(self.sample_rate * 0.45);
+        self.lowpass_coeff = 1.0 - (-2.0 * PI * cutoff / self.sample_rate).exp();
+    }
+}
+
+/// Coordinate of the source's mirror image `n` reflections along an axis
+/// of length `side`; odd images are flipped.
+fn image(n: i32, source: f32, side: f32) -> f32 {
+    if n % 2 == 0 {
+        n as f32 * side + source
+    } else {
+        (n + 1) as f32 * side - source
+    }
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn length(v: [f32; 3]) -> f32 {
+    v.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+fn render(delay: &DelayLine, taps: &[Tap]) -> [f32; 2] {
+    taps.iter().fold([0.0; 2], |[l, r], tap| {
+        let sample = delay.read_frac(tap.delay);
+        [l + sample * tap.gains[0], r + sample * tap.gains[1]]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_reflection_arrives_from_the_nearest_wall() {
+        let room = RoomGeometry::new(10.0, 10.0, 10.0, 0.3);
+        let mut early = EarlyReflections::new(48_000.0, room);
+        early.set_listener([5.0, 5.0, 5.0]);
+        early.set_source(0.0, 0.0, 2.0);
+        early.reset();
+        assert_eq!(early.reflections(), MAX_TAPS);
+
+        let mut input = vec![0.0; 4096];
+        input[0] = 1.0;
+        let mut left = vec![0.0; 4096];
+        let mut right = vec![0.0; 4096];
+        early.process(&input, &mut left, &mut right);
+
+        // Front wall image 8 m away against 2 m direct: (8 - 2) / 343 s.
+        let expected = 6.0 / SPEED_OF_SOUND * 48_000.0;
+        let first = left.iter().position(|s| s.abs() > 1e-6).unwrap();
+        assert!(
+            (first as f32 - expected).abs() < 2.0,
+            "{first} vs {expected}"
+        );
+        // A source straight ahead in a centred room is mirrored evenly.
+        let energy = |s: &[f32]| s.iter().map(|x| x * x).sum::<f32>();
+        assert!((energy(&left) - energy(&right)).abs() < energy(&left) * 1e-3);
+    }
+
+    #[test]
+    fn test_harder_bigger_rooms_ring_longer() {
+        let booth = RoomGeometry::new(2.0, 2.0, 2.2, 0.6);
+        let hall = RoomGeometry::new(30.0, 20.0, 12.0, 0.2);
+        assert!(booth.rt60() < 0.2, "{}", booth.rt60());
+        assert!(hall.rt60() > 2.0, "{}", hall.rt60());
+        assert!(booth.mean_free_path() < hall.mean_free_path());
+        assert_eq!(hall.clamp([-3.0, 50.0, f32::NAN]), [0.1, 19.9, 0.1]);
+    }
+}
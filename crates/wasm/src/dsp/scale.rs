@@ -0,0 +1,100 @@
+/// Musical scales used to quantize pitch, indexed the way the `scale`
+/// parameters expose them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scale {
+    Chromatic,
+    Major,
+    Minor,
+    HarmonicMinor,
+    MajorPentatonic,
+    MinorPentatonic,
+    Blues,
+    Dorian,
+    Mixolydian,
+}
+
+impl Scale {
+    pub const ALL: [Scale; 9] = [
+        Scale::Chromatic,
+        Scale::Major,
+        Scale::Minor,
+        Scale::HarmonicMinor,
+        Scale::MajorPentatonic,
+        Scale::MinorPentatonic,
+        Scale::Blues,
+        Scale::Dorian,
+        Scale::Mixolydian,
+    ];
+
+    pub fn from_index(index: usize) -> Self {
+        Self::ALL[index.min(Self::ALL.len() - 1)]
+    }
+
+    /// Semitone offsets from the root that belong to the scale.
+    pub fn intervals(self) -> &'static [u8] {
+        match self {
+            Scale::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            Scale::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Scale::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            Scale::HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            Scale::MajorPentatonic => &[0, 2, 4, 7, 9],
+            Scale::MinorPentatonic => &[0, 3, 5, 7, 10],
+            Scale::Blues => &[0, 3, 5, 6, 7, 10],
+            Scale::Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            Scale::Mixolydian => &[0, 2, 4, 5, 7, 9, 10],
+        }
+    }
+
+    /// Whether the whole MIDI note `note` is in this scale rooted at `key`.
+    pub fn contains(self, note: i32, key: i32) -> bool {
+        let class = (note - key).rem_euclid(12) as u8;
+        self.intervals().contains(&class)
+    }
+
+    /// Nearest whole note in the scale to a fractional MIDI note.
+    pub fn quantize(self, note: f32, key: i32) -> i32 {
+        let centre = note.round() as i32;
+        (centre - 6..=centre + 6)
+            .filter(|&n| self.contains(n, key))
+            .min_by(|&a, &b| {
+                let da = (a as f32 - note).abs();
+                let db = (b as f32 - note).abs();
+                da.total_cmp(&db)
+            })
+            .unwrap_or(centre)
+    }
+
+    /// Note `steps` scale degrees away from `note`, which should itself be in
+    /// the scale. Negative steps go down.
+    pub fn transpose(self, note: i32, key: i32, steps: i32) -> i32 {
+        let mut result = note;
+        for _ in 0..steps.unsigned_abs() {
+            result += steps.signum();
+            while !self.contains(result, key) {
+                result += steps.signum();
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantize_to_c_major() {
+        // C#4 is not in C major, so notes around it go to C or D.
+        assert_eq!(Scale::Major.quantize(60.8, 0), 60);
+        assert_eq!(Scale::Major.quantize(61.6, 0), 62);
+        assert_eq!(Scale::Chromatic.quantize(61.6, 0), 62);
+    }
+
+    #[test]
+    fn test_transpose_by_degrees() {
+        // A diatonic third above C in C major is E, above E it is G.
+        assert_eq!(Scale::Major.transpose(60, 0, 2), 64);
+        assert_eq!(Scale::Major.transpose(64, 0, 2), 67);
+        assert_eq!(Scale::Major.transpose(60, 0, -1), 59);
+    }
+}
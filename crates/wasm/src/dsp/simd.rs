@@ -0,0 +1,295 @@
+//! Inner loops with `wasm32` `simd128` versions.
+//!
+//! The vector paths are compiled only when the module is built with
+//! `-C target-feature=+simd128` (`./build.sh --simd`); everywhere else,
+//! including native tests, the scalar fallbacks in [`scalar`] are used.
+//! Both paths must produce the same results up to float reassociation;
+//! `./build.sh --simd` checks that under node before building.
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+use core::arch::wasm32::*;
+
+#[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+pub use scalar::{dot, mix_into, peak_and_energy, scale};
+
+/// The fallbacks, also compiled into simd128 test builds to compare
+/// against.
+#[cfg(any(test, not(all(target_arch = "wasm32", target_feature = "simd128"))))]
+mod scalar {
+    use super::Biquad4;
+
+    pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    pub fn mix_into(dst: &mut [f32], src: &[f32], gain: f32) {
+        for (d, s) in dst.iter_mut().zip(src) {
+            *d += s * gain;
+        }
+    }
+
+    pub fn scale(buffer: &mut [f32], gain: f32) {
+        for s in buffer.iter_mut() {
+            *s *= gain;
+        }
+    }
+
+    pub fn peak_and_energy(buffer: &[f32]) -> (f32, f32) {
+        buffer.iter().fold((0.0, 0.0), |(peak, energy), &x| {
+            (f32::max(peak, x.abs()), energy + x * x)
+        })
+    }
+
+    pub fn biquad4(f: &mut Biquad4, input: [f32; 4]) -> [f32; 4] {
+        let mut output = [0.0; 4];
+        for lane in 0..4 {
+            let x = input[lane];
+            let y = f.b0[lane] * x + f.z1[lane];
+            f.z1[lane] = crate::dsp::flush_denormal(f.b1[lane] * x - f.a1[lane] * y + f.z2[lane]);
+            f.z2[lane] = crate::dsp::flush_denormal(f.b2[lane] * x - f.a2[lane] * y);
+            output[lane] = y;
+        }
+        output
+    }
+}
+
+/// Sum of `a[i] * b[i]` over the shorter of the two slices. The inner loop
+/// of every FIR and convolution.
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    let len = a.len().min(b.len());
+    let split = len - len % 4;
+    let mut acc = f32x4_splat(0.0);
+    for i in (0..split).step_by(4) {
+        // SAFETY: `i + 4 <= split <= len` for both slices; loads are unaligned.
+        let (x, y) = unsafe {
+            (
+                v128_load(a.as_ptr().add(i) as *const v128),
+                v128_load(b.as_ptr().add(i) as *const v128),
+            )
+        };
+        acc = f32x4_add(acc, f32x4_mul(x, y));
+    }
+    let tail: f32 = a[split..len]
+        .iter()
+        .zip(&b[split..len])
+        .map(|(x, y)| x * y)
+        .sum();
+    horizontal_sum(acc) + tail
+}
+
+/// `dst[i] += src[i] * gain`, for summing buses.
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+pub fn mix_into(dst: &mut [f32], src: &[f32], gain: f32) {
+    let len = dst.len().min(src.len());
+    let split = len - len % 4;
+    let g = f32x4_splat(gain);
+    for i in (0..split).step_by(4) {
+        // SAFETY: `i + 4 <= split <= len` for both slices; loads and stores
+        // are unaligned.
+        unsafe {
+            let d = dst.as_mut_ptr().add(i) as *mut v128;
+            let s = v128_load(src.as_ptr().add(i) as *const v128);
+            v128_store(d, f32x4_add(v128_load(d), f32x4_mul(s, g)));
+        }
+    }
+    for (d, s) in dst[split..len].iter_mut().zip(&src[split..len]) {
+        *d += s * gain;
+    }
+}
+
+/// Multiplies every sample by `gain` in place.
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+pub fn scale(buffer: &mut [f32], gain: f32) {
+    let split = buffer.len() - buffer.len() % 4;
+    let g = f32x4_splat(gain);
+    for i in (0..split).step_by(4) {
+        // SAFETY: `i + 4 <= split <= buffer.len()`; unaligned access.
+        unsafe {
+            let p = buffer.as_mut_ptr().add(i) as *mut v128;
+            v128_store(p, f32x4_mul(v128_load(p), g));
+        }
+    }
+    for s in buffer[split..].iter_mut() {
+        *s *= gain;
+    }
+}
+
+/// Peak absolute value and sum of squares, for level meters.
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+pub fn peak_and_energy(buffer: &[f32]) -> (f32, f32) {
+    let split = buffer.len() - buffer.len() % 4;
+    let mut peak = f32x4_splat(0.0);
+    let mut energy = f32x4_splat(0.0);
+    for i in (0..split).step_by(4) {
+        // SAFETY: `i + 4 <= split <= buffer.len()`; unaligned load.
+        let x = unsafe { v128_load(buffer.as_ptr().add(i) as *const v128) };
+        peak = f32x4_max(peak, f32x4_abs(x));
+        energy = f32x4_add(energy, f32x4_mul(x, x));
+    }
+    let mut peak = f32x4_extract_lane::<0>(peak)
+        .max(f32x4_extract_lane::<1>(peak))
+        .max(f32x4_extract_lane::<2>(peak))
+        .max(f32x4_extract_lane::<3>(peak));
+    let mut energy = horizontal_sum(energy);
+    for &x in &buffer[split..] {
+        peak = peak.max(x.abs());
+        energy += x * x;
+    }
+    (peak, energy)
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+fn horizontal_sum(v: v128) -> f32 {
+    f32x4_extract_lane::<0>(v)
+        + f32x4_extract_lane::<1>(v)
+        + f32x4_extract_lane::<2>(v)
+        + f32x4_extract_lane::<3>(v)
+}
+
+/// Four independent biquads run side by side, one per lane: four channels,
+/// or four bands of a filter bank, for the price of one.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Biquad4 {
+    b0: [f32; 4],
+    b1: [f32; 4],
+    b2: [f32; 4],
+    a1: [f32; 4],
+    a2: [f32; 4],
+    z1: [f32; 4],
+    z2: [f32; 4],
+}
+
+impl Biquad4 {
+    /// Copies the coefficients of `filter` into `lane`, keeping its state.
+    pub fn set_lane(&mut self, lane: usize, filter: &super::Biquad) {
+        let [b0, b1, b2, a1, a2] = filter.coefficients();
+        self.b0[lane] = b0;
+        self.b1[lane] = b1;
+        self.b2[lane] = b2;
+        self.a1[lane] = a1;
+        self.a2[lane] = a2;
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    pub fn process(&mut self, input: [f32; 4]) -> [f32; 4] {
+        let load = |a: &[f32; 4]| f32x4(a[0], a[1], a[2], a[3]);
+        let store = |v: v128| {
+            [
+                f32x4_extract_lane::<0>(v),
+                f32x4_extract_lane::<1>(v),
+                f32x4_extract_lane::<2>(v),
+                f32x4_extract_lane::<3>(v),
+            ]
+        };
+        let x = load(&input);
+        let y = f32x4_add(f32x4_mul(load(&self.b0), x), load(&self.z1));
+        let z1 = f32x4_add(
+            f32x4_sub(f32x4_mul(load(&self.b1), x), f32x4_mul(load(&self.a1), y)),
+            load(&self.z2),
+        );
+        let z2 = f32x4_sub(f32x4_mul(load(&self.b2), x), f32x4_mul(load(&self.a2), y));
+        // Flush subnormal state, as the scalar path does.
+        let tiny = f32x4_splat(1e-20);
+        let flush = |v: v128| v128_andnot(v, f32x4_lt(f32x4_abs(v), tiny));
+        self.z1 = store(flush(z1));
+        self.z2 = store(flush(z2));
+        store(y)
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+    pub fn process(&mut self, input: [f32; 4]) -> [f32; 4] {
+        scalar::biquad4(self, input)
+    }
+
+    pub fn reset(&mut self) {
+        self.z1 = [0.0; 4];
+        self.z2 = [0.0; 4];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::{Biquad, FilterKind};
+
+    #[test]
+    fn test_kernels_match_naive_loops() {
+        let a: Vec<f32> = (0..13).map(|i| i as f32 * 0.5 - 3.0).collect();
+        let b: Vec<f32> = (0..13).map(|i| (i as f32).sin()).collect();
+        let expected: f32 = a.iter().zip(&b).map(|(x, y)| x * y).sum();
+        assert!((dot(&a, &b) - expected).abs() < 1e-4);
+
+        let mut dst = b.clone();
+        mix_into(&mut dst, &a, 0.5);
+        scale(&mut dst, 2.0);
+        for i in 0..13 {
+            assert!((dst[i] - (b[i] + a[i] * 0.5) * 2.0).abs() < 1e-5);
+        }
+
+        let (peak, energy) = peak_and_energy(&a);
+        assert_eq!(peak, 3.0);
+        assert!((energy - a.iter().map(|x| x * x).sum::<f32>()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_biquad4_lanes_match_scalar_biquads() {
+        let mut scalar: Vec<Biquad> = [200.0, 800.0, 2000.0, 6000.0]
+            .iter()
+            .map(|&f| Biquad::new(FilterKind::BandPass, 48_000.0, f, 2.0, 0.0))
+            .collect();
+        let mut lanes = Biquad4::default();
+        for (lane, filter) in scalar.iter().enumerate() {
+            lanes.set_lane(lane, filter);
+        }
+
+        for i in 0..256 {
+            let x = (i as f32 * 0.37).sin();
+            let y = lanes.process([x; 4]);
+            for (lane, filter) in scalar.iter_mut().enumerate() {
+                assert!((y[lane] - filter.process(x)).abs() < 1e-6);
+            }
+        }
+    }
+
+    /// Only built by `./build.sh --simd`, where the kernels above are the
+    /// vector ones.
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn test_simd_matches_scalar() {
+        // Every length up to a few vectors, so each tail size is covered.
+        for len in 0..20 {
+            let a: Vec<f32> = (0..len).map(|i| (i as f32 * 1.3).sin() * 2.0).collect();
+            let b: Vec<f32> = (0..len).map(|i| (i as f32 * 0.7).cos()).collect();
+            assert!((dot(&a, &b) - scalar::dot(&a, &b)).abs() < 1e-4);
+
+            let (mut simd, mut plain) = (b.clone(), b.clone());
+            mix_into(&mut simd, &a, 0.3);
+            scalar::mix_into(&mut plain, &a, 0.3);
+            scale(&mut simd, -1.7);
+            scalar::scale(&mut plain, -1.7);
+            assert_eq!(simd, plain);
+
+            let (peak, energy) = peak_and_energy(&a);
+            let (expected_peak, expected_energy) = scalar::peak_and_energy(&a);
+            assert_eq!(peak, expected_peak);
+            assert!((energy - expected_energy).abs() < 1e-4);
+        }
+
+        // Same operations lane by lane, so the filters agree exactly, down
+        // to the denormal flush as an impulse rings out.
+        let mut simd = Biquad4::default();
+        for (lane, freq) in [200.0, 800.0, 2000.0, 6000.0].into_iter().enumerate() {
+            simd.set_lane(
+                lane,
+                &Biquad::new(FilterKind::BandPass, 48_000.0, freq, 8.0, 0.0),
+            );
+        }
+        let mut plain = simd;
+        for i in 0..48_000 {
+            let x = if i == 0 { 1.0 } else { 0.0 };
+            assert_eq!(simd.process([x; 4]), scalar::biquad4(&mut plain, [x; 4]));
+        }
+        assert_eq!(simd.z1, [0.0; 4]);
+    }
+}
@@ -0,0 +1,205 @@
+use super::fft::{Complex, Fft};
+use std::f32::consts::PI;
+
+/// Analysis and synthesis window shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Window {
+    Hann,
+    Hamming,
+    Blackman,
+}
+
+impl Window {
+    /// Periodic window value at `phase` in `[0, 1)`.
+    pub fn value(self, phase: f32) -> f32 {
+        let x = 2.0 * PI * phase;
+        match self {
+            Window::Hann => 0.5 - 0.5 * x.cos(),
+            Window::Hamming => 0.54 - 0.46 * x.cos(),
+            Window::Blackman => 0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos(),
+        }
+    }
+}
+
+/// Streaming short-time Fourier transform with overlap-add resynthesis.
+///
+/// Samples go in one at a time or a block at a time; every `hop` samples
+/// the last `size` of them are windowed and transformed, the caller edits
+/// the `size / 2 + 1` positive-frequency bins, and the inverse transform is
+/// windowed again and overlap-added into the output. Left alone, the bins
+/// come back out as the input delayed by [`latency`] samples.
+///
+/// The same window is used for analysis and synthesis, and each output
+/// sample is divided by the squared windows that overlap it, so unedited
+/// bins reconstruct exactly for any window and any hop up to half the size.
+///
+/// [`latency`]: Stft::latency
+pub struct Stft {
+    fft: Fft,
+    hop: usize,
+    window: Vec<f32>,
+    /// Output gain per position within a hop, undoing the overlapping
+    /// squared windows.
+    norm: Vec<f32>,
+    input: Vec<f32>,
+    output: Vec<f32>,
+    accumulator: Vec<f32>,
+    frame: Vec<Complex>,
+    /// Next write position in `input`, from `size - hop` up to `size`.
+    position: usize,
+}
+
+impl Stft {
+    /// `size` must be a power of two and `hop` must divide it.
+    pub fn new(size: usize, hop: usize, window: Window) -> Self {
+        assert!(
+            hop > 0 && hop <= size / 2 && size.is_multiple_of(hop),
+            "hop must divide the FFT size and overlap frames"
+        );
+        let window: Vec<f32> = (0..size)
+            .map(|i| window.value(i as f32 / size as f32))
+            .collect();
+        let norm = (0..hop)
+            .map(|j| 1.0 / window[j..].iter().step_by(hop).map(|w| w * w).sum::<f32>())
+            .collect();
+        Self {
+            fft: Fft::new(size),
+            hop,
+            norm,
+            window,
+            input: vec![0.0; size],
+            output: vec![0.0; hop],
+            accumulator: vec![0.0; size],
+            frame: vec![Complex::ZERO; size],
+            position: size - hop,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.window.len()
+    }
+
+    pub fn hop(&self) -> usize {
+        self.hop
+    }
+
+    /// Number of positive-frequency bins handed to the callback.
+    pub fn bins(&self) -> usize {
+        self.size() / 2 + 1
+    }
+
+    /// Delay between a sample going in and coming back out, in samples:
+    /// a whole frame, since the oldest sample of a frame is only complete
+    /// once the newest has arrived.
+    pub fn latency(&self) -> usize {
+        self.size()
+    }
+
+    /// Runs `buffer` through in place, calling `edit` with the bins of
+    /// every completed frame.
+    pub fn process(&mut self, buffer: &mut [f32], mut edit: impl FnMut(&mut [Complex])) {
+        let start = self.size() - self.hop;
+        for sample in buffer.iter_mut() {
+            self.input[self.position] = *sample;
+            *sample = self.output[self.position - start];
+            self.position += 1;
+            if self.position == self.size() {
+                self.position = start;
+                self.run_frame(&mut edit);
+            }
+        }
+    }
+
+    fn run_frame(&mut self, edit: &mut impl FnMut(&mut [Complex])) {
+        let size = self.size();
+        let bins = self.bins();
+        for ((bin, &x), &w) in self.frame.iter_mut().zip(&self.input).zip(&self.window) {
+            *bin = Complex::new(x * w, 0.0);
+        }
+        self.fft.forward(&mut self.frame);
+        edit(&mut self.frame[..bins]);
+        // Restore the conjugate symmetry a real signal's spectrum has.
+        self.frame[0].im = 0.0;
+        self.frame[bins - 1].im = 0.0;
+        for k in bins..size {
+            self.frame[k] = self.frame[size - k].conj();
+        }
+        self.fft.inverse(&mut self.frame);
+
+        for ((acc, bin), &w) in self
+            .accumulator
+            .iter_mut()
+            .zip(&self.frame)
+            .zip(&self.window)
+        {
+            *acc += bin.re * w;
+        }
+        for ((out, &acc), &norm) in self
+            .output
+            .iter_mut()
+            .zip(&self.accumulator)
+            .zip(&self.norm)
+        {
+            *out = acc * norm;
+        }
+        self.accumulator.copy_within(self.hop.., 0);
+        self.accumulator[size - self.hop..].fill(0.0);
+        self.input.copy_within(self.hop.., 0);
+    }
+
+    pub fn reset(&mut self) {
+        self.input.fill(0.0);
+        self.output.fill(0.0);
+        self.accumulator.fill(0.0);
+        self.position = self.size() - self.hop;
+    }
+}
+
+/// Power-of-two frame of roughly 20 ms at `sample_rate` (1024 at 48 kHz),
+/// fine enough in frequency to resolve voice harmonics.
+pub fn frame_size(sample_rate: f32) -> usize {
+    ((sample_rate * 0.02) as usize)
+        .next_power_of_two()
+        .clamp(256, 8192)
+}
+
+/// Phase advance per hop of a sinusoid exactly centred on bin `k`.
+pub fn expected_advance(k: usize, size: usize, hop: usize) -> f32 {
+    2.0 * PI * (k * hop) as f32 / size as f32
+}
+
+/// Wraps a phase into `(-PI, PI]`.
+pub fn wrap_phase(phase: f32) -> f32 {
+    phase - 2.0 * PI * ((phase + PI) / (2.0 * PI)).floor()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_untouched_bins_reconstruct_the_delayed_input() {
+        for window in [Window::Hann, Window::Hamming, Window::Blackman] {
+            let mut stft = Stft::new(256, 64, window);
+            assert_eq!(stft.latency(), 256);
+            let input: Vec<f32> = (0..2048)
+                .map(|i| (i as f32 * 0.031).sin() * 0.5 + (i as f32 * 0.17).cos() * 0.2)
+                .collect();
+            let mut buffer = input.clone();
+            // Uneven blocks, to cover frames completing mid-block.
+            for chunk in buffer.chunks_mut(100) {
+                stft.process(chunk, |_| {});
+            }
+
+            // Skip the first frame, which overlaps the initial silence.
+            for i in 512..2048 {
+                let expected = input[i - stft.latency()];
+                assert!(
+                    (buffer[i] - expected).abs() < 1e-4,
+                    "{window:?} at {i}: {} vs {expected}",
+                    buffer[i]
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use super::{find_param, Effect, ParamInfo};
+use crate::dsp::stft::{self, expected_advance, wrap_phase};
+use crate::dsp::{Complex, Stft, Window};
+
+const PARAMS: &[ParamInfo] = &[
+    ParamInfo::new("shift_hz", -2000.0, 2000.0, 150.0),
+    ParamInfo::new("mix", 0.0, 1.0, 1.0),
+];
+
+struct Channel {
+    stft: Stft,
+    shifted: Vec<Complex>,
+    /// Phase correction for the moved bins, advanced every frame so a
+    /// partial moved by `n` bins keeps a steady frequency across frames.
+    rotation: f32,
+}
+
+impl Channel {
+    fn new(size: usize) -> Self {
+        let stft = Stft::new(size, size / 4, Window::Hann);
+        let bins = stft.bins();
+        Self {
+            stft,
+            shifted: vec![Complex::ZERO; bins],
+            rotation: 0.0,
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [f32], shift: isize, mix: f32) {
+        let size = self.stft.size();
+        let hop = self.stft.hop();
+        let Self {
+            stft,
+            shifted,
+            rotation,
+        } = self;
+        stft.process(buffer, |bins| {
+            let step = expected_advance(shift.unsigned_abs(), size, hop);
+            *rotation = wrap_phase(*rotation + step * shift.signum() as f32);
+            let turn = Complex::from_polar(1.0, *rotation);
+            for (k, out) in shifted.iter_mut().enumerate() {
+                *out = match k.checked_add_signed(-shift) {
+                    Some(from) if from < bins.len() => bins[from] * turn,
+                    _ => Complex::ZERO,
+                };
+            }
+            for (bin, &wet) in bins.iter_mut().zip(shifted.iter()) {
+                *bin = bin.scale(1.0 - mix) + wet.scale(mix);
+            }
+        });
+    }
+
+    fn reset(&mut self) {
+        self.stft.reset();
+        self.rotation = 0.0;
+    }
+}
+
+/// Bin shift: moves the whole spectrum up or down by a fixed number of
+/// hertz (rounded to whole bins). Unlike a pitch shift this breaks the
+/// harmonic ratios, turning voices metallic and bell-like.
+pub struct BinShift {
+    channels: [Channel; 2],
+    bin_hz: f32,
+    shift_hz: f32,
+    /// `shift_hz` in whole bins.
+    shift: isize,
+    mix: f32,
+}
+
+impl BinShift {
+    pub const KIND: &'static str = "bin_shift";
+
+    pub fn new(sample_rate: f32) -> Self {
+        let size = stft::frame_size(sample_rate);
+        let mut effect = Self {
+            channels: [Channel::new(size), Channel::new(size)],
+            bin_hz: sample_rate / size as f32,
+            shift_hz: 0.0,
+            shift: 0,
+            mix: 0.0,
+        };
+        for info in PARAMS {
+            effect.set_param(info.name, info.default);
+        }
+        effect
+    }
+}
+
+impl Effect for BinShift {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        PARAMS
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        let Some(info) = find_param(PARAMS, name) else {
+            return false;
+        };
+        let value = info.clamp(value);
+        match name {
+            "shift_hz" => {
+                self.shift_hz = value;
+                self.shift = (value / self.bin_hz).round() as isize;
+            }
+            "mix" => self.mix = value,
+            _ => return false,
+        }
+        true
+    }
+
+    fn get_param(&self, name: &str) -> Option<f32> {
+        match name {
+            "shift_hz" => Some(self.shift_hz),
+            "mix" => Some(self.mix),
+            _ => None,
+        }
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let [l, r] = &mut self.channels;
+        l.process(left, self.shift, self.mix);
+        r.process(right, self.shift, self.mix);
+    }
+
+    fn reset(&mut self) {
+        self.channels.iter_mut().for_each(Channel::reset);
+    }
+
+    fn latency(&self) -> usize {
+        self.channels[0].stft.latency()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moves_a_tone_by_the_shift() {
+        let mut effect = BinShift::new(48_000.0);
+        // 1024-point frames at 48 kHz: 46.875 Hz per bin, so a 20-bin move.
+        effect.set_param("shift_hz", 937.5);
+        let bin_hz = 48_000.0 / 1024.0;
+        let freq = bin_hz * 10.0;
+        let mut left: Vec<f32> = (0..24_000)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / 48_000.0).sin() * 0.5)
+            .collect();
+        let mut right = left.clone();
+        effect.process(&mut left, &mut right);
+
+        // The output should cross zero at 30 bins' worth of Hz.
+        let tail = &left[4800..24_000];
+        let crossings = tail
+            .windows(2)
+            .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+            .count() as f32;
+        let measured = crossings * 48_000.0 / tail.len() as f32;
+        assert!((measured - bin_hz * 30.0).abs() < 5.0, "{measured} Hz");
+        let peak = tail.iter().fold(0.0f32, |m, x| m.max(x.abs()));
+        assert!((peak - 0.5).abs() < 0.05, "peak {peak}");
+    }
+}
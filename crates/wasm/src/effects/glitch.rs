@@ -0,0 +1,431 @@
+use super::{find_param, Effect, ParamInfo};
+use crate::dsp::CaptureBuffer;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Longest slice: a quarter note at the slowest tempo.
+const MAX_SLICE_SECONDS: f32 = 1.5;
+/// History kept for tape stops, which fall further behind the input the
+/// longer they ramp.
+const CAPTURE_SECONDS: f32 = 4.0;
+/// Crossfade into and out of a glitch, and at slice edges.
+const FADE_MS: f32 = 3.0;
+
+const PARAMS: &[ParamInfo] = &[
+    ParamInfo::new("bpm", 40.0, 240.0, 120.0),
+    ParamInfo::new("division", 0.0, 3.0, 2.0),
+    ParamInfo::new("mode", 0.0, 3.0, 1.0),
+    ParamInfo::new("hold", 0.0, 1.0, 0.0),
+    ParamInfo::new("probability", 0.0, 1.0, 0.0),
+    ParamInfo::new("ramp_ms", 50.0, 2000.0, 400.0),
+    ParamInfo::new("seed", 0.0, 65_535.0, 1.0),
+    ParamInfo::new("mix", 0.0, 1.0, 1.0),
+];
+
+/// Slice lengths selectable with `division`, in beats: quarter, eighth,
+/// sixteenth and thirty-second notes.
+const DIVISIONS: [f32; 4] = [1.0, 0.5, 0.25, 0.125];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlitchKind {
+    Reverse,
+    Stutter,
+    TapeStop,
+}
+
+impl GlitchKind {
+    pub const ALL: [GlitchKind; 3] = [
+        GlitchKind::Reverse,
+        GlitchKind::Stutter,
+        GlitchKind::TapeStop,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Live,
+    /// Looping the captured slice, backwards if `reverse`.
+    Slice {
+        reverse: bool,
+    },
+    TapeStop,
+    /// Spinning back up after a tape stop was released.
+    TapeStart,
+}
+
+/// Buffer-repeat glitches on the last moments of input.
+///
+/// Input is captured continuously into a ring buffer, and a glitch replays
+/// from it: `stutter` loops the last slice, `reverse` loops it backwards,
+/// and `tape_stop` slows playback to a halt over `ramp_ms` and spins back
+/// up when released. Slices are `division` of a beat at `bpm` long, and the
+/// probability engine rolls on the same grid.
+///
+/// Glitches start from [`Effect::trigger`] events (`stutter`, `reverse`,
+/// `tape_stop`, `retrigger` for one grid step of `mode`, and `release`),
+/// from the `hold` parameter, or by chance: at every grid step, with
+/// `probability`, the `mode` glitch plays for one step. `mode` 3 picks one
+/// of the three at random from the seeded generator.
+pub struct Glitch {
+    sample_rate: f32,
+    capture: CaptureBuffer,
+    slice: [Vec<f32>; 2],
+    slice_len: usize,
+    slice_pos: usize,
+    tape_delay: f32,
+    tape_rate: f32,
+    state: State,
+    /// Whether the glitch is wanted; the crossfade runs on after it ends.
+    active: bool,
+    /// Started by the probability engine, so it ends on the next step.
+    chance: bool,
+    wet: f32,
+    fade_step: f32,
+    step_len: usize,
+    until_step: usize,
+    rng: StdRng,
+    bpm: f32,
+    division: f32,
+    mode: f32,
+    hold: f32,
+    probability: f32,
+    ramp_ms: f32,
+    seed: f32,
+    mix: f32,
+}
+
+impl Glitch {
+    pub const KIND: &'static str = "glitch";
+
+    pub fn new(sample_rate: f32) -> Self {
+        let slice_capacity = (sample_rate * MAX_SLICE_SECONDS) as usize;
+        let mut effect = Self {
+            sample_rate,
+            capture: CaptureBuffer::new((sample_rate * CAPTURE_SECONDS) as usize),
+            slice: [vec![0.0; slice_capacity], vec![0.0; slice_capacity]],
+            slice_len: 1,
+            slice_pos: 0,
+            tape_delay: 0.0,
+            tape_rate: 1.0,
+            state: State::Live,
+            active: false,
+            chance: false,
+            wet: 0.0,
+            fade_step: 1.0 / (FADE_MS * 0.001 * sample_rate),
+            step_len: 1,
+            until_step: 0,
+            rng: StdRng::seed_from_u64(1),
+            bpm: 0.0,
+            division: 0.0,
+            mode: 0.0,
+            hold: 0.0,
+            probability: 0.0,
+            ramp_ms: 0.0,
+            seed: 0.0,
+            mix: 0.0,
+        };
+        for info in PARAMS {
+            effect.set_param(info.name, info.default);
+        }
+        effect
+    }
+
+    fn update_step(&mut self) {
+        let beats = DIVISIONS[self.division as usize];
+        let samples = 60.0 / self.bpm * beats * self.sample_rate;
+        self.step_len = (samples.round() as usize).clamp(1, self.slice[0].len());
+        self.until_step = self.until_step.min(self.step_len);
+    }
+
+    fn pick(&mut self) -> GlitchKind {
+        match self.mode as usize {
+            mode if mode < GlitchKind::ALL.len() => GlitchKind::ALL[mode],
+            _ => GlitchKind::ALL[self.rng.gen_range(0..GlitchKind::ALL.len())],
+        }
+    }
+
+    /// Starts `kind` from the audio captured up to now.
+    pub fn engage(&mut self, kind: GlitchKind) {
+        self.active = true;
+        self.chance = false;
+        match kind {
+            GlitchKind::Reverse | GlitchKind::Stutter => {
+                self.slice_len = self.step_len;
+                let [left, right] = &mut self.slice;
+                self.capture
+                    .copy_recent(&mut left[..self.slice_len], &mut right[..self.slice_len]);
+                self.slice_pos = 0;
+                self.state = State::Slice {
+                    reverse: kind == GlitchKind::Reverse,
+                };
+            }
+            GlitchKind::TapeStop => {
+                if self.state != State::TapeStop {
+                    self.tape_delay = 0.0;
+                    self.tape_rate = 1.0;
+                }
+                self.state = State::TapeStop;
+            }
+        }
+    }
+
+    /// Lets go of the current glitch: slices fade back to the live input,
+    /// a tape stop spins back up first.
+    pub fn release(&mut self) {
+        self.chance = false;
+        match self.state {
+            State::TapeStop => {
+                // Resume no further back than one ramp; after a long stop
+                // the stopped moment is long gone from the capture buffer.
+                self.tape_delay = self.tape_delay.min(self.ramp_samples());
+                self.state = State::TapeStart;
+            }
+            State::TapeStart => {}
+            _ => self.active = false,
+        }
+    }
+
+    fn ramp_samples(&self) -> f32 {
+        self.ramp_ms * 0.001 * self.sample_rate
+    }
+
+    fn on_step(&mut self) {
+        if self.chance {
+            self.release();
+        }
+        let held = self.hold >= 0.5;
+        if !held
+            && !self.active
+            && self.probability > 0.0
+            && self.rng.gen::<f32>() < self.probability
+        {
+            let kind = self.pick();
+            self.engage(kind);
+            self.chance = true;
+        }
+    }
+
+    fn next_frame(&mut self, live: [f32; 2]) -> [f32; 2] {
+        match self.state {
+            State::Live => live,
+            State::Slice { reverse } => {
+                let len = self.slice_len;
+                let i = self.slice_pos;
+                let index = if reverse { len - 1 - i } else { i };
+                self.slice_pos = (i + 1) % len;
+                let fade = self.fade_step;
+                let edge = ((i + 1) as f32 * fade)
+                    .min((len - i) as f32 * fade)
+                    .min(1.0);
+                [self.slice[0][index] * edge, self.slice[1][index] * edge]
+            }
+            State::TapeStop | State::TapeStart => {
+                let frame = self.capture.read_frac(self.tape_delay);
+                let step = 1.0 / self.ramp_samples().max(1.0);
+                if self.state == State::TapeStop {
+                    self.tape_rate = (self.tape_rate - step).max(0.0);
+                } else {
+                    self.tape_rate = (self.tape_rate + step).min(1.0);
+                    if self.tape_rate == 1.0 {
+                        self.active = false;
+                    }
+                }
+                // Reading one sample further behind for every sample the
+                // tape falls short of full speed.
+                let max_delay = (self.capture.max_delay() - 2) as f32;
+                self.tape_delay = (self.tape_delay + 1.0 - self.tape_rate).min(max_delay);
+                // Playback level falls with tape speed.
+                let gain = self.tape_rate.sqrt();
+                [frame[0] * gain, frame[1] * gain]
+            }
+        }
+    }
+}
+
+impl Effect for Glitch {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        PARAMS
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        let Some(info) = find_param(PARAMS, name) else {
+            return false;
+        };
+        let value = info.clamp(value);
+        match name {
+            "bpm" => {
+                self.bpm = value;
+                self.update_step();
+            }
+            "division" => {
+                self.division = value.round();
+                self.update_step();
+            }
+            "mode" => self.mode = value.round(),
+            "hold" => {
+                let was_held = self.hold >= 0.5;
+                self.hold = value;
+                match (was_held, value >= 0.5) {
+                    (false, true) => {
+                        let kind = self.pick();
+                        self.engage(kind);
+                    }
+                    (true, false) => self.release(),
+                    _ => {}
+                }
+            }
+            "probability" => self.probability = value,
+            "ramp_ms" => self.ramp_ms = value,
+            "seed" => {
+                self.seed = value.round();
+                self.rng = StdRng::seed_from_u64(self.seed as u64);
+            }
+            "mix" => self.mix = value,
+            _ => return false,
+        }
+        true
+    }
+
+    fn get_param(&self, name: &str) -> Option<f32> {
+        match name {
+            "bpm" => Some(self.bpm),
+            "division" => Some(self.division),
+            "mode" => Some(self.mode),
+            "hold" => Some(self.hold),
+            "probability" => Some(self.probability),
+            "ramp_ms" => Some(self.ramp_ms),
+            "seed" => Some(self.seed),
+            "mix" => Some(self.mix),
+            _ => None,
+        }
+    }
+
+    fn trigger(&mut self, event: &str) -> bool {
+        match event {
+            "reverse" => self.engage(GlitchKind::Reverse),
+            "stutter" => self.engage(GlitchKind::Stutter),
+            "tape_stop" => self.engage(GlitchKind::TapeStop),
+            "retrigger" => {
+                let kind = self.pick();
+                self.engage(kind);
+                self.chance = true;
+            }
+            "release" if self.active => self.release(),
+            _ => return false,
+        }
+        true
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let live = [*l, *r];
+            self.capture.push(live);
+            if self.until_step == 0 {
+                self.until_step = self.step_len;
+                self.on_step();
+            }
+            self.until_step -= 1;
+
+            let glitched = self.next_frame(live);
+            let target = if self.active { 1.0 } else { 0.0 };
+            self.wet = if self.wet < target {
+                (self.wet + self.fade_step).min(target)
+            } else {
+                (self.wet - self.fade_step).max(target)
+            };
+            if !self.active && self.wet == 0.0 {
+                self.state = State::Live;
+            }
+            let amount = self.wet * self.mix;
+            *l += (glitched[0] - *l) * amount;
+            *r += (glitched[1] - *r) * amount;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.capture.clear();
+        self.state = State::Live;
+        self.active = self.hold >= 0.5;
+        self.chance = false;
+        self.wet = 0.0;
+        self.until_step = 0;
+        self.rng = StdRng::seed_from_u64(self.seed as u64);
+        if self.active {
+            let kind = self.pick();
+            self.engage(kind);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(len: usize) -> (Vec<f32>, Vec<f32>) {
+        let left: Vec<f32> = (0..len).map(|i| i as f32 / len as f32).collect();
+        (left.clone(), left)
+    }
+
+    #[test]
+    fn test_stutter_and_reverse_repeat_the_last_slice() {
+        let mut effect = Glitch::new(48_000.0);
+        // A sixteenth at 120 bpm is 6000 samples.
+        let (mut left, mut right) = ramp(12_000);
+        effect.process(&mut left, &mut right);
+        let captured: Vec<f32> = ramp(12_000).0[6000..].to_vec();
+
+        assert!(effect.trigger("stutter"));
+        let (mut left, mut right) = (vec![0.0; 12_000], vec![0.0; 12_000]);
+        effect.process(&mut left, &mut right);
+        // Past the fade-in, each repeat matches the captured slice.
+        for i in [1000, 3000, 7000, 9000] {
+            assert!((left[i] - captured[i % 6000]).abs() < 1e-6, "{i}");
+        }
+
+        // Capture keeps recording the input under a glitch.
+        let (mut left, mut right) = ramp(6000);
+        effect.process(&mut left, &mut right);
+        assert!(effect.trigger("reverse"));
+        let (mut left, mut right) = (vec![0.0; 6000], vec![0.0; 6000]);
+        effect.process(&mut left, &mut right);
+        assert!(left[1000] > left[4000]);
+        assert!((left[3000] - 0.5).abs() < 1e-3);
+
+        assert!(effect.trigger("release"));
+        let (mut left, mut right) = (vec![0.25; 1000], vec![0.25; 1000]);
+        effect.process(&mut left, &mut right);
+        assert_eq!(left[999], 0.25);
+        assert!(!effect.trigger("release"));
+    }
+
+    #[test]
+    fn test_tape_stop_slows_to_silence_and_chance_is_seeded() {
+        let mut effect = Glitch::new(48_000.0);
+        effect.set_param("ramp_ms", 100.0);
+        effect.set_param("mode", 2.0);
+        effect.set_param("hold", 1.0);
+        let mut left: Vec<f32> = (0..9600).map(|i| (i as f32 * 0.05).sin()).collect();
+        let mut right = left.clone();
+        effect.process(&mut left, &mut right);
+        assert!(left[6000..].iter().all(|s| s.abs() < 1e-3));
+
+        let render = |seed: f32| {
+            let mut effect = Glitch::new(48_000.0);
+            effect.set_param("probability", 0.5);
+            effect.set_param("mode", 3.0);
+            effect.set_param("seed", seed);
+            let mut left: Vec<f32> = (0..96_000).map(|i| (i as f32 * 0.01).sin()).collect();
+            let mut right = left.clone();
+            for (l, r) in left.chunks_mut(128).zip(right.chunks_mut(128)) {
+                effect.process(l, r);
+            }
+            left
+        };
+        assert_eq!(render(7.0), render(7.0));
+        assert_ne!(render(7.0), render(8.0));
+    }
+}
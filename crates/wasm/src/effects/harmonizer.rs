@@ -0,0 +1,244 @@
+use super::{find_param, Effect, ParamInfo};
+use crate::dsp::{hz_to_midi, midi_to_hz, semitones_to_ratio, PitchDetector, PitchShifter, Scale};
+use std::f32::consts::FRAC_PI_4;
+
+const VOICES: usize = 4;
+
+const PARAMS: &[ParamInfo] = &[
+    ParamInfo::new("mode", 0.0, 1.0, 0.0),
+    ParamInfo::new("key", 0.0, 11.0, 0.0),
+    ParamInfo::new("scale", 0.0, (Scale::ALL.len() - 1) as f32, 1.0),
+    ParamInfo::new("dry", 0.0, 1.0, 1.0),
+    ParamInfo::new("voice1_interval", -24.0, 24.0, 4.0),
+    ParamInfo::new("voice1_level", 0.0, 1.0, 0.7),
+    ParamInfo::new("voice1_pan", -1.0, 1.0, -0.5),
+    ParamInfo::new("voice2_interval", -24.0, 24.0, 7.0),
+    ParamInfo::new("voice2_level", 0.0, 1.0, 0.7),
+    ParamInfo::new("voice2_pan", -1.0, 1.0, 0.5),
+    ParamInfo::new("voice3_interval", -24.0, 24.0, 12.0),
+    ParamInfo::new("voice3_level", 0.0, 1.0, 0.0),
+    ParamInfo::new("voice3_pan", -1.0, 1.0, 0.0),
+    ParamInfo::new("voice4_interval", -24.0, 24.0, -12.0),
+    ParamInfo::new("voice4_level", 0.0, 1.0, 0.0),
+    ParamInfo::new("voice4_pan", -1.0, 1.0, 0.0),
+];
+
+/// How voice intervals are interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Intervals are fixed semitone offsets.
+    Fixed,
+    /// Intervals are scale degrees above the detected note, so the harmony
+    /// follows the key (a "third" is major or minor as the scale demands).
+    Scale,
+}
+
+struct Voice {
+    shifter: PitchShifter,
+    interval: f32,
+    level: f32,
+    pan: f32,
+}
+
+impl Voice {
+    fn gains(&self) -> (f32, f32) {
+        let angle = (self.pan + 1.0) * FRAC_PI_4;
+        (angle.cos() * self.level, angle.sin() * self.level)
+    }
+}
+
+/// Splits `voiceN_field` into a zero-based voice index and the field name.
+fn voice_param(name: &str) -> Option<(usize, &str)> {
+    let (index, field) = name.strip_prefix("voice")?.split_once('_')?;
+    let index: usize = index.parse().ok()?;
+    (1..=VOICES).contains(&index).then_some((index - 1, field))
+}
+
+/// Mixes up to four pitch-shifted copies of the input, each with its own
+/// interval, level and pan.
+pub struct Harmonizer {
+    detector: PitchDetector,
+    voices: [Voice; VOICES],
+    mode: Mode,
+    key: i32,
+    scale: Scale,
+    dry: f32,
+}
+
+impl Harmonizer {
+    pub const KIND: &'static str = "harmonizer";
+
+    pub fn new(sample_rate: f32) -> Self {
+        let window = sample_rate * 0.03;
+        let voices = std::array::from_fn(|_| {
+            let mut shifter = PitchShifter::new((sample_rate * 0.1) as usize);
+            shifter.set_window(window);
+            Voice {
+                shifter,
+                interval: 0.0,
+                level: 0.0,
+                pan: 0.0,
+            }
+        });
+
+        let mut effect = Self {
+            detector: PitchDetector::new(sample_rate, 70.0, 1000.0),
+            voices,
+            mode: Mode::Fixed,
+            key: 0,
+            scale: Scale::Major,
+            dry: 0.0,
+        };
+        for info in PARAMS {
+            effect.set_param(info.name, info.default);
+        }
+        effect
+    }
+
+    fn update_fixed_ratios(&mut self) {
+        for voice in self.voices.iter_mut() {
+            voice.shifter.set_ratio(semitones_to_ratio(voice.interval));
+        }
+    }
+
+    fn update_scale_ratios(&mut self) {
+        // Unvoiced input keeps the last ratios so tails don't jump.
+        let Some(hz) = self.detector.frequency() else {
+            return;
+        };
+        let base = self.scale.quantize(hz_to_midi(hz), self.key);
+        for voice in self.voices.iter_mut() {
+            let degrees = voice.interval.round() as i32;
+            let target = self.scale.transpose(base, self.key, degrees);
+            voice.shifter.set_ratio(midi_to_hz(target as f32) / hz);
+        }
+    }
+}
+
+impl Effect for Harmonizer {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        PARAMS
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        let Some(info) = find_param(PARAMS, name) else {
+            return false;
+        };
+        let value = info.clamp(value);
+        if let Some((index, field)) = voice_param(name) {
+            let voice = &mut self.voices[index];
+            match field {
+                "interval" => voice.interval = value,
+                "level" => voice.level = value,
+                "pan" => voice.pan = value,
+                _ => return false,
+            }
+        } else {
+            match name {
+                "mode" => {
+                    self.mode = if value >= 0.5 {
+                        Mode::Scale
+                    } else {
+                        Mode::Fixed
+                    };
+                }
+                "key" => self.key = value.round() as i32,
+                "scale" => self.scale = Scale::from_index(value.round() as usize),
+                "dry" => self.dry = value,
+                _ => return false,
+            }
+        }
+        if self.mode == Mode::Fixed {
+            self.update_fixed_ratios();
+        }
+        true
+    }
+
+    fn get_param(&self, name: &str) -> Option<f32> {
+        if let Some((index, field)) = voice_param(name) {
+            let voice = &self.voices[index];
+            return match field {
+                "interval" => Some(voice.interval),
+                "level" => Some(voice.level),
+                "pan" => Some(voice.pan),
+                _ => None,
+            };
+        }
+        match name {
+            "mode" => Some(match self.mode {
+                Mode::Fixed => 0.0,
+                Mode::Scale => 1.0,
+            }),
+            "key" => Some(self.key as f32),
+            "scale" => Scale::ALL
+                .iter()
+                .position(|&s| s == self.scale)
+                .map(|i| i as f32),
+            "dry" => Some(self.dry),
+            _ => None,
+        }
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let mid = (*l + *r) * 0.5;
+            if self.mode == Mode::Scale && self.detector.push(mid) {
+                self.update_scale_ratios();
+            }
+
+            let mut out_l = *l * self.dry;
+            let mut out_r = *r * self.dry;
+            for voice in self.voices.iter_mut() {
+                if voice.level <= 0.0 {
+                    continue;
+                }
+                let (gain_l, gain_r) = voice.gains();
+                let wet = voice.shifter.process(mid);
+                out_l += wet * gain_l;
+                out_r += wet * gain_r;
+            }
+            *l = out_l;
+            *r = out_r;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.detector.reset();
+        self.voices.iter_mut().for_each(|v| v.shifter.reset());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_voice_param_names() {
+        assert_eq!(voice_param("voice1_level"), Some((0, "level")));
+        assert_eq!(voice_param("voice4_pan"), Some((3, "pan")));
+        assert_eq!(voice_param("voice5_pan"), None);
+        assert_eq!(voice_param("dry"), None);
+    }
+
+    #[test]
+    fn test_hard_panned_voice_only_reaches_one_side() {
+        let mut effect = Harmonizer::new(48_000.0);
+        effect.set_param("dry", 0.0);
+        effect.set_param("voice1_level", 1.0);
+        effect.set_param("voice1_pan", -1.0);
+        effect.set_param("voice2_level", 0.0);
+
+        let mut left: Vec<f32> = (0..4800).map(|i| (i as f32 * 0.06).sin()).collect();
+        let mut right = left.clone();
+        effect.process(&mut left, &mut right);
+
+        let energy_l: f32 = left.iter().map(|s| s * s).sum();
+        let energy_r: f32 = right.iter().map(|s| s * s).sum();
+        assert!(energy_l > 100.0);
+        assert!(energy_r < 1e-6);
+    }
+}
@@ -0,0 +1,536 @@
+use super::{find_param, Effect, ParamInfo};
+use crate::dsp::{flush_denormal, resample, scrub};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Longest loop that can be recorded. Storage for this is allocated on the
+/// first `record` rather than up front, so a looper that is never used costs
+/// nothing; the undo copy is sized to the loop once its length is known.
+const MAX_SECONDS: f32 = 16.0;
+
+const PARAMS: &[ParamInfo] = &[
+    ParamInfo::new("level", 0.0, 1.0, 1.0),
+    ParamInfo::new("dry", 0.0, 1.0, 1.0),
+    ParamInfo::new("feedback", 0.0, 1.0, 1.0),
+    ParamInfo::new("bpm", 0.0, 240.0, 0.0),
+    ParamInfo::new("quantize_beats", 1.0, 16.0, 4.0),
+    ParamInfo::new("disintegrate", 0.0, 1.0, 0.0),
+    ParamInfo::new("seed", 0.0, 65_535.0, 1.0),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopState {
+    Empty,
+    Recording,
+    Playing,
+    Overdubbing,
+    Stopped,
+}
+
+/// Per-pass tape wear applied while the loop plays in disintegration mode.
+///
+/// Every sample that plays is written back slightly worse: darker, a little
+/// saturated, with hiss, and occasionally with a dropout that flakes a
+/// stretch of the loop for good. Damage accumulates pass after pass.
+struct Wear {
+    rng: StdRng,
+    lowpass: [f32; 2],
+    dropout_left: usize,
+    dropout_gain: f32,
+}
+
+impl Wear {
+    fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            lowpass: [0.0; 2],
+            dropout_left: 0,
+            dropout_gain: 1.0,
+        }
+    }
+
+    /// Degrades one stereo frame by `amount` and returns it.
+    fn apply(&mut self, frame: [f32; 2], amount: f32, sample_rate: f32) -> [f32; 2] {
+        // Each pass is a gentle one-pole low-pass, from ~16 kHz at light
+        // wear down to ~4 kHz at full wear; repeated passes compound.
+        let cutoff = 16_000.0 - 12_000.0 * amount;
+        let coeff = 1.0 - (-std::f32::consts::TAU * cutoff / sample_rate).exp();
+
+        if self.dropout_left == 0 && self.rng.gen::<f32>() < amount * 2e-5 {
+            let length = self.rng.gen_range(0.005..0.08) * sample_rate;
+            self.dropout_left = length as usize;
+            self.dropout_gain = self.rng.gen_range(0.2..0.8);
+        }
+        let dropout = if self.dropout_left > 0 {
+            self.dropout_left -= 1;
+            self.dropout_gain
+        } else {
+            1.0
+        };
+
+        let drive = 1.0 + amount;
+        let loss = 1.0 - 0.02 * amount;
+        let mut out = [0.0; 2];
+        for (channel, sample) in frame.into_iter().enumerate() {
+            let state = &mut self.lowpass[channel];
+            *state = flush_denormal(*state + (sample - *state) * coeff);
+            let hiss = self.rng.gen_range(-1.0..1.0) * 2e-4 * amount;
+            let saturated = (*state * drive).tanh() / drive;
+            out[channel] = (saturated * loss + hiss) * dropout;
+        }
+        out
+    }
+}
+
+/// Loop recorder with overdub, single-level undo and tempo-quantized length.
+///
+/// Driven by [`Effect::trigger`] events: `record`, `play`, `overdub`,
+/// `stop`, `undo` and `clear`. With a non-zero `bpm` the loop length snaps
+/// to the nearest multiple of `quantize_beats`; if that is longer than what
+/// was played, recording carries on until the loop is full.
+///
+/// Only `clear` throws the loop away. [`Effect::reset`] keeps it, and a
+/// sample-rate change resamples it along with the undo layer.
+pub struct Looper {
+    sample_rate: f32,
+    buffer: [Vec<f32>; 2],
+    undo: [Vec<f32>; 2],
+    state: LoopState,
+    length: usize,
+    position: usize,
+    record_target: Option<usize>,
+    undo_start: usize,
+    undo_count: usize,
+    has_undo: bool,
+    wear: Wear,
+    level: f32,
+    dry: f32,
+    feedback: f32,
+    bpm: f32,
+    quantize_beats: f32,
+    disintegrate: f32,
+    seed: f32,
+}
+
+impl Looper {
+    pub const KIND: &'static str = "looper";
+
+    pub fn new(sample_rate: f32) -> Self {
+        let mut effect = Self {
+            sample_rate,
+            buffer: [Vec::new(), Vec::new()],
+            undo: [Vec::new(), Vec::new()],
+            state: LoopState::Empty,
+            length: 0,
+            position: 0,
+            record_target: None,
+            undo_start: 0,
+            undo_count: 0,
+            has_undo: false,
+            wear: Wear::new(1),
+            level: 0.0,
+            dry: 0.0,
+            feedback: 0.0,
+            bpm: 0.0,
+            quantize_beats: 0.0,
+            disintegrate: 0.0,
+            seed: 0.0,
+        };
+        for info in PARAMS {
+            effect.set_param(info.name, info.default);
+        }
+        effect
+    }
+
+    pub fn state(&self) -> LoopState {
+        self.state
+    }
+
+    /// Current loop length in samples (0 while empty or still recording).
+    pub fn loop_length(&self) -> usize {
+        self.length
+    }
+
+    fn capacity(&self) -> usize {
+        (self.sample_rate * MAX_SECONDS) as usize
+    }
+
+    /// Length the loop should have when recording of `recorded` samples ends.
+    fn quantized_length(&self, recorded: usize) -> usize {
+        if self.bpm <= 0.0 {
+            return recorded;
+        }
+        let unit = 60.0 / self.bpm * self.quantize_beats.round() * self.sample_rate;
+        let max_units = (self.capacity() as f32 / unit).floor().max(1.0);
+        let units = (recorded as f32 / unit).round().clamp(1.0, max_units);
+        ((units * unit) as usize).min(self.capacity())
+    }
+
+    fn start_recording(&mut self) {
+        let capacity = self.capacity();
+        for buffer in &mut self.buffer {
+            buffer.resize(capacity, 0.0);
+        }
+        self.state = LoopState::Recording;
+        self.length = 0;
+        self.position = 0;
+        self.record_target = None;
+        self.has_undo = false;
+    }
+
+    fn finish_recording(&mut self) {
+        let recorded = self.position;
+        if recorded == 0 {
+            self.state = LoopState::Empty;
+            return;
+        }
+        let target = self.quantized_length(recorded);
+        if target > recorded {
+            self.record_target = Some(target);
+        } else {
+            self.close_loop(target);
+        }
+    }
+
+    fn close_loop(&mut self, length: usize) {
+        // Only grows, so recording a shorter loop later doesn't reallocate.
+        for undo in &mut self.undo {
+            if undo.len() < length {
+                undo.resize(length, 0.0);
+            }
+        }
+        self.length = length;
+        self.position = 0;
+        self.record_target = None;
+        self.state = LoopState::Playing;
+    }
+
+    fn start_overdub(&mut self) {
+        self.state = LoopState::Overdubbing;
+        self.undo_start = self.position;
+        self.undo_count = 0;
+        self.has_undo = true;
+    }
+
+    fn restore_undo(&mut self) -> bool {
+        if !self.has_undo || self.length == 0 {
+            return false;
+        }
+        for i in 0..self.undo_count {
+            let index = (self.undo_start + i) % self.length;
+            for (buffer, undo) in self.buffer.iter_mut().zip(self.undo.iter()) {
+                buffer[index] = undo[index];
+            }
+        }
+        self.has_undo = false;
+        self.undo_count = 0;
+        if self.state == LoopState::Overdubbing {
+            self.state = LoopState::Playing;
+        }
+        true
+    }
+
+    fn record_sample(&mut self, input: [f32; 2]) {
+        if self.position >= self.capacity() {
+            self.close_loop(self.capacity());
+            return;
+        }
+        for (buffer, sample) in self.buffer.iter_mut().zip(input) {
+            buffer[self.position] = sample;
+        }
+        self.position += 1;
+        if self.record_target == Some(self.position) {
+            self.close_loop(self.position);
+        }
+    }
+
+    fn play_sample(&mut self, input: [f32; 2]) -> [f32; 2] {
+        let index = self.position;
+        let mut frame = [self.buffer[0][index], self.buffer[1][index]];
+        let played = frame;
+
+        if self.state == LoopState::Overdubbing {
+            if self.undo_count < self.length {
+                for (undo, buffer) in self.undo.iter_mut().zip(self.buffer.iter()) {
+                    undo[index] = buffer[index];
+                }
+                self.undo_count += 1;
+            }
+            for (sample, dry) in frame.iter_mut().zip(input) {
+                *sample = *sample * self.feedback + dry;
+            }
+        }
+        if self.disintegrate > 0.0 {
+            frame = self.wear.apply(frame, self.disintegrate, self.sample_rate);
+        }
+        for (buffer, sample) in self.buffer.iter_mut().zip(frame) {
+            buffer[index] = flush_denormal(sample);
+        }
+
+        self.position = (self.position + 1) % self.length;
+        played
+    }
+}
+
+impl Effect for Looper {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        PARAMS
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        let Some(info) = find_param(PARAMS, name) else {
+            return false;
+        };
+        let value = info.clamp(value);
+        match name {
+            "level" => self.level = value,
+            "dry" => self.dry = value,
+            "feedback" => self.feedback = value,
+            "bpm" => self.bpm = value,
+            "quantize_beats" => self.quantize_beats = value,
+            "disintegrate" => self.disintegrate = value,
+            "seed" => {
+                self.seed = value.round();
+                self.wear = Wear::new(self.seed as u64);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn get_param(&self, name: &str) -> Option<f32> {
+        match name {
+            "level" => Some(self.level),
+            "dry" => Some(self.dry),
+            "feedback" => Some(self.feedback),
+            "bpm" => Some(self.bpm),
+            "quantize_beats" => Some(self.quantize_beats),
+            "disintegrate" => Some(self.disintegrate),
+            "seed" => Some(self.seed),
+            _ => None,
+        }
+    }
+
+    fn trigger(&mut self, event: &str) -> bool {
+        match (event, self.state) {
+            ("record", LoopState::Empty | LoopState::Stopped) => self.start_recording(),
+            ("record" | "play", LoopState::Recording) => self.finish_recording(),
+            ("record" | "overdub", LoopState::Playing) => self.start_overdub(),
+            ("record" | "overdub", LoopState::Overdubbing) => self.state = LoopState::Playing,
+            ("play", LoopState::Stopped) => {
+                self.position = 0;
+                self.state = LoopState::Playing;
+            }
+            ("stop", LoopState::Recording) => {
+                self.finish_recording();
+                if self.state == LoopState::Playing {
+                    self.state = LoopState::Stopped;
+                }
+            }
+            ("stop", LoopState::Playing | LoopState::Overdubbing) => {
+                self.state = LoopState::Stopped;
+            }
+            ("undo", _) => return self.restore_undo(),
+            ("clear", _) => {
+                self.state = LoopState::Empty;
+                self.length = 0;
+                self.position = 0;
+                self.record_target = None;
+                self.has_undo = false;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let input = [*l, *r];
+            let looped = match self.state {
+                LoopState::Recording => {
+                    self.record_sample(input);
+                    [0.0; 2]
+                }
+                LoopState::Playing | LoopState::Overdubbing if self.length > 0 => {
+                    self.play_sample(input)
+                }
+                _ => [0.0; 2],
+            };
+            *l = input[0] * self.dry + looped[0] * self.level;
+            *r = input[1] * self.dry + looped[1] * self.level;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.wear = Wear::new(self.seed as u64);
+        let extent = self.length.max(self.position);
+        for buffer in self.buffer.iter_mut().chain(self.undo.iter_mut()) {
+            let extent = extent.min(buffer.len());
+            scrub(&mut buffer[..extent]);
+        }
+    }
+
+    fn rebuild(&self, sample_rate: f32) -> Option<Box<dyn Effect>> {
+        let mut fresh = Looper::new(sample_rate);
+        for info in PARAMS {
+            fresh.set_param(info.name, self.get_param(info.name)?);
+        }
+        let ratio = sample_rate / self.sample_rate;
+        let capacity = fresh.capacity();
+        let scale = |n: usize| ((n as f32 * ratio).round() as usize).min(capacity);
+
+        fresh.length = scale(self.length);
+        let extent = self.length.max(self.position);
+        // Storage that was never allocated stays that way.
+        let carry = |from: &[f32], len: usize| {
+            if from.is_empty() {
+                return Vec::new();
+            }
+            let from = &from[..extent.min(from.len())];
+            let mut to = resample(from, self.sample_rate, sample_rate);
+            to.resize(len, 0.0);
+            to
+        };
+        fresh.buffer = self.buffer.each_ref().map(|from| carry(from, capacity));
+        fresh.undo = self.undo.each_ref().map(|from| carry(from, fresh.length));
+
+        fresh.state = self.state;
+        fresh.position = scale(self.position);
+        fresh.undo_start = scale(self.undo_start);
+        if fresh.length > 0 {
+            fresh.position %= fresh.length;
+            fresh.undo_start %= fresh.length;
+        }
+        fresh.undo_count = scale(self.undo_count).min(fresh.length);
+        fresh.has_undo = self.has_undo;
+        // Still ahead of the write position, or recording would never stop.
+        fresh.record_target = self
+            .record_target
+            .map(|target| scale(target).max(fresh.position + 1));
+        Some(Box::new(fresh))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(looper: &mut Looper, input: f32, samples: usize) -> Vec<f32> {
+        let mut left = vec![input; samples];
+        let mut right = vec![input; samples];
+        looper.process(&mut left, &mut right);
+        left
+    }
+
+    #[test]
+    fn test_record_then_play_back() {
+        let mut looper = Looper::new(1000.0);
+        looper.set_param("dry", 0.0);
+
+        looper.trigger("record");
+        run(&mut looper, 0.5, 100);
+        looper.trigger("record");
+        assert_eq!(looper.state(), LoopState::Playing);
+        assert_eq!(looper.loop_length(), 100);
+
+        let out = run(&mut looper, 0.0, 200);
+        assert!(out.iter().all(|&s| s == 0.5));
+    }
+
+    #[test]
+    fn test_storage_is_allocated_on_first_record() {
+        let mut looper = Looper::new(1000.0);
+        run(&mut looper, 0.5, 100);
+        looper.reset();
+        assert!(looper.buffer.iter().chain(&looper.undo).all(Vec::is_empty));
+
+        looper.trigger("record");
+        assert_eq!(looper.buffer[0].len(), looper.capacity());
+        run(&mut looper, 0.5, 480);
+        looper.trigger("record");
+        assert_eq!(looper.undo[0].len(), 480);
+    }
+
+    #[test]
+    fn test_overdub_and_undo() {
+        let mut looper = Looper::new(1000.0);
+        looper.set_param("dry", 0.0);
+        looper.trigger("record");
+        run(&mut looper, 0.25, 50);
+        looper.trigger("record");
+
+        looper.trigger("overdub");
+        run(&mut looper, 0.25, 50);
+        looper.trigger("overdub");
+        assert!(run(&mut looper, 0.0, 50).iter().all(|&s| s == 0.5));
+
+        assert!(looper.trigger("undo"));
+        assert!(run(&mut looper, 0.0, 50).iter().all(|&s| s == 0.25));
+    }
+
+    #[test]
+    fn test_loop_survives_reset_and_sample_rate_change() {
+        let mut looper = Looper::new(1000.0);
+        looper.set_param("dry", 0.0);
+        looper.set_param("feedback", 0.5);
+        looper.trigger("record");
+        run(&mut looper, 0.5, 50);
+        run(&mut looper, 0.0, 50);
+        looper.trigger("record");
+
+        looper.reset();
+        assert_eq!(looper.state(), LoopState::Playing);
+        let out = run(&mut looper, 0.0, 100);
+        assert!(out[..50].iter().all(|&s| s == 0.5));
+
+        // At twice the rate the loop takes twice the samples: loud for the
+        // first 100, quiet for the next 100. The edges ring from resampling.
+        let mut looper = looper.rebuild(2000.0).unwrap();
+        assert_eq!(looper.get_param("feedback"), Some(0.5));
+        let mut left = vec![0.0; 200];
+        let mut right = vec![0.0; 200];
+        looper.process(&mut left, &mut right);
+        assert!(left[20..80].iter().all(|&s| (s - 0.5).abs() < 0.01));
+        assert!(left[120..180].iter().all(|&s| s.abs() < 0.01));
+    }
+
+    #[test]
+    fn test_length_snaps_to_tempo() {
+        // 60 bpm at 1 kHz, one beat per unit: 1000 samples per unit.
+        let mut looper = Looper::new(1000.0);
+        looper.set_param("bpm", 60.0);
+        looper.set_param("quantize_beats", 1.0);
+
+        looper.trigger("record");
+        run(&mut looper, 0.1, 1700);
+        looper.trigger("record");
+        assert_eq!(looper.state(), LoopState::Recording);
+        run(&mut looper, 0.1, 300);
+        assert_eq!(looper.state(), LoopState::Playing);
+        assert_eq!(looper.loop_length(), 2000);
+    }
+
+    #[test]
+    fn test_disintegration_wears_loop_down() {
+        let mut looper = Looper::new(8000.0);
+        looper.set_param("dry", 0.0);
+        looper.set_param("disintegrate", 1.0);
+        looper.trigger("record");
+        let tone: Vec<f32> = (0..800).map(|i| (i as f32 * 0.9).sin() * 0.8).collect();
+        let mut right = tone.clone();
+        looper.process(&mut tone.clone(), &mut right);
+        looper.trigger("record");
+
+        let energy = |out: &[f32]| out.iter().map(|s| s * s).sum::<f32>();
+        let first = energy(&run(&mut looper, 0.0, 800));
+        for _ in 0..20 {
+            run(&mut looper, 0.0, 800);
+        }
+        let later = energy(&run(&mut looper, 0.0, 800));
+        assert!(later < first * 0.5, "first {first}, later {later}");
+    }
+}
@@ -0,0 +1,313 @@
+//! Effects that can be placed in the processor chain.
+//!
+//! Every effect works on a stereo pair of equally sized slices in place and
+//! exposes its controls through a small static parameter table, so the chain
+//! can be driven by name from JS without bespoke bindings per effect.
+
+pub mod bin_shift;
+pub mod distortion;
+pub mod glitch;
+pub mod harmonizer;
+pub mod looper;
+pub mod pitch_correction;
+pub mod pitch_shift;
+pub mod radio;
+pub mod reverb;
+pub mod ring_mod;
+pub mod spectral_blur;
+pub mod spectral_freeze;
+pub mod transmission;
+pub mod vinyl;
+pub mod vocoder;
+pub mod voice_character;
+
+pub use bin_shift::BinShift;
+pub use distortion::Distortion;
+pub use glitch::Glitch;
+pub use harmonizer::Harmonizer;
+pub use looper::{LoopState, Looper};
+pub use pitch_correction::PitchCorrection;
+pub use pitch_shift::PitchShift;
+pub use radio::Radio;
+pub use reverb::Reverb;
+pub use ring_mod::RingMod;
+pub use spectral_blur::SpectralBlur;
+pub use spectral_freeze::SpectralFreeze;
+pub use transmission::Transmission;
+pub use vinyl::Vinyl;
+pub use vocoder::Vocoder;
+pub use voice_character::VoiceCharacter;
+
+/// Description of one automatable parameter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParamInfo {
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+}
+
+impl ParamInfo {
+    pub const fn new(name: &'static str, min: f32, max: f32, default: f32) -> Self {
+        Self {
+            name,
+            min,
+            max,
+            default,
+        }
+    }
+
+    /// Clamps `value` to the range. NaN, which `f32::clamp` passes through,
+    /// becomes the default.
+    pub fn clamp(&self, value: f32) -> f32 {
+        if value.is_nan() {
+            self.default
+        } else {
+            value.clamp(self.min, self.max)
+        }
+    }
+}
+
+/// Looks up a parameter by name in an effect's table.
+pub fn find_param(params: &'static [ParamInfo], name: &str) -> Option<&'static ParamInfo> {
+    params.iter().find(|p| p.name == name)
+}
+
+pub trait Effect {
+    /// Identifier used by [`create`] to build this effect.
+    fn kind(&self) -> &'static str;
+
+    fn params(&self) -> &'static [ParamInfo];
+
+    /// Sets a parameter, clamped to its range. Returns `false` for unknown names.
+    fn set_param(&mut self, name: &str, value: f32) -> bool;
+
+    fn get_param(&self, name: &str) -> Option<f32>;
+
+    /// Handles a one-shot event such as a looper's `record`. Returns `false`
+    /// if the effect doesn't know the event or it makes no sense right now.
+    fn trigger(&mut self, _event: &str) -> bool {
+        false
+    }
+
+    /// Processes one block in place. Both slices have the same length.
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]);
+
+    /// Clears all internal state (delay lines, envelopes, ...).
+    fn reset(&mut self);
+
+    /// Delay this effect adds to the signal, in samples.
+    fn latency(&self) -> usize {
+        0
+    }
+
+    /// A copy of this effect for `sample_rate`. The default builds a fresh
+    /// one with the same parameters; effects holding something the user
+    /// made, like a recorded loop, override it to bring that along too.
+    fn rebuild(&self, sample_rate: f32) -> Option<Box<dyn Effect>> {
+        let mut fresh = create(self.kind(), sample_rate)?;
+        for info in self.params() {
+            if let Some(value) = self.get_param(info.name) {
+                fresh.set_param(info.name, value);
+            }
+        }
+        Some(fresh)
+    }
+}
+
+/// Every kind [`create`] knows about.
+pub const KINDS: &[&str] = &[
+    PitchShift::KIND,
+    PitchCorrection::KIND,
+    Harmonizer::KIND,
+    Reverb::KIND,
+    VoiceCharacter::KIND,
+    Vocoder::KIND,
+    RingMod::KIND,
+    Looper::KIND,
+    Distortion::KIND,
+    SpectralFreeze::KIND,
+    SpectralBlur::KIND,
+    BinShift::KIND,
+    Glitch::KIND,
+    Transmission::KIND,
+    Vinyl::KIND,
+    Radio::KIND,
+];
+
+/// Builds an effect by its kind identifier.
+pub fn create(kind: &str, sample_rate: f32) -> Option<Box<dyn Effect>> {
+    let effect: Box<dyn Effect> = match kind {
+        PitchShift::KIND => Box::new(PitchShift::new(sample_rate)),
+        PitchCorrection::KIND => Box::new(PitchCorrection::new(sample_rate)),
+        Harmonizer::KIND => Box::new(Harmonizer::new(sample_rate)),
+        Reverb::KIND => Box::new(Reverb::new(sample_rate)),
+        VoiceCharacter::KIND => Box::new(VoiceCharacter::new(sample_rate)),
+        Vocoder::KIND => Box::new(Vocoder::new(sample_rate)),
+        RingMod::KIND => Box::new(RingMod::new(sample_rate)),
+        Looper::KIND => Box::new(Looper::new(sample_rate)),
+        Distortion::KIND => Box::new(Distortion::new(sample_rate)),
+        SpectralFreeze::KIND => Box::new(SpectralFreeze::new(sample_rate)),
+        SpectralBlur::KIND => Box::new(SpectralBlur::new(sample_rate)),
+        BinShift::KIND => Box::new(BinShift::new(sample_rate)),
+        Glitch::KIND => Box::new(Glitch::new(sample_rate)),
+        Transmission::KIND => Box::new(Transmission::new(sample_rate)),
+        Vinyl::KIND => Box::new(Vinyl::new(sample_rate)),
+        Radio::KIND => Box::new(Radio::new(sample_rate)),
+        _ => return None,
+    };
+    Some(effect)
+}
+
+/// Where the effect at `index` ends up after `removed` is taken out of a
+/// chain, or `None` if it was the one removed. Lets anything that points
+/// into a chain by index follow [`EffectChain::remove`].
+pub fn index_after_removal(index: usize, removed: usize) -> Option<usize> {
+    match index {
+        i if i == removed => None,
+        i if i > removed => Some(i - 1),
+        i => Some(i),
+    }
+}
+
+/// Where the effect at `index` ends up after [`EffectChain::move_effect`]
+/// moves `from` to `to`.
+pub fn index_after_move(index: usize, from: usize, to: usize) -> usize {
+    match index {
+        i if i == from => to,
+        i if from < to && (from + 1..=to).contains(&i) => i - 1,
+        i if to < from && (to..from).contains(&i) => i + 1,
+        i => i,
+    }
+}
+
+/// Ordered list of effects applied one after another.
+#[derive(Default)]
+pub struct EffectChain {
+    effects: Vec<Box<dyn Effect>>,
+}
+
+impl EffectChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.effects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    pub fn push(&mut self, effect: Box<dyn Effect>) {
+        self.effects.push(effect);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Box<dyn Effect>> {
+        (index < self.effects.len()).then(|| self.effects.remove(index))
+    }
+
+    /// Moves the effect at `from` so that it ends up at position `to`.
+    pub fn move_effect(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.effects.len() || to >= self.effects.len() {
+            return false;
+        }
+        let effect = self.effects.remove(from);
+        self.effects.insert(to, effect);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.effects.clear();
+    }
+
+    pub fn get(&self, index: usize) -> Option<&dyn Effect> {
+        self.effects.get(index).map(|e| e.as_ref())
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut (dyn Effect + 'static)> {
+        self.effects.get_mut(index).map(|e| e.as_mut())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Effect> {
+        self.effects.iter().map(|e| e.as_ref())
+    }
+
+    pub fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for effect in self.effects.iter_mut() {
+            effect.process(left, right);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.effects.iter_mut().for_each(|e| e.reset());
+    }
+
+    /// Recreates every effect at a new sample rate with
+    /// [`Effect::rebuild`], carrying parameters over.
+    pub fn rebuild(&mut self, sample_rate: f32) {
+        for effect in self.effects.iter_mut() {
+            if let Some(fresh) = effect.rebuild(sample_rate) {
+                *effect = fresh;
+            }
+        }
+    }
+
+    /// Total latency of the chain, in samples.
+    pub fn latency(&self) -> usize {
+        self.effects.iter().map(|e| e.latency()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_known_and_unknown_kinds() {
+        assert!(create(PitchShift::KIND, 48_000.0).is_some());
+        assert!(create(PitchCorrection::KIND, 48_000.0).is_some());
+        assert!(create(Harmonizer::KIND, 48_000.0).is_some());
+        assert!(create(Reverb::KIND, 48_000.0).is_some());
+        assert!(create(VoiceCharacter::KIND, 48_000.0).is_some());
+        assert!(create(Vocoder::KIND, 48_000.0).is_some());
+        assert!(create(RingMod::KIND, 48_000.0).is_some());
+        assert!(create(Looper::KIND, 48_000.0).is_some());
+        assert!(create(Distortion::KIND, 48_000.0).is_some());
+        assert!(create(SpectralFreeze::KIND, 48_000.0).is_some());
+        assert!(create(SpectralBlur::KIND, 48_000.0).is_some());
+        assert!(create(BinShift::KIND, 48_000.0).is_some());
+        assert!(create(Glitch::KIND, 48_000.0).is_some());
+        assert!(create(Transmission::KIND, 48_000.0).is_some());
+        assert!(create(Vinyl::KIND, 48_000.0).is_some());
+        assert!(create(Radio::KIND, 48_000.0).is_some());
+        assert!(create("does-not-exist", 48_000.0).is_none());
+        for kind in KINDS {
+            assert_eq!(create(kind, 48_000.0).map(|e| e.kind()), Some(*kind));
+        }
+    }
+
+    #[test]
+    fn test_chain_edits() {
+        let mut chain = EffectChain::new();
+        chain.push(create(PitchShift::KIND, 48_000.0).unwrap());
+        chain.push(create(PitchCorrection::KIND, 48_000.0).unwrap());
+
+        assert!(chain.move_effect(1, 0));
+        assert_eq!(chain.get(0).unwrap().kind(), PitchCorrection::KIND);
+        assert!(!chain.move_effect(0, 5));
+        assert!(chain.remove(0).is_some());
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_params_are_clamped() {
+        let mut effect = create(PitchShift::KIND, 48_000.0).unwrap();
+        assert!(effect.set_param("semitones", 100.0));
+        assert_eq!(effect.get_param("semitones"), Some(24.0));
+        assert!(effect.set_param("semitones", f32::NAN));
+        assert_eq!(effect.get_param("semitones"), Some(-12.0));
+        assert!(!effect.set_param("nope", 1.0));
+    }
+}
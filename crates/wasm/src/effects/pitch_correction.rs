@@ -0,0 +1,253 @@
+use super::{find_param, Effect, ParamInfo};
+use crate::dsp::{
+    hz_to_midi, one_pole_coeff, semitones_to_ratio, DelayLine, PitchDetector, Psola, Scale,
+};
+
+const PARAMS: &[ParamInfo] = &[
+    ParamInfo::new("key", 0.0, 11.0, 0.0),
+    ParamInfo::new("scale", 0.0, (Scale::ALL.len() - 1) as f32, 1.0),
+    ParamInfo::new("retune_ms", 0.0, 400.0, 20.0),
+    ParamInfo::new("humanize", 0.0, 1.0, 0.0),
+    ParamInfo::new("formant", 0.0, 1.0, 1.0),
+    ParamInfo::new("mix", 0.0, 1.0, 1.0),
+];
+
+const MIN_HZ: f32 = 80.0;
+const MAX_HZ: f32 = 1000.0;
+/// Correction applied past a scale boundary before switching target notes,
+/// in semitones. Stops the target flickering on notes sung between degrees.
+const HYSTERESIS: f32 = 0.15;
+/// Below this periodicity the input is treated as unvoiced and left alone.
+const MIN_CONFIDENCE: f32 = 0.8;
+/// Extra retune time humanize adds to a fully sustained note.
+const HUMANIZE_MS: f32 = 300.0;
+/// How long a note has to be held to count as fully sustained.
+const SUSTAIN_SECONDS: f32 = 0.5;
+
+/// Pitch correction ("auto-tune") that snaps the voice to a key and scale.
+///
+/// The mid signal is tracked with YIN and each channel is re-pitched with
+/// PSOLA so the formants stay in place. `retune_ms` sets how quickly the
+/// pitch is pulled onto the target note (0 gives the hard, robotic effect),
+/// `humanize` slows the pull on sustained notes so vibrato survives, and
+/// `formant` blends between preserved (1) and pitch-following (0) formants.
+pub struct PitchCorrection {
+    sample_rate: f32,
+    detector: PitchDetector,
+    shifters: [Psola; 2],
+    dry: [DelayLine; 2],
+    key: i32,
+    scale: Scale,
+    retune_ms: f32,
+    humanize: f32,
+    formant: f32,
+    mix: f32,
+    target_note: Option<i32>,
+    target_correction: f32,
+    correction: f32,
+    held_samples: usize,
+}
+
+impl PitchCorrection {
+    pub const KIND: &'static str = "pitch_correction";
+
+    pub fn new(sample_rate: f32) -> Self {
+        let max_period = (sample_rate / MIN_HZ).ceil() as usize;
+        let shifters = [Psola::new(max_period, 1.25), Psola::new(max_period, 1.25)];
+        let latency = shifters[0].latency();
+
+        let mut effect = Self {
+            sample_rate,
+            detector: PitchDetector::new(sample_rate, MIN_HZ, MAX_HZ),
+            shifters,
+            dry: [DelayLine::new(latency), DelayLine::new(latency)],
+            key: 0,
+            scale: Scale::Major,
+            retune_ms: 0.0,
+            humanize: 0.0,
+            formant: 0.0,
+            mix: 0.0,
+            target_note: None,
+            target_correction: 0.0,
+            correction: 0.0,
+            held_samples: 0,
+        };
+        for info in PARAMS {
+            effect.set_param(info.name, info.default);
+        }
+        effect
+    }
+
+    /// Current correction being applied, in semitones.
+    pub fn correction(&self) -> f32 {
+        self.correction
+    }
+
+    fn update_target(&mut self) {
+        let tracked = self
+            .detector
+            .frequency()
+            .filter(|_| self.detector.confidence() >= MIN_CONFIDENCE);
+
+        let Some(hz) = tracked else {
+            self.target_note = None;
+            self.target_correction = 0.0;
+            self.held_samples = 0;
+            return;
+        };
+
+        let note = hz_to_midi(hz);
+        let nearest = self.scale.quantize(note, self.key);
+        let target = match self.target_note {
+            Some(current)
+                if current != nearest
+                    && self.scale.contains(current, self.key)
+                    && (note - current as f32).abs()
+                        < (note - nearest as f32).abs() + HYSTERESIS =>
+            {
+                current
+            }
+            _ => nearest,
+        };
+
+        if self.target_note != Some(target) {
+            self.held_samples = 0;
+        }
+        self.target_note = Some(target);
+        self.target_correction = target as f32 - note;
+
+        let period = self.sample_rate / hz;
+        self.shifters.iter_mut().for_each(|s| s.set_period(period));
+    }
+
+    fn smoothing_coeff(&self) -> f32 {
+        let held = self.held_samples as f32 / (self.sample_rate * SUSTAIN_SECONDS);
+        let time_ms = self.retune_ms + self.humanize * HUMANIZE_MS * held.min(1.0);
+        one_pole_coeff(time_ms, self.sample_rate)
+    }
+}
+
+impl Effect for PitchCorrection {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        PARAMS
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        let Some(info) = find_param(PARAMS, name) else {
+            return false;
+        };
+        let value = info.clamp(value);
+        match name {
+            "key" => self.key = value.round() as i32,
+            "scale" => self.scale = Scale::from_index(value.round() as usize),
+            "retune_ms" => self.retune_ms = value,
+            "humanize" => self.humanize = value,
+            "formant" => self.formant = value,
+            "mix" => self.mix = value,
+            _ => return false,
+        }
+        true
+    }
+
+    fn get_param(&self, name: &str) -> Option<f32> {
+        match name {
+            "key" => Some(self.key as f32),
+            "scale" => Scale::ALL
+                .iter()
+                .position(|&s| s == self.scale)
+                .map(|i| i as f32),
+            "retune_ms" => Some(self.retune_ms),
+            "humanize" => Some(self.humanize),
+            "formant" => Some(self.formant),
+            "mix" => Some(self.mix),
+            _ => None,
+        }
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let latency = self.shifters[0].latency();
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            if self.detector.push((*l + *r) * 0.5) {
+                self.update_target();
+            }
+
+            let coeff = self.smoothing_coeff();
+            self.correction =
+                self.target_correction + (self.correction - self.target_correction) * coeff;
+            if self.target_note.is_some() {
+                self.held_samples += 1;
+            }
+
+            let ratio = semitones_to_ratio(self.correction);
+            let envelope = ratio.powf(1.0 - self.formant);
+            let [shifter_l, shifter_r] = &mut self.shifters;
+            let [dry_l, dry_r] = &mut self.dry;
+            let channels = [(l, shifter_l, dry_l), (r, shifter_r, dry_r)];
+            for (sample, shifter, dry) in channels {
+                shifter.set_ratio(ratio);
+                shifter.set_formant(envelope);
+                let wet = shifter.process(*sample);
+                dry.push(*sample);
+                let delayed = dry.read(latency);
+                *sample = delayed + (wet - delayed) * self.mix;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.detector.reset();
+        self.shifters.iter_mut().for_each(|s| s.reset());
+        self.dry.iter_mut().for_each(|d| d.clear());
+        self.target_note = None;
+        self.target_correction = 0.0;
+        self.correction = 0.0;
+        self.held_samples = 0;
+    }
+
+    fn latency(&self) -> usize {
+        self.shifters[0].latency()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::midi_to_hz;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_flat_note_is_pulled_onto_scale() {
+        let sample_rate = 48_000.0;
+        let mut effect = PitchCorrection::new(sample_rate);
+        effect.set_param("retune_ms", 0.0);
+
+        // A4 sung 30 cents flat should be corrected up by ~0.3 semitones.
+        let hz = midi_to_hz(68.7);
+        let mut left: Vec<f32> = (0..24_000)
+            .map(|i| (2.0 * PI * hz * i as f32 / sample_rate).sin() * 0.5)
+            .collect();
+        let mut right = left.clone();
+        effect.process(&mut left, &mut right);
+
+        assert!(
+            (effect.correction() - 0.3).abs() < 0.05,
+            "correction was {}",
+            effect.correction()
+        );
+        assert!(left.iter().all(|s| s.is_finite() && s.abs() <= 1.0));
+    }
+
+    #[test]
+    fn test_silence_is_not_corrected() {
+        let mut effect = PitchCorrection::new(48_000.0);
+        let mut left = vec![0.0; 4096];
+        let mut right = vec![0.0; 4096];
+        effect.process(&mut left, &mut right);
+        assert_eq!(effect.correction(), 0.0);
+        assert!(left.iter().all(|&s| s == 0.0));
+    }
+}
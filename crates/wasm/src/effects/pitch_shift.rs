@@ -0,0 +1,130 @@
+use super::{find_param, Effect, ParamInfo};
+use crate::dsp::{semitones_to_ratio, DelayLine, PitchShifter};
+
+const PARAMS: &[ParamInfo] = &[
+    ParamInfo::new("semitones", -24.0, 24.0, -12.0),
+    ParamInfo::new("window_ms", 10.0, 100.0, 40.0),
+    ParamInfo::new("mix", 0.0, 1.0, 1.0),
+];
+
+/// Fixed-interval pitch shift. The default drops one octave, which is what
+/// the processor used to do with its half-speed read. The dry signal is
+/// delayed by the shifter's latency so a partial `mix` doesn't echo.
+pub struct PitchShift {
+    sample_rate: f32,
+    shifters: [PitchShifter; 2],
+    dry: [DelayLine; 2],
+    semitones: f32,
+    window_ms: f32,
+    mix: f32,
+}
+
+impl PitchShift {
+    pub const KIND: &'static str = "pitch_shift";
+
+    pub fn new(sample_rate: f32) -> Self {
+        let max_window = (sample_rate * 0.1).ceil() as usize;
+        let mut effect = Self {
+            sample_rate,
+            shifters: [PitchShifter::new(max_window), PitchShifter::new(max_window)],
+            dry: [
+                DelayLine::new(max_window / 2 + 1),
+                DelayLine::new(max_window / 2 + 1),
+            ],
+            semitones: 0.0,
+            window_ms: 0.0,
+            mix: 0.0,
+        };
+        for info in PARAMS {
+            effect.set_param(info.name, info.default);
+        }
+        effect
+    }
+}
+
+impl Effect for PitchShift {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        PARAMS
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        let Some(info) = find_param(PARAMS, name) else {
+            return false;
+        };
+        let value = info.clamp(value);
+        match name {
+            "semitones" => {
+                self.semitones = value;
+                let ratio = semitones_to_ratio(value);
+                self.shifters.iter_mut().for_each(|s| s.set_ratio(ratio));
+            }
+            "window_ms" => {
+                self.window_ms = value;
+                let samples = value * 0.001 * self.sample_rate;
+                self.shifters.iter_mut().for_each(|s| s.set_window(samples));
+            }
+            "mix" => self.mix = value,
+            _ => return false,
+        }
+        true
+    }
+
+    fn get_param(&self, name: &str) -> Option<f32> {
+        match name {
+            "semitones" => Some(self.semitones),
+            "window_ms" => Some(self.window_ms),
+            "mix" => Some(self.mix),
+            _ => None,
+        }
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let latency = self.shifters[0].latency();
+        let [shifter_l, shifter_r] = &mut self.shifters;
+        let [dry_l, dry_r] = &mut self.dry;
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let channels = [
+                (l, &mut *shifter_l, &mut *dry_l),
+                (r, &mut *shifter_r, &mut *dry_r),
+            ];
+            for (sample, shifter, dry) in channels {
+                let wet = shifter.process(*sample);
+                dry.push(*sample);
+                let delayed = dry.read(latency);
+                *sample = delayed + (wet - delayed) * self.mix;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.shifters.iter_mut().for_each(|s| s.reset());
+        self.dry.iter_mut().for_each(|d| d.clear());
+    }
+
+    fn latency(&self) -> usize {
+        self.shifters[0].latency()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dry_path_is_delayed_by_the_latency() {
+        let mut effect = PitchShift::new(48_000.0);
+        effect.set_param("mix", 0.0);
+        assert_eq!(effect.latency(), 960);
+
+        let mut left = vec![0.0; 2048];
+        left[0] = 1.0;
+        let mut right = left.clone();
+        effect.process(&mut left, &mut right);
+        assert_eq!(left.iter().position(|s| *s != 0.0), Some(effect.latency()));
+        assert_eq!(left, right);
+    }
+}
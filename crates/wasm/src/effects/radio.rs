@@ -0,0 +1,276 @@
+use super::{find_param, Effect, ParamInfo};
+use crate::dsp::{flush_denormal, one_pole_coeff, Biquad, FilterKind, Oscillator, Waveform};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const PARAMS: &[ParamInfo] = &[
+    ParamInfo::new("bandwidth_hz", 1000.0, 8000.0, 3500.0),
+    ParamInfo::new("static", 0.0, 1.0, 0.2),
+    ParamInfo::new("fading", 0.0, 1.0, 0.4),
+    ParamInfo::new("whistle", 0.0, 1.0, 0.1),
+    ParamInfo::new("drift", 0.0, 1.0, 0.3),
+    ParamInfo::new("seed", 0.0, 65_535.0, 1.0),
+    ParamInfo::new("mix", 0.0, 1.0, 1.0),
+];
+
+const LOW_CUT_HZ: f32 = 250.0;
+/// Heterodyne between the station and a neighbour, in the middle of the
+/// voice band.
+const WHISTLE_HZ: f32 = 1400.0;
+/// How far full `drift` pulls the tuning, moving the whistle and the edge
+/// of the passband.
+const MAX_DRIFT_HZ: f32 = 600.0;
+const WHISTLE_GAIN: f32 = 0.08;
+const HISS_GAIN: f32 = 0.12;
+const BURST_GAIN: f32 = 0.6;
+/// Static bursts per second at full `static`.
+const BURST_RATE: f32 = 1.5;
+
+/// A value in `[0, 1]` that wanders: it glides towards a fresh random
+/// target every `period` samples.
+struct Wander {
+    value: f32,
+    target: f32,
+    countdown: usize,
+    period: usize,
+    coeff: f32,
+}
+
+impl Wander {
+    fn new(sample_rate: f32, period_ms: f32) -> Self {
+        Self {
+            value: 0.0,
+            target: 0.0,
+            countdown: 0,
+            period: (period_ms * 0.001 * sample_rate) as usize,
+            coeff: one_pole_coeff(period_ms * 0.5, sample_rate),
+        }
+    }
+
+    fn next(&mut self, rng: &mut StdRng) -> f32 {
+        if self.countdown == 0 {
+            self.countdown = self.period;
+            self.target = rng.gen();
+        }
+        self.countdown -= 1;
+        self.value = self.target + (self.value - self.target) * self.coeff;
+        self.value
+    }
+
+    fn reset(&mut self) {
+        self.value = 0.0;
+        self.target = 0.0;
+        self.countdown = 0;
+    }
+}
+
+/// AM and shortwave radio reception: a narrow, mono passband with hiss and
+/// crackling bursts of static, signal fading in and out, a heterodyne
+/// whistle from a neighbouring station and tuning that drifts.
+///
+/// `drift` sets how far the tuning wanders, which pulls both the whistle's
+/// pitch and the top of the passband along with it. As the signal fades,
+/// the hiss comes up the way a receiver's automatic gain control lifts it.
+/// All the randomness comes from a generator seeded by `seed`.
+pub struct Radio {
+    sample_rate: f32,
+    low_cut: Biquad,
+    band: [Biquad; 2],
+    whistle_osc: Oscillator,
+    fade: Wander,
+    tuning: Wander,
+    burst: f32,
+    burst_decay: f32,
+    rng: StdRng,
+    bandwidth_hz: f32,
+    static_level: f32,
+    fading: f32,
+    whistle: f32,
+    drift: f32,
+    seed: f32,
+    mix: f32,
+}
+
+impl Radio {
+    pub const KIND: &'static str = "radio";
+
+    pub fn new(sample_rate: f32) -> Self {
+        let mut effect = Self {
+            sample_rate,
+            low_cut: Biquad::new(FilterKind::HighPass, sample_rate, LOW_CUT_HZ, 0.7, 0.0),
+            band: [Biquad::default(); 2],
+            whistle_osc: Oscillator::new(sample_rate, Waveform::Sine),
+            fade: Wander::new(sample_rate, 1500.0),
+            tuning: Wander::new(sample_rate, 4000.0),
+            burst: 0.0,
+            burst_decay: 0.0,
+            rng: StdRng::seed_from_u64(1),
+            bandwidth_hz: 0.0,
+            static_level: 0.0,
+            fading: 0.0,
+            whistle: 0.0,
+            drift: 0.0,
+            seed: 0.0,
+            mix: 0.0,
+        };
+        for info in PARAMS {
+            effect.set_param(info.name, info.default);
+        }
+        effect
+    }
+
+    /// Tuning offset in hertz, from -`MAX_DRIFT_HZ` to +`MAX_DRIFT_HZ` at
+    /// full drift.
+    fn detune(&self) -> f32 {
+        (self.tuning.value * 2.0 - 1.0) * self.drift * MAX_DRIFT_HZ
+    }
+
+    fn update_band(&mut self) {
+        let cutoff = (self.bandwidth_hz - self.detune().abs()).max(LOW_CUT_HZ * 2.0);
+        for (filter, q) in self.band.iter_mut().zip([0.54, 1.31]) {
+            filter.design(FilterKind::LowPass, self.sample_rate, cutoff, q, 0.0);
+        }
+    }
+}
+
+impl Effect for Radio {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        PARAMS
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        let Some(info) = find_param(PARAMS, name) else {
+            return false;
+        };
+        let value = info.clamp(value);
+        match name {
+            "bandwidth_hz" => {
+                self.bandwidth_hz = value;
+                self.update_band();
+            }
+            "static" => self.static_level = value,
+            "fading" => self.fading = value,
+            "whistle" => self.whistle = value,
+            "drift" => {
+                self.drift = value;
+                self.update_band();
+            }
+            "seed" => {
+                self.seed = value.round();
+                self.rng = StdRng::seed_from_u64(self.seed as u64);
+            }
+            "mix" => self.mix = value,
+            _ => return false,
+        }
+        true
+    }
+
+    fn get_param(&self, name: &str) -> Option<f32> {
+        match name {
+            "bandwidth_hz" => Some(self.bandwidth_hz),
+            "static" => Some(self.static_level),
+            "fading" => Some(self.fading),
+            "whistle" => Some(self.whistle),
+            "drift" => Some(self.drift),
+            "seed" => Some(self.seed),
+            "mix" => Some(self.mix),
+            _ => None,
+        }
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        // Tuning moves slowly enough to follow once a block.
+        self.update_band();
+        self.whistle_osc.set_frequency(WHISTLE_HZ + self.detune());
+        let burst_chance = self.static_level * BURST_RATE / self.sample_rate;
+
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let input = 0.5 * (*l + *r);
+            self.tuning.next(&mut self.rng);
+            let gain = 1.0 - self.fading * self.fade.next(&mut self.rng);
+
+            if self.rng.gen::<f32>() < burst_chance {
+                self.burst = self.rng.gen_range(0.3..1.0);
+                let length_ms = self.rng.gen_range(40.0..300.0);
+                self.burst_decay = one_pole_coeff(length_ms, self.sample_rate);
+            }
+            self.burst = flush_denormal(self.burst * self.burst_decay);
+            let noise: f32 = self.rng.gen_range(-1.0..1.0);
+            // Bursts crackle rather than hiss: most samples are damped.
+            let crackle = if self.rng.gen::<f32>() < 0.3 {
+                1.0
+            } else {
+                0.2
+            };
+            let hiss = noise * HISS_GAIN * self.static_level * (0.3 + 0.7 * (1.0 - gain));
+            let burst = noise * crackle * self.burst * BURST_GAIN * self.static_level;
+            let whistle = self.whistle_osc.next_sample() * self.whistle * WHISTLE_GAIN;
+
+            let received = input * gain + hiss + burst + whistle;
+            let filtered = self
+                .band
+                .iter_mut()
+                .fold(self.low_cut.process(received), |x, filter| {
+                    filter.process(x)
+                });
+            let wet = flush_denormal(filtered);
+
+            *l += (wet - *l) * self.mix;
+            *r += (wet - *r) * self.mix;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.low_cut.reset();
+        self.band.iter_mut().for_each(Biquad::reset);
+        self.whistle_osc.reset();
+        self.fade.reset();
+        self.tuning.reset();
+        self.burst = 0.0;
+        self.rng = StdRng::seed_from_u64(self.seed as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peak_of_tone(effect: &mut Radio, freq: f32) -> f32 {
+        let mut left: Vec<f32> = (0..24_000)
+            .map(|i| (std::f32::consts::TAU * freq * i as f32 / 48_000.0).sin() * 0.5)
+            .collect();
+        let mut right = left.clone();
+        effect.process(&mut left, &mut right);
+        left[4800..].iter().fold(0.0f32, |m, x| m.max(x.abs()))
+    }
+
+    #[test]
+    fn test_passes_the_voice_band_and_seeds_the_static() {
+        let mut clean = Radio::new(48_000.0);
+        for name in ["static", "fading", "whistle", "drift"] {
+            clean.set_param(name, 0.0);
+        }
+        assert!(peak_of_tone(&mut clean, 1000.0) > 0.4);
+        clean.reset();
+        assert!(peak_of_tone(&mut clean, 9000.0) < 0.02);
+        clean.reset();
+        assert!(peak_of_tone(&mut clean, 60.0) < 0.05);
+
+        let noisy = |seed: f32| {
+            let mut effect = Radio::new(48_000.0);
+            effect.set_param("static", 1.0);
+            effect.set_param("seed", seed);
+            let mut left = vec![0.0; 9600];
+            let mut right = vec![0.0; 9600];
+            effect.process(&mut left, &mut right);
+            left
+        };
+        assert!(noisy(3.0).iter().any(|s| s.abs() > 0.01));
+        assert_eq!(noisy(3.0), noisy(3.0));
+        assert_ne!(noisy(3.0), noisy(4.0));
+    }
+}
@@ -0,0 +1,172 @@
+use super::{find_param, Effect, ParamInfo};
+use crate::dsp::{semitones_to_ratio, DelayLine, Fdn, PitchShifter};
+
+const PARAMS: &[ParamInfo] = &[
+    ParamInfo::new("size", 0.0, 1.0, 0.6),
+    ParamInfo::new("decay", 0.1, 20.0, 2.5),
+    ParamInfo::new("damping", 0.0, 0.95, 0.4),
+    ParamInfo::new("predelay_ms", 0.0, 200.0, 10.0),
+    ParamInfo::new("shimmer", 0.0, 1.0, 0.0),
+    ParamInfo::new("shimmer_semitones", -24.0, 24.0, 12.0),
+    ParamInfo::new("mix", 0.0, 1.0, 0.3),
+];
+
+/// Algorithmic reverb with an optional shimmer mode.
+///
+/// With `shimmer` above zero the reverb output is pitch shifted and fed back
+/// into its own input, so every trip round the loop climbs another interval
+/// and the tail blooms upward. The loop is soft-clipped so long decays with
+/// full shimmer stay bounded.
+pub struct Reverb {
+    sample_rate: f32,
+    fdn: Fdn,
+    predelay: DelayLine,
+    shifter: PitchShifter,
+    feedback: f32,
+    size: f32,
+    decay: f32,
+    damping: f32,
+    predelay_ms: f32,
+    shimmer: f32,
+    shimmer_semitones: f32,
+    mix: f32,
+}
+
+impl Reverb {
+    pub const KIND: &'static str = "reverb";
+
+    pub fn new(sample_rate: f32) -> Self {
+        let mut shifter = PitchShifter::new((sample_rate * 0.1) as usize);
+        shifter.set_window(sample_rate * 0.06);
+
+        let mut effect = Self {
+            sample_rate,
+            fdn: Fdn::new(sample_rate),
+            predelay: DelayLine::new((sample_rate * 0.2) as usize + 1),
+            shifter,
+            feedback: 0.0,
+            size: 0.0,
+            decay: 0.0,
+            damping: 0.0,
+            predelay_ms: 0.0,
+            shimmer: 0.0,
+            shimmer_semitones: 0.0,
+            mix: 0.0,
+        };
+        for info in PARAMS {
+            effect.set_param(info.name, info.default);
+        }
+        effect
+    }
+}
+
+impl Effect for Reverb {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        PARAMS
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        let Some(info) = find_param(PARAMS, name) else {
+            return false;
+        };
+        let value = info.clamp(value);
+        match name {
+            "size" => {
+                self.size = value;
+                self.fdn.set_size(value);
+            }
+            "decay" => {
+                self.decay = value;
+                self.fdn.set_decay(value);
+            }
+            "damping" => {
+                self.damping = value;
+                self.fdn.set_damping(value);
+            }
+            "predelay_ms" => self.predelay_ms = value,
+            "shimmer" => self.shimmer = value,
+            "shimmer_semitones" => {
+                self.shimmer_semitones = value;
+                self.shifter.set_ratio(semitones_to_ratio(value));
+            }
+            "mix" => self.mix = value,
+            _ => return false,
+        }
+        true
+    }
+
+    fn get_param(&self, name: &str) -> Option<f32> {
+        match name {
+            "size" => Some(self.size),
+            "decay" => Some(self.decay),
+            "damping" => Some(self.damping),
+            "predelay_ms" => Some(self.predelay_ms),
+            "shimmer" => Some(self.shimmer),
+            "shimmer_semitones" => Some(self.shimmer_semitones),
+            "mix" => Some(self.mix),
+            _ => None,
+        }
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let predelay = (self.predelay_ms * 0.001 * self.sample_rate) as usize;
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            self.predelay.push((*l + *r) * 0.5);
+            let mut input = self.predelay.read(predelay);
+
+            if self.shimmer > 0.0 {
+                let shifted = self.shifter.process(self.feedback);
+                input += (shifted * self.shimmer).tanh();
+            }
+
+            let (wet_l, wet_r) = self.fdn.process(input);
+            self.feedback = (wet_l + wet_r) * 0.5;
+
+            *l += (wet_l - *l) * self.mix;
+            *r += (wet_r - *r) * self.mix;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.fdn.reset();
+        self.predelay.clear();
+        self.shifter.reset();
+        self.feedback = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_shimmer_stays_bounded() {
+        let mut reverb = Reverb::new(48_000.0);
+        reverb.set_param("decay", 20.0);
+        reverb.set_param("shimmer", 1.0);
+        reverb.set_param("mix", 1.0);
+
+        let mut peak = 0.0_f32;
+        for block in 0..2000 {
+            let mut left = [0.0; 128];
+            let mut right = [0.0; 128];
+            if block < 40 {
+                for (i, s) in left.iter_mut().enumerate() {
+                    *s = ((block * 128 + i) as f32 * 0.05).sin() * 0.5;
+                }
+                right.copy_from_slice(&left);
+            }
+            reverb.process(&mut left, &mut right);
+            peak = left
+                .iter()
+                .chain(right.iter())
+                .fold(peak, |m, s| m.max(s.abs()));
+        }
+
+        assert!(peak.is_finite() && peak < 4.0, "peak {peak}");
+    }
+}
@@ -0,0 +1,123 @@
+use super::{find_param, Effect, ParamInfo};
+use crate::dsp::{Oscillator, Waveform};
+
+const PARAMS: &[ParamInfo] = &[
+    ParamInfo::new("freq_hz", 1.0, 4000.0, 220.0),
+    ParamInfo::new("lfo_rate_hz", 0.0, 20.0, 0.5),
+    ParamInfo::new("lfo_depth", 0.0, 1.0, 0.0),
+    ParamInfo::new("mix", 0.0, 1.0, 1.0),
+];
+
+/// Octaves the LFO sweeps the carrier either side at full depth.
+const LFO_OCTAVES: f32 = 2.0;
+
+/// Ring modulator: multiplies the input by an internal sine carrier whose
+/// frequency can be swept by a sine LFO.
+pub struct RingMod {
+    carrier: Oscillator,
+    lfo: Oscillator,
+    freq_hz: f32,
+    lfo_rate_hz: f32,
+    lfo_depth: f32,
+    mix: f32,
+}
+
+impl RingMod {
+    pub const KIND: &'static str = "ring_mod";
+
+    pub fn new(sample_rate: f32) -> Self {
+        let mut effect = Self {
+            carrier: Oscillator::new(sample_rate, Waveform::Sine),
+            lfo: Oscillator::new(sample_rate, Waveform::Sine),
+            freq_hz: 0.0,
+            lfo_rate_hz: 0.0,
+            lfo_depth: 0.0,
+            mix: 0.0,
+        };
+        for info in PARAMS {
+            effect.set_param(info.name, info.default);
+        }
+        effect
+    }
+}
+
+impl Effect for RingMod {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        PARAMS
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        let Some(info) = find_param(PARAMS, name) else {
+            return false;
+        };
+        let value = info.clamp(value);
+        match name {
+            "freq_hz" => {
+                self.freq_hz = value;
+                self.carrier.set_frequency(value);
+            }
+            "lfo_rate_hz" => {
+                self.lfo_rate_hz = value;
+                self.lfo.set_frequency(value);
+            }
+            "lfo_depth" => self.lfo_depth = value,
+            "mix" => self.mix = value,
+            _ => return false,
+        }
+        true
+    }
+
+    fn get_param(&self, name: &str) -> Option<f32> {
+        match name {
+            "freq_hz" => Some(self.freq_hz),
+            "lfo_rate_hz" => Some(self.lfo_rate_hz),
+            "lfo_depth" => Some(self.lfo_depth),
+            "mix" => Some(self.mix),
+            _ => None,
+        }
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let modulated = self.lfo_depth > 0.0;
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            if modulated {
+                let sweep = self.lfo.next_sample() * self.lfo_depth * LFO_OCTAVES;
+                self.carrier.set_frequency(self.freq_hz * sweep.exp2());
+            }
+            let carrier = self.carrier.next_sample();
+            *l += (*l * carrier - *l) * self.mix;
+            *r += (*r * carrier - *r) * self.mix;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.carrier.reset();
+        self.lfo.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dc_input_becomes_carrier() {
+        let mut effect = RingMod::new(48_000.0);
+        effect.set_param("freq_hz", 1000.0);
+        let mut left = vec![0.5; 480];
+        let mut right = vec![0.5; 480];
+        effect.process(&mut left, &mut right);
+
+        // 1 kHz at 48 kHz: a quarter period in, the carrier is at its peak.
+        assert!((left[12] - 0.5).abs() < 1e-3);
+        let crossings = left
+            .windows(2)
+            .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+            .count();
+        assert_eq!(crossings, 9);
+    }
+}
@@ -0,0 +1,188 @@
+use super::{find_param, Effect, ParamInfo};
+use crate::dsp::stft::{self, expected_advance, wrap_phase};
+use crate::dsp::{flush_denormal, one_pole_coeff, Complex, Stft, Window};
+
+const PARAMS: &[ParamInfo] = &[
+    ParamInfo::new("time_ms", 10.0, 10_000.0, 800.0),
+    ParamInfo::new("mix", 0.0, 1.0, 1.0),
+];
+
+struct Channel {
+    stft: Stft,
+    last_phase: Vec<f32>,
+    /// Running average of every bin's magnitude.
+    magnitude: Vec<f32>,
+    /// Resynthesis phase of every bin.
+    phase: Vec<f32>,
+}
+
+impl Channel {
+    fn new(size: usize) -> Self {
+        let stft = Stft::new(size, size / 4, Window::Hann);
+        let bins = stft.bins();
+        let mut channel = Self {
+            stft,
+            last_phase: vec![0.0; bins],
+            magnitude: vec![0.0; bins],
+            phase: vec![0.0; bins],
+        };
+        channel.reset();
+        channel
+    }
+
+    fn process(&mut self, buffer: &mut [f32], smoothing: f32, mix: f32) {
+        let size = self.stft.size();
+        let hop = self.stft.hop();
+        let Self {
+            stft,
+            last_phase,
+            magnitude,
+            phase,
+        } = self;
+        stft.process(buffer, |bins| {
+            for (k, bin) in bins.iter_mut().enumerate() {
+                let current = bin.arg();
+                let expected = expected_advance(k, size, hop);
+                let advance = expected + wrap_phase(current - last_phase[k] - expected);
+                last_phase[k] = current;
+
+                magnitude[k] += (bin.norm() - magnitude[k]) * (1.0 - smoothing);
+                magnitude[k] = flush_denormal(magnitude[k]);
+                phase[k] = wrap_phase(phase[k] + advance);
+                let blurred = Complex::from_polar(magnitude[k], phase[k]);
+                *bin = bin.scale(1.0 - mix) + blurred.scale(mix);
+            }
+        });
+    }
+
+    fn reset(&mut self) {
+        self.stft.reset();
+        self.last_phase.fill(0.0);
+        self.magnitude.fill(0.0);
+        // Spread the starting phases so bins that only ever carry the
+        // smeared tail don't all line up into a click every few frames.
+        for (k, phase) in self.phase.iter_mut().enumerate() {
+            let golden = (k as f32 * 0.618_034).fract();
+            *phase = wrap_phase(golden * std::f32::consts::TAU);
+        }
+    }
+}
+
+/// Spectral blur: every bin's magnitude is averaged over time while its
+/// phase runs on at the bin's current frequency, so transients and
+/// consonants smear into a wash that fades over `time_ms` while pitch is
+/// kept.
+pub struct SpectralBlur {
+    channels: [Channel; 2],
+    frame_rate: f32,
+    time_ms: f32,
+    /// One-pole coefficient per frame for `time_ms`.
+    smoothing: f32,
+    mix: f32,
+}
+
+impl SpectralBlur {
+    pub const KIND: &'static str = "spectral_blur";
+
+    pub fn new(sample_rate: f32) -> Self {
+        let size = stft::frame_size(sample_rate);
+        let channels = [Channel::new(size), Channel::new(size)];
+        let mut effect = Self {
+            frame_rate: sample_rate / channels[0].stft.hop() as f32,
+            channels,
+            time_ms: 0.0,
+            smoothing: 0.0,
+            mix: 0.0,
+        };
+        for info in PARAMS {
+            effect.set_param(info.name, info.default);
+        }
+        effect
+    }
+}
+
+impl Effect for SpectralBlur {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        PARAMS
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        let Some(info) = find_param(PARAMS, name) else {
+            return false;
+        };
+        let value = info.clamp(value);
+        match name {
+            "time_ms" => {
+                self.time_ms = value;
+                self.smoothing = one_pole_coeff(value, self.frame_rate);
+            }
+            "mix" => self.mix = value,
+            _ => return false,
+        }
+        true
+    }
+
+    fn get_param(&self, name: &str) -> Option<f32> {
+        match name {
+            "time_ms" => Some(self.time_ms),
+            "mix" => Some(self.mix),
+            _ => None,
+        }
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let [l, r] = &mut self.channels;
+        l.process(left, self.smoothing, self.mix);
+        r.process(right, self.smoothing, self.mix);
+    }
+
+    fn reset(&mut self) {
+        self.channels.iter_mut().for_each(Channel::reset);
+    }
+
+    fn latency(&self) -> usize {
+        self.channels[0].stft.latency()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn energy(buffer: &[f32]) -> f32 {
+        buffer.iter().map(|x| x * x).sum()
+    }
+
+    #[test]
+    fn test_smears_a_burst_over_time() {
+        let mut short = SpectralBlur::new(48_000.0);
+        short.set_param("time_ms", 10.0);
+        let mut long = SpectralBlur::new(48_000.0);
+        long.set_param("time_ms", 1000.0);
+
+        let mut tails = Vec::new();
+        for effect in [&mut short, &mut long] {
+            // 50 ms of noise-like signal, then silence.
+            let mut left: Vec<f32> = (0..24_000)
+                .map(|i| {
+                    if i < 2400 {
+                        ((i * 7919) % 113) as f32 / 113.0 - 0.5
+                    } else {
+                        0.0
+                    }
+                })
+                .collect();
+            let mut right = left.clone();
+            effect.process(&mut left, &mut right);
+            assert!(left.iter().all(|x| x.is_finite()));
+            let latency = effect.latency();
+            tails.push(energy(&left[latency + 12_000..latency + 20_000]));
+        }
+        assert!(tails[0] < 1e-6, "short tail {}", tails[0]);
+        assert!(tails[1] > 1e-2, "long tail {}", tails[1]);
+    }
+}
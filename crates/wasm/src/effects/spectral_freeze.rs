@@ -0,0 +1,220 @@
+use super::{find_param, Effect, ParamInfo};
+use crate::dsp::stft::{self, expected_advance, wrap_phase};
+use crate::dsp::{Complex, Stft, Window};
+
+const PARAMS: &[ParamInfo] = &[
+    ParamInfo::new("freeze", 0.0, 1.0, 0.0),
+    ParamInfo::new("mix", 0.0, 1.0, 1.0),
+];
+
+/// One channel's analysis state and captured spectrum.
+struct Channel {
+    stft: Stft,
+    /// Phase of every bin in the previous frame.
+    last_phase: Vec<f32>,
+    /// Phase advance per hop measured at the last frame.
+    advance: Vec<f32>,
+    /// The captured spectrum, as magnitude and running phase per bin plus
+    /// the advance each bin had when it was captured.
+    magnitude: Vec<f32>,
+    phase: Vec<f32>,
+    held_advance: Vec<f32>,
+}
+
+impl Channel {
+    fn new(size: usize) -> Self {
+        let stft = Stft::new(size, size / 4, Window::Hann);
+        let bins = stft.bins();
+        Self {
+            stft,
+            last_phase: vec![0.0; bins],
+            advance: vec![0.0; bins],
+            magnitude: vec![0.0; bins],
+            phase: vec![0.0; bins],
+            held_advance: vec![0.0; bins],
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [f32], frozen: bool, capture: bool, mix: f32) -> bool {
+        let size = self.stft.size();
+        let hop = self.stft.hop();
+        let mut captured = false;
+        let Self {
+            stft,
+            last_phase,
+            advance,
+            magnitude,
+            phase,
+            held_advance,
+        } = self;
+        stft.process(buffer, |bins| {
+            for (k, bin) in bins.iter().enumerate() {
+                // Each bin's true frequency, as a phase advance per hop.
+                let current = bin.arg();
+                let expected = expected_advance(k, size, hop);
+                advance[k] = expected + wrap_phase(current - last_phase[k] - expected);
+                last_phase[k] = current;
+            }
+            if !frozen {
+                return;
+            }
+            if capture && !captured {
+                captured = true;
+                for (k, bin) in bins.iter().enumerate() {
+                    magnitude[k] = bin.norm();
+                    phase[k] = last_phase[k] - advance[k];
+                }
+                held_advance.copy_from_slice(advance);
+            }
+            for (k, bin) in bins.iter_mut().enumerate() {
+                phase[k] = wrap_phase(phase[k] + held_advance[k]);
+                let held = Complex::from_polar(magnitude[k], phase[k]);
+                *bin = bin.scale(1.0 - mix) + held.scale(mix);
+            }
+        });
+        captured
+    }
+
+    fn reset(&mut self) {
+        self.stft.reset();
+        self.last_phase.fill(0.0);
+        self.advance.fill(0.0);
+        self.magnitude.fill(0.0);
+        self.phase.fill(0.0);
+        self.held_advance.fill(0.0);
+    }
+}
+
+/// Spectral freeze: while `freeze` is on, holds the spectrum of the moment
+/// it was switched on and resynthesises it as an endless drone, each bin
+/// keeping the frequency it had then. `mix` blends the live input back in
+/// underneath.
+pub struct SpectralFreeze {
+    channels: [Channel; 2],
+    freeze: f32,
+    /// Still waiting for a frame to capture since `freeze` came on.
+    pending: bool,
+    mix: f32,
+}
+
+impl SpectralFreeze {
+    pub const KIND: &'static str = "spectral_freeze";
+
+    pub fn new(sample_rate: f32) -> Self {
+        let size = stft::frame_size(sample_rate);
+        let mut effect = Self {
+            channels: [Channel::new(size), Channel::new(size)],
+            freeze: 0.0,
+            pending: false,
+            mix: 0.0,
+        };
+        for info in PARAMS {
+            effect.set_param(info.name, info.default);
+        }
+        effect
+    }
+
+    fn is_frozen(&self) -> bool {
+        self.freeze >= 0.5
+    }
+}
+
+impl Effect for SpectralFreeze {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        PARAMS
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        let Some(info) = find_param(PARAMS, name) else {
+            return false;
+        };
+        let value = info.clamp(value);
+        match name {
+            "freeze" => {
+                let was_frozen = self.is_frozen();
+                self.freeze = value;
+                if self.is_frozen() && !was_frozen {
+                    self.pending = true;
+                }
+            }
+            "mix" => self.mix = value,
+            _ => return false,
+        }
+        true
+    }
+
+    fn get_param(&self, name: &str) -> Option<f32> {
+        match name {
+            "freeze" => Some(self.freeze),
+            "mix" => Some(self.mix),
+            _ => None,
+        }
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let frozen = self.is_frozen();
+        let [l, r] = &mut self.channels;
+        // Both channels complete frames on the same samples, so they capture
+        // the same moment.
+        let captured = l.process(left, frozen, self.pending, self.mix);
+        r.process(right, frozen, self.pending, self.mix);
+        if captured {
+            self.pending = false;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.channels.iter_mut().for_each(Channel::reset);
+        self.pending = self.is_frozen();
+    }
+
+    fn latency(&self) -> usize {
+        self.channels[0].stft.latency()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / 48_000.0).sin() * 0.5)
+            .collect()
+    }
+
+    fn rms(buffer: &[f32]) -> f32 {
+        (buffer.iter().map(|x| x * x).sum::<f32>() / buffer.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_holds_a_tone_after_the_input_stops() {
+        let mut effect = SpectralFreeze::new(48_000.0);
+        let mut left = sine(440.0, 9600);
+        let mut right = left.clone();
+        effect.process(&mut left, &mut right);
+        effect.set_param("freeze", 1.0);
+
+        // Silence in, but the frozen tone keeps sounding.
+        let mut left = vec![0.0; 9600];
+        let mut right = vec![0.0; 9600];
+        effect.process(&mut left, &mut right);
+        let tail = &left[4800..];
+        assert!(rms(tail) > 0.2, "rms {}", rms(tail));
+        let crossings = tail
+            .windows(2)
+            .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+            .count();
+        assert!((43..=45).contains(&crossings), "{crossings} crossings");
+
+        effect.set_param("freeze", 0.0);
+        let mut left = vec![0.0; 4800];
+        let mut right = vec![0.0; 4800];
+        effect.process(&mut left, &mut right);
+        assert!(rms(&left[2400..]) < 1e-4);
+    }
+}
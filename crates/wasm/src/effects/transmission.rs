@@ -0,0 +1,400 @@
+use super::{find_param, Effect, ParamInfo};
+use crate::dsp::{one_pole_coeff, stft, Companding, DelayLine, Mdct};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const PARAMS: &[ParamInfo] = &[
+    ParamInfo::new("companding", 0.0, 2.0, 1.0),
+    ParamInfo::new("bitrate_kbps", 6.0, 320.0, 32.0),
+    ParamInfo::new("loss", 0.0, 0.5, 0.03),
+    ParamInfo::new("burst", 0.0, 0.9, 0.3),
+    ParamInfo::new("conceal", 0.0, 1.0, 1.0),
+    ParamInfo::new("jitter_ms", 0.0, MAX_JITTER_MS, 4.0),
+    ParamInfo::new("seed", 0.0, 65_535.0, 1.0),
+    ParamInfo::new("mix", 0.0, 1.0, 1.0),
+];
+
+const MAX_JITTER_MS: f32 = 40.0;
+
+/// Audio carried per network packet, as in most VoIP codecs.
+const PACKET_MS: f32 = 20.0;
+/// Crossfade into and out of a lost packet.
+const FADE_MS: f32 = 2.0;
+/// Finest quantization given to a band, in bits per coefficient.
+const MAX_BAND_BITS: u8 = 12;
+
+/// Per-frame bit allocation and quantization of MDCT coefficients.
+struct Quantizer {
+    /// Start of every band plus the end of the last, widening with
+    /// frequency roughly like critical bands.
+    edges: Vec<usize>,
+    bits: Vec<u8>,
+    level_db: Vec<f32>,
+    /// Bits available per channel per frame.
+    budget: usize,
+}
+
+impl Quantizer {
+    fn new(coefficients: usize) -> Self {
+        let mut edges = vec![0];
+        let mut start = 0;
+        while start < coefficients {
+            start = (start + (start / 4).max(4)).min(coefficients);
+            edges.push(start);
+        }
+        let bands = edges.len() - 1;
+        Self {
+            edges,
+            bits: vec![0; bands],
+            level_db: vec![0.0; bands],
+            budget: 0,
+        }
+    }
+
+    /// Hands out bits one per coefficient of a band at a time, always to
+    /// the band whose quantization noise would be loudest, then quantizes.
+    /// Bands that get nothing drop out, which is what makes low bitrates
+    /// sound watery and band-limited.
+    fn quantize(&mut self, coefficients: &mut [f32]) {
+        for (b, band) in self.edges.windows(2).enumerate() {
+            let band = &coefficients[band[0]..band[1]];
+            let power = band.iter().map(|c| c * c).sum::<f32>() / band.len() as f32;
+            self.level_db[b] = 10.0 * (power + 1e-12).log10();
+        }
+        self.bits.fill(0);
+        let mut remaining = self.budget;
+        loop {
+            let mut best = None;
+            let mut best_score = -100.0;
+            for (b, band) in self.edges.windows(2).enumerate() {
+                let score = self.level_db[b] - 6.02 * self.bits[b] as f32;
+                if band[1] - band[0] <= remaining
+                    && self.bits[b] < MAX_BAND_BITS
+                    && score > best_score
+                {
+                    best = Some(b);
+                    best_score = score;
+                }
+            }
+            let Some(b) = best else { break };
+            self.bits[b] += 1;
+            remaining -= self.edges[b + 1] - self.edges[b];
+        }
+
+        for (band, &bits) in self.edges.windows(2).zip(&self.bits) {
+            let band = &mut coefficients[band[0]..band[1]];
+            if bits == 0 {
+                band.fill(0.0);
+                continue;
+            }
+            let peak = band.iter().fold(0.0f32, |m, c| m.max(c.abs()));
+            if peak == 0.0 {
+                continue;
+            }
+            let steps = (1u32 << (bits - 1)) as f32;
+            for c in band.iter_mut() {
+                *c = (*c / peak * steps).round() / steps * peak;
+            }
+        }
+    }
+}
+
+struct Channel {
+    mdct: Mdct,
+    jitter: DelayLine,
+    /// Input delayed by the codec latency, for the dry side of `mix`.
+    dry: DelayLine,
+    packet: Vec<f32>,
+    last_good: Vec<f32>,
+}
+
+impl Channel {
+    fn new(hop: usize, max_jitter: usize, packet_len: usize) -> Self {
+        let mdct = Mdct::new(hop);
+        let latency = mdct.latency();
+        Self {
+            mdct,
+            jitter: DelayLine::new(max_jitter),
+            dry: DelayLine::new(latency),
+            packet: vec![0.0; packet_len],
+            last_good: vec![0.0; packet_len],
+        }
+    }
+
+    fn reset(&mut self) {
+        self.mdct.reset();
+        self.jitter.clear();
+        self.dry.clear();
+        self.packet.fill(0.0);
+        self.last_good.fill(0.0);
+    }
+}
+
+/// Degraded transmission: the signal as it might arrive over a bad voice
+/// call.
+///
+/// In order, the stages are:
+///
+/// - jitter: delay wandering by up to `jitter_ms`, retargeted every
+///   packet, which warps time and pitch slightly;
+/// - `companding`: off, or 8-bit mu-law or A-law as in telephony;
+/// - a lossy MDCT codec squeezed into `bitrate_kbps` (for both channels);
+/// - packet loss: each 20 ms packet is dropped with probability `loss`,
+///   or `burst` right after a drop, and replaced by a repeat of the last
+///   good packet fading away, or by silence if `conceal` is off.
+///
+/// Losses and jitter draw from a generator seeded by `seed`.
+pub struct Transmission {
+    sample_rate: f32,
+    channels: [Channel; 2],
+    quantizer: Quantizer,
+    rng: StdRng,
+    packet_pos: usize,
+    /// Consecutive packets lost, counting the current one.
+    lost_run: u32,
+    /// Crossfade from the decoded signal to concealment.
+    lost_amount: f32,
+    fade_step: f32,
+    delay: f32,
+    target_delay: f32,
+    delay_coeff: f32,
+    companding: f32,
+    bitrate_kbps: f32,
+    loss: f32,
+    burst: f32,
+    conceal: f32,
+    jitter_ms: f32,
+    seed: f32,
+    mix: f32,
+}
+
+impl Transmission {
+    pub const KIND: &'static str = "transmission";
+
+    pub fn new(sample_rate: f32) -> Self {
+        let hop = stft::frame_size(sample_rate) / 4;
+        let packet_len = (PACKET_MS * 0.001 * sample_rate) as usize;
+        let max_jitter = (MAX_JITTER_MS * 0.001 * sample_rate) as usize + 2;
+        let mut effect = Self {
+            sample_rate,
+            channels: [
+                Channel::new(hop, max_jitter, packet_len),
+                Channel::new(hop, max_jitter, packet_len),
+            ],
+            quantizer: Quantizer::new(hop),
+            rng: StdRng::seed_from_u64(1),
+            packet_pos: 0,
+            lost_run: 0,
+            lost_amount: 0.0,
+            fade_step: 1.0 / (FADE_MS * 0.001 * sample_rate),
+            delay: 0.0,
+            target_delay: 0.0,
+            delay_coeff: one_pole_coeff(PACKET_MS, sample_rate),
+            companding: 0.0,
+            bitrate_kbps: 0.0,
+            loss: 0.0,
+            burst: 0.0,
+            conceal: 0.0,
+            jitter_ms: 0.0,
+            seed: 0.0,
+            mix: 0.0,
+        };
+        for info in PARAMS {
+            effect.set_param(info.name, info.default);
+        }
+        effect
+    }
+
+    fn companding_law(&self) -> Option<Companding> {
+        match self.companding as usize {
+            1 => Some(Companding::MuLaw),
+            2 => Some(Companding::ALaw),
+            _ => None,
+        }
+    }
+
+    /// Decides the fate of the packet starting now.
+    fn next_packet(&mut self) {
+        let threshold = if self.lost_run > 0 {
+            self.burst
+        } else {
+            self.loss
+        };
+        let lost = self.rng.gen::<f32>() < threshold;
+        if self.lost_run == 0 {
+            // The packet just finished arrived; it's what gets repeated.
+            for channel in &mut self.channels {
+                std::mem::swap(&mut channel.packet, &mut channel.last_good);
+            }
+        }
+        self.lost_run = if lost { self.lost_run + 1 } else { 0 };
+        let jitter = self.jitter_ms * 0.001 * self.sample_rate;
+        self.target_delay = self.rng.gen::<f32>() * jitter;
+    }
+}
+
+impl Effect for Transmission {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        PARAMS
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        let Some(info) = find_param(PARAMS, name) else {
+            return false;
+        };
+        let value = info.clamp(value);
+        match name {
+            "companding" => self.companding = value.round(),
+            "bitrate_kbps" => {
+                self.bitrate_kbps = value;
+                let hop = self.channels[0].mdct.hop() as f32;
+                let per_channel = value * 1000.0 / 2.0;
+                self.quantizer.budget = (per_channel * hop / self.sample_rate) as usize;
+            }
+            "loss" => self.loss = value,
+            "burst" => self.burst = value,
+            "conceal" => self.conceal = value,
+            "jitter_ms" => self.jitter_ms = value,
+            "seed" => {
+                self.seed = value.round();
+                self.rng = StdRng::seed_from_u64(self.seed as u64);
+            }
+            "mix" => self.mix = value,
+            _ => return false,
+        }
+        true
+    }
+
+    fn get_param(&self, name: &str) -> Option<f32> {
+        match name {
+            "companding" => Some(self.companding),
+            "bitrate_kbps" => Some(self.bitrate_kbps),
+            "loss" => Some(self.loss),
+            "burst" => Some(self.burst),
+            "conceal" => Some(self.conceal),
+            "jitter_ms" => Some(self.jitter_ms),
+            "seed" => Some(self.seed),
+            "mix" => Some(self.mix),
+            _ => None,
+        }
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let law = self.companding_law();
+        let latency = self.latency();
+        let packet_len = self.channels[0].packet.len();
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            if self.packet_pos == 0 {
+                self.next_packet();
+            }
+            self.delay = self.target_delay + (self.delay - self.target_delay) * self.delay_coeff;
+
+            let target = if self.lost_run > 0 { 1.0 } else { 0.0 };
+            self.lost_amount = if self.lost_amount < target {
+                (self.lost_amount + self.fade_step).min(target)
+            } else {
+                (self.lost_amount - self.fade_step).max(target)
+            };
+            // Each repeat of the last good packet is half as loud as the
+            // one before, ramped across the packet to avoid steps.
+            let progress = self.packet_pos as f32 / packet_len as f32;
+            let repeat_gain = match self.lost_run {
+                0 => 1.0,
+                run => 0.5f32.powi(run as i32 - 1) * (1.0 - 0.5 * progress),
+            } * self.conceal;
+
+            for (channel, sample) in self.channels.iter_mut().zip([l, r]) {
+                let input = *sample;
+                channel.dry.push(input);
+                channel.jitter.push(input);
+                let mut wet = channel.jitter.read_frac(self.delay);
+                if let Some(law) = law {
+                    wet = law.process(wet);
+                }
+                let quantizer = &mut self.quantizer;
+                channel
+                    .mdct
+                    .process(std::slice::from_mut(&mut wet), |c| quantizer.quantize(c));
+                channel.packet[self.packet_pos] = wet;
+                let concealed = channel.last_good[self.packet_pos] * repeat_gain;
+                wet += (concealed - wet) * self.lost_amount;
+
+                let dry = channel.dry.read(latency);
+                *sample = dry + (wet - dry) * self.mix;
+            }
+
+            self.packet_pos = (self.packet_pos + 1) % packet_len;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.channels.iter_mut().for_each(Channel::reset);
+        self.rng = StdRng::seed_from_u64(self.seed as u64);
+        self.packet_pos = 0;
+        self.lost_run = 0;
+        self.lost_amount = 0.0;
+        self.delay = 0.0;
+        self.target_delay = 0.0;
+    }
+
+    fn latency(&self) -> usize {
+        self.channels[0].mdct.latency()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(len: usize) -> Vec<f32> {
+        (0..len).map(|i| (i as f32 * 0.05).sin() * 0.5).collect()
+    }
+
+    #[test]
+    fn test_clean_settings_pass_a_tone_and_low_bitrate_degrades_it() {
+        let render = |bitrate: f32| {
+            let mut effect = Transmission::new(48_000.0);
+            effect.set_param("companding", 0.0);
+            effect.set_param("loss", 0.0);
+            effect.set_param("jitter_ms", 0.0);
+            effect.set_param("bitrate_kbps", bitrate);
+            let mut left = tone(9600);
+            let mut right = left.clone();
+            effect.process(&mut left, &mut right);
+            let latency = effect.latency();
+            let reference = tone(9600);
+            left[latency + 2400..]
+                .iter()
+                .zip(&reference[2400..])
+                .map(|(a, b)| (a - b).abs())
+                .fold(0.0f32, f32::max)
+        };
+        assert!(render(320.0) < 0.01, "{}", render(320.0));
+        assert!(render(6.0) > render(320.0) * 2.0);
+    }
+
+    #[test]
+    fn test_lost_packets_drop_out_at_the_loss_rate() {
+        let mut effect = Transmission::new(48_000.0);
+        effect.set_param("companding", 0.0);
+        effect.set_param("bitrate_kbps", 320.0);
+        effect.set_param("jitter_ms", 0.0);
+        effect.set_param("conceal", 0.0);
+        effect.set_param("loss", 0.5);
+        effect.set_param("burst", 0.0);
+        let mut left = tone(48_000);
+        let mut right = left.clone();
+        effect.process(&mut left, &mut right);
+
+        // With concealment off, every lost packet is a silent gap.
+        let silent_packets = left[4800..]
+            .chunks(960)
+            .filter(|packet| packet[100..860].iter().all(|s| s.abs() < 1e-6))
+            .count();
+        assert!((10..35).contains(&silent_packets), "{silent_packets}");
+    }
+}
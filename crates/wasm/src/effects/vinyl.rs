@@ -0,0 +1,327 @@
+use super::{find_param, Effect, ParamInfo};
+use crate::dsp::{flush_denormal, Biquad, DelayLine, FilterKind, Noise, Oscillator, Waveform};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const PARAMS: &[ParamInfo] = &[
+    ParamInfo::new("crackle", 0.0, 1.0, 0.3),
+    ParamInfo::new("density", 0.0, 100.0, 15.0),
+    ParamInfo::new("rumble", 0.0, 1.0, 0.2),
+    ParamInfo::new("wow", 0.0, 1.0, 0.3),
+    ParamInfo::new("eq", 0.0, 1.0, 0.7),
+    ParamInfo::new("wear", 0.0, 1.0, 0.3),
+    ParamInfo::new("seed", 0.0, 65_535.0, 1.0),
+    ParamInfo::new("mix", 0.0, 1.0, 1.0),
+];
+
+/// Once round per revolution at 33⅓ rpm.
+const WOW_HZ: f32 = 0.555;
+const FLUTTER_HZ: f32 = 6.5;
+/// Pitch deviation at full `wow`.
+const WOW_DEPTH: f32 = 0.006;
+const FLUTTER_DEPTH: f32 = 0.0008;
+/// Centre of the wow delay, leaving room to swing both ways. The dry path
+/// is delayed by the same amount so the two line up in the mix.
+const BASE_DELAY_MS: f32 = 5.0;
+/// Share of crackle events that are heavier pops.
+const POP_SHARE: f32 = 0.05;
+const CLICK_GAIN: f32 = 0.3;
+const POP_GAIN: f32 = 2.5;
+const RUMBLE_GAIN: f32 = 0.5;
+
+struct Channel {
+    delay: DelayLine,
+    low_shelf: Biquad,
+    high_shelf: Biquad,
+    /// Turns single-sample impulses into sharp ticks...
+    click: Biquad,
+    /// ...and into duller thumps.
+    pop: Biquad,
+}
+
+impl Channel {
+    fn new(sample_rate: f32) -> Self {
+        let max_delay = 2.0 * BASE_DELAY_MS * 0.001 * sample_rate;
+        Self {
+            delay: DelayLine::new(max_delay as usize),
+            low_shelf: Biquad::default(),
+            high_shelf: Biquad::default(),
+            click: Biquad::new(FilterKind::HighPass, sample_rate, 2000.0, 0.7, 0.0),
+            pop: Biquad::new(FilterKind::BandPass, sample_rate, 180.0, 0.8, 0.0),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.delay.clear();
+        self.low_shelf.reset();
+        self.high_shelf.reset();
+        self.click.reset();
+        self.pop.reset();
+    }
+}
+
+/// Vinyl record playback: crackle and pops, turntable rumble, wow and
+/// flutter, a warm playback EQ and a worn stereo image.
+///
+/// `density` sets the crackle events per second and `crackle` their level;
+/// one in twenty is a heavier pop. `eq` leans into the bass and rolls off
+/// the treble the way an old cartridge and preamp do, and `wear` narrows
+/// the stereo image, dulls the top further and scatters crackle unevenly
+/// between the channels. Crackle and rumble are drawn from a generator
+/// seeded by `seed`.
+pub struct Vinyl {
+    sample_rate: f32,
+    /// [`BASE_DELAY_MS`] in whole samples.
+    base_delay: usize,
+    channels: [Channel; 2],
+    wow_lfo: Oscillator,
+    flutter_lfo: Oscillator,
+    rumble_noise: Noise,
+    rumble_filter: Biquad,
+    rng: StdRng,
+    crackle: f32,
+    density: f32,
+    rumble: f32,
+    wow: f32,
+    eq: f32,
+    wear: f32,
+    seed: f32,
+    mix: f32,
+}
+
+impl Vinyl {
+    pub const KIND: &'static str = "vinyl";
+
+    pub fn new(sample_rate: f32) -> Self {
+        let mut wow_lfo = Oscillator::new(sample_rate, Waveform::Sine);
+        wow_lfo.set_frequency(WOW_HZ);
+        let mut flutter_lfo = Oscillator::new(sample_rate, Waveform::Sine);
+        flutter_lfo.set_frequency(FLUTTER_HZ);
+        let mut effect = Self {
+            sample_rate,
+            base_delay: (BASE_DELAY_MS * 0.001 * sample_rate).round() as usize,
+            channels: [Channel::new(sample_rate), Channel::new(sample_rate)],
+            wow_lfo,
+            flutter_lfo,
+            rumble_noise: Noise::new(1),
+            rumble_filter: Biquad::new(FilterKind::LowPass, sample_rate, 30.0, 0.9, 0.0),
+            rng: StdRng::seed_from_u64(1),
+            crackle: 0.0,
+            density: 0.0,
+            rumble: 0.0,
+            wow: 0.0,
+            eq: 0.0,
+            wear: 0.0,
+            seed: 0.0,
+            mix: 0.0,
+        };
+        for info in PARAMS {
+            effect.set_param(info.name, info.default);
+        }
+        effect
+    }
+
+    fn update_eq(&mut self) {
+        let bass_db = 4.0 * self.eq;
+        let treble_db = -8.0 * self.eq - 6.0 * self.wear;
+        for channel in &mut self.channels {
+            channel
+                .low_shelf
+                .design(FilterKind::LowShelf, self.sample_rate, 120.0, 0.7, bass_db);
+            channel.high_shelf.design(
+                FilterKind::HighShelf,
+                self.sample_rate,
+                5000.0,
+                0.7,
+                treble_db,
+            );
+        }
+    }
+
+    fn reseed(&mut self) {
+        let seed = self.seed as u64;
+        self.rng = StdRng::seed_from_u64(seed);
+        self.rumble_noise.reseed(seed);
+    }
+}
+
+impl Effect for Vinyl {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        PARAMS
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        let Some(info) = find_param(PARAMS, name) else {
+            return false;
+        };
+        let value = info.clamp(value);
+        match name {
+            "crackle" => self.crackle = value,
+            "density" => self.density = value,
+            "rumble" => self.rumble = value,
+            "wow" => self.wow = value,
+            "eq" => {
+                self.eq = value;
+                self.update_eq();
+            }
+            "wear" => {
+                self.wear = value;
+                self.update_eq();
+            }
+            "seed" => {
+                self.seed = value.round();
+                self.reseed();
+            }
+            "mix" => self.mix = value,
+            _ => return false,
+        }
+        true
+    }
+
+    fn get_param(&self, name: &str) -> Option<f32> {
+        match name {
+            "crackle" => Some(self.crackle),
+            "density" => Some(self.density),
+            "rumble" => Some(self.rumble),
+            "wow" => Some(self.wow),
+            "eq" => Some(self.eq),
+            "wear" => Some(self.wear),
+            "seed" => Some(self.seed),
+            "mix" => Some(self.mix),
+            _ => None,
+        }
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let rate = self.sample_rate;
+        let base = self.base_delay as f32;
+        // A sine delay swing of `d` samples at `f` Hz bends pitch by up to
+        // 2π f d / rate.
+        let wow_swing = self.wow * WOW_DEPTH * rate / (std::f32::consts::TAU * WOW_HZ);
+        let flutter_swing = self.wow * FLUTTER_DEPTH * rate / (std::f32::consts::TAU * FLUTTER_HZ);
+        let chance = self.density / rate;
+        let width = 1.0 - 0.7 * self.wear;
+
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let delay = base
+                + self.wow_lfo.next_sample() * wow_swing
+                + self.flutter_lfo.next_sample() * flutter_swing;
+
+            let mut impulses = [[0.0; 2]; 2];
+            if self.rng.gen::<f32>() < chance {
+                let pop = self.rng.gen::<f32>() < POP_SHARE;
+                let level = self.rng.gen::<f32>().powi(2) * self.crackle;
+                let level = if self.rng.gen() { level } else { -level };
+                // Worn grooves crackle more on one side than the other.
+                let pan = self.rng.gen_range(-1.0..1.0) * self.wear;
+                let gains = [(1.0 - pan).min(1.0), (1.0 + pan).min(1.0)];
+                for (impulse, gain) in impulses.iter_mut().zip(gains) {
+                    impulse[usize::from(pop)] = level * gain;
+                }
+            }
+            let rumble = self.rumble_filter.process(self.rumble_noise.next_sample())
+                * self.rumble
+                * RUMBLE_GAIN;
+
+            let mut out = [0.0; 2];
+            let mut dry = [*l, *r];
+            for ((channel, out), (dry, impulse)) in self
+                .channels
+                .iter_mut()
+                .zip(out.iter_mut())
+                .zip(dry.iter_mut().zip(impulses))
+            {
+                channel.delay.push(*dry);
+                *dry = channel.delay.read(self.base_delay);
+                let played = channel.delay.read_frac(delay);
+                let played = channel
+                    .high_shelf
+                    .process(channel.low_shelf.process(played));
+                let click = channel.click.process(impulse[0]) * CLICK_GAIN;
+                let pop = channel.pop.process(impulse[1]) * POP_GAIN;
+                *out = flush_denormal(played + click + pop + rumble);
+            }
+
+            let mid = 0.5 * (out[0] + out[1]);
+            let side = 0.5 * (out[0] - out[1]) * width;
+            *l = dry[0] + (mid + side - dry[0]) * self.mix;
+            *r = dry[1] + (mid - side - dry[1]) * self.mix;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.channels.iter_mut().for_each(Channel::reset);
+        self.wow_lfo.reset();
+        self.flutter_lfo.reset();
+        self.rumble_filter.reset();
+        self.reseed();
+    }
+
+    fn latency(&self) -> usize {
+        self.base_delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(effect: &mut Vinyl, len: usize) -> Vec<f32> {
+        let mut left = vec![0.0; len];
+        let mut right = vec![0.0; len];
+        for (l, r) in left.chunks_mut(128).zip(right.chunks_mut(128)) {
+            effect.process(l, r);
+        }
+        left
+    }
+
+    #[test]
+    fn test_crackle_follows_density_and_seed() {
+        let ticks = |density: f32, seed: f32| {
+            let mut effect = Vinyl::new(48_000.0);
+            effect.set_param("rumble", 0.0);
+            effect.set_param("crackle", 1.0);
+            effect.set_param("density", density);
+            effect.set_param("seed", seed);
+            let out = render(&mut effect, 96_000);
+            let count = out
+                .windows(2)
+                .filter(|w| w[0].abs() < 0.01 && w[1].abs() > 0.02)
+                .count();
+            (count, out)
+        };
+        let (sparse, _) = ticks(5.0, 1.0);
+        let (dense, a) = ticks(80.0, 1.0);
+        assert!(dense > sparse * 4, "{sparse} vs {dense}");
+        assert_eq!(a, ticks(80.0, 1.0).1);
+        assert_ne!(a, ticks(80.0, 2.0).1);
+
+        let mut quiet = Vinyl::new(48_000.0);
+        quiet.set_param("crackle", 0.0);
+        quiet.set_param("rumble", 0.0);
+        assert!(render(&mut quiet, 4800).iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_dry_path_lines_up_with_the_wet_path() {
+        let mut effect = Vinyl::new(48_000.0);
+        for name in ["crackle", "rumble", "wow", "eq", "wear"] {
+            effect.set_param(name, 0.0);
+        }
+        effect.set_param("mix", 0.5);
+        assert_eq!(effect.latency(), 240);
+
+        let mut left = vec![0.0; 1024];
+        left[0] = 1.0;
+        let mut right = left.clone();
+        effect.process(&mut left, &mut right);
+        // One impulse at the latency rather than a dry one plus a late echo.
+        let peak = left.iter().position(|s| s.abs() > 0.5).unwrap();
+        assert_eq!(peak, effect.latency());
+        assert!(left[..peak].iter().all(|s| s.abs() < 1e-3));
+    }
+}
@@ -0,0 +1,245 @@
+use super::{find_param, Effect, ParamInfo};
+use crate::dsp::{semitones_to_ratio, DelayLine, FormantShifter, PitchShifter};
+
+const PARAMS: &[ParamInfo] = &[
+    ParamInfo::new("pitch_semitones", -12.0, 12.0, 0.0),
+    ParamInfo::new("formant_semitones", -12.0, 12.0, 0.0),
+    ParamInfo::new("gender", -1.0, 1.0, 0.0),
+    ParamInfo::new("age", -1.0, 1.0, 0.0),
+    ParamInfo::new("mix", 0.0, 1.0, 1.0),
+];
+
+/// Pitch and formant offsets (in semitones) at full `gender` towards a
+/// higher, more feminine voice. Negative gender mirrors them.
+const GENDER_PITCH: f32 = 5.0;
+const GENDER_FORMANT: f32 = 2.5;
+/// Offsets at `age` -1 (a child's voice) and +1 (an elderly voice).
+const CHILD_PITCH: f32 = 6.0;
+const CHILD_FORMANT: f32 = 3.5;
+const ELDER_PITCH: f32 = -1.5;
+const ELDER_FORMANT: f32 = -1.0;
+/// Below this the pitch stage is bypassed; a unity-ratio delay-line shifter
+/// would only comb filter.
+const PITCH_EPSILON: f32 = 0.01;
+/// How long the pitch stage takes to fade in or out of the bypass.
+const BYPASS_FADE_MS: f32 = 10.0;
+
+/// Voice character transform: independent pitch and formant shifting, with
+/// `gender` and `age` macros for quick anonymisation.
+///
+/// The pitch stage is a delay-line shifter, which drags formants along with
+/// the pitch; the LPC formant stage then moves the envelope by whatever is
+/// left to reach the requested formant shift.
+///
+/// With no pitch shift the shifter is crossfaded out for the input delayed
+/// by the shifter's latency, so the latency stays the same whatever the
+/// settings and sweeping a macro through zero doesn't click. The shifter is
+/// fed all the while, so it has current audio to fade back in with.
+pub struct VoiceCharacter {
+    pitch: [PitchShifter; 2],
+    formant: [FormantShifter; 2],
+    dry: [DelayLine; 2],
+    /// How far the shifter is faded in, from 0 (bypassed) to 1.
+    engaged: f32,
+    fade_step: f32,
+    pitch_semitones: f32,
+    formant_semitones: f32,
+    gender: f32,
+    age: f32,
+    mix: f32,
+    total_pitch: f32,
+}
+
+impl VoiceCharacter {
+    pub const KIND: &'static str = "voice_character";
+
+    pub fn new(sample_rate: f32) -> Self {
+        let make_shifter = || {
+            let mut shifter = PitchShifter::new((sample_rate * 0.1) as usize);
+            shifter.set_window(sample_rate * 0.03);
+            shifter
+        };
+
+        let latency = make_shifter().latency();
+        let mut effect = Self {
+            pitch: [make_shifter(), make_shifter()],
+            formant: [
+                FormantShifter::new(sample_rate),
+                FormantShifter::new(sample_rate),
+            ],
+            dry: [DelayLine::new(latency + 1), DelayLine::new(latency + 1)],
+            engaged: 0.0,
+            fade_step: 1.0 / (BYPASS_FADE_MS * 0.001 * sample_rate),
+            pitch_semitones: 0.0,
+            formant_semitones: 0.0,
+            gender: 0.0,
+            age: 0.0,
+            mix: 0.0,
+            total_pitch: 0.0,
+        };
+        for info in PARAMS {
+            effect.set_param(info.name, info.default);
+        }
+        effect
+    }
+
+    /// Combined pitch and formant offsets in semitones after the macros.
+    fn offsets(&self) -> (f32, f32) {
+        let (age_pitch, age_formant) = if self.age < 0.0 {
+            (-self.age * CHILD_PITCH, -self.age * CHILD_FORMANT)
+        } else {
+            (self.age * ELDER_PITCH, self.age * ELDER_FORMANT)
+        };
+        let pitch = self.pitch_semitones + self.gender * GENDER_PITCH + age_pitch;
+        let formant = self.formant_semitones + self.gender * GENDER_FORMANT + age_formant;
+        (pitch, formant)
+    }
+
+    fn update(&mut self) {
+        let (pitch, formant) = self.offsets();
+        self.total_pitch = pitch;
+        let ratio = semitones_to_ratio(pitch);
+        // The pitch stage has already moved the envelope by `pitch`.
+        let factor = semitones_to_ratio(formant - pitch);
+        self.pitch.iter_mut().for_each(|s| s.set_ratio(ratio));
+        self.formant.iter_mut().for_each(|s| s.set_factor(factor));
+    }
+}
+
+impl Effect for VoiceCharacter {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn params(&self) -> &'static [ParamInfo] {
+        PARAMS
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) -> bool {
+        let Some(info) = find_param(PARAMS, name) else {
+            return false;
+        };
+        let value = info.clamp(value);
+        match name {
+            "pitch_semitones" => self.pitch_semitones = value,
+            "formant_semitones" => self.formant_semitones = value,
+            "gender" => self.gender = value,
+            "age" => self.age = value,
+            "mix" => self.mix = value,
+            _ => return false,
+        }
+        self.update();
+        true
+    }
+
+    fn get_param(&self, name: &str) -> Option<f32> {
+        match name {
+            "pitch_semitones" => Some(self.pitch_semitones),
+            "formant_semitones" => Some(self.formant_semitones),
+            "gender" => Some(self.gender),
+            "age" => Some(self.age),
+            "mix" => Some(self.mix),
+            _ => None,
+        }
+    }
+
+    fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let latency = self.pitch[0].latency();
+        let target = if self.total_pitch.abs() >= PITCH_EPSILON {
+            1.0
+        } else {
+            0.0
+        };
+        let start = self.engaged;
+        let channels = [left, right];
+        for (((samples, pitch), formant), dry) in channels
+            .into_iter()
+            .zip(self.pitch.iter_mut())
+            .zip(self.formant.iter_mut())
+            .zip(self.dry.iter_mut())
+        {
+            let mut engaged = start;
+            for sample in samples.iter_mut() {
+                engaged += (target - engaged).clamp(-self.fade_step, self.fade_step);
+                dry.push(*sample);
+                let delayed = dry.read(latency);
+                let shifted = pitch.process(*sample);
+                let wet = formant.process(delayed + (shifted - delayed) * engaged);
+                *sample = delayed + (wet - delayed) * self.mix;
+            }
+            self.engaged = engaged;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.pitch.iter_mut().for_each(|s| s.reset());
+        self.formant.iter_mut().for_each(|s| s.reset());
+        self.dry.iter_mut().for_each(|d| d.clear());
+        // Start where the settings are rather than fading in from bypass.
+        self.engaged = if self.total_pitch.abs() >= PITCH_EPSILON {
+            1.0
+        } else {
+            0.0
+        };
+    }
+
+    fn latency(&self) -> usize {
+        self.pitch[0].latency()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_macros_combine_with_manual_offsets() {
+        let mut effect = VoiceCharacter::new(48_000.0);
+        effect.set_param("gender", 1.0);
+        effect.set_param("pitch_semitones", -1.0);
+        assert_eq!(effect.offsets(), (4.0, 2.5));
+
+        effect.set_param("gender", 0.0);
+        effect.set_param("pitch_semitones", 0.0);
+        effect.set_param("age", -1.0);
+        assert_eq!(effect.offsets(), (CHILD_PITCH, CHILD_FORMANT));
+    }
+
+    #[test]
+    fn test_sweeping_through_neutral_keeps_latency_and_does_not_click() {
+        let mut effect = VoiceCharacter::new(48_000.0);
+        let latency = effect.latency();
+        assert_eq!(latency, 720);
+        effect.set_param("gender", 1.0);
+        assert_eq!(effect.latency(), latency);
+
+        // Dry alone comes out delayed by the reported latency.
+        effect.set_param("gender", 0.0);
+        effect.set_param("mix", 0.0);
+        effect.reset();
+        let mut left = vec![0.0; 1024];
+        left[0] = 1.0;
+        let mut right = left.clone();
+        effect.process(&mut left, &mut right);
+        assert_eq!(left.iter().position(|s| *s != 0.0), Some(latency));
+
+        effect.set_param("mix", 1.0);
+        effect.reset();
+        let tone: Vec<f32> = (0..48_000)
+            .map(|i| (std::f32::consts::TAU * 200.0 * i as f32 / 48_000.0).sin() * 0.5)
+            .collect();
+        let mut out = Vec::new();
+        for (i, block) in tone.chunks(128).enumerate() {
+            // Gender wanders either side of zero, in and out of the bypass.
+            effect.set_param("gender", (i as f32 * 0.3).sin() * 0.01);
+            let (mut l, mut r) = (block.to_vec(), block.to_vec());
+            effect.process(&mut l, &mut r);
+            out.extend(l);
+        }
+        let jump = out[4800..]
+            .windows(2)
+            .map(|w| (w[1] - w[0]).abs())
+            .fold(0.0, f32::max);
+        assert!(jump < 0.05, "jump {jump}");
+    }
+}
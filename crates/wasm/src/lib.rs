@@ -1,5 +1,16 @@
+pub mod age;
 mod audio_processor;
+pub mod dsp;
+pub mod effects;
+pub mod midi;
+mod mixer;
+pub mod network;
+pub mod preset;
+pub mod samples;
+
 pub use audio_processor::AudioProcessor;
+pub use mixer::Mixer;
+pub use preset::Preset;
 
 use wasm_bindgen::prelude::*;
 
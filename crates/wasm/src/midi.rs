@@ -0,0 +1,501 @@
+//! MIDI input: a byte-stream parser and the learnable mapping from
+//! controllers and notes to effect parameters.
+//!
+//! Web MIDI hands the page raw message bytes; they are forwarded to the
+//! processor unchanged and parsed here, so the mapping logic is the same
+//! wherever the chain runs. A [`MidiMapping`] ties one CC or note on one
+//! channel to a parameter of an effect in the chain, with an optional
+//! sub-range, a response curve and soft takeover. Mappings are saved in the
+//! `midi` list of a preset:
+//!
+//! ```json
+//! { "source": { "cc": { "channel": 0, "controller": 74 } },
+//!   "effect": 1, "param": "mix", "min": 0.2, "max": 0.8,
+//!   "curve": "exponential", "takeover": true }
+//! ```
+
+use crate::effects::{self, Effect, EffectChain};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidiMessage {
+    NoteOff {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    NoteOn {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    PolyPressure {
+        channel: u8,
+        note: u8,
+        pressure: u8,
+    },
+    ControlChange {
+        channel: u8,
+        controller: u8,
+        value: u8,
+    },
+    ProgramChange {
+        channel: u8,
+        program: u8,
+    },
+    ChannelPressure {
+        channel: u8,
+        pressure: u8,
+    },
+    /// 14-bit bend, 8192 at rest.
+    PitchBend {
+        channel: u8,
+        value: u16,
+    },
+}
+
+/// Number of data bytes that follow a channel status byte.
+fn data_len(status: u8) -> usize {
+    match status & 0xF0 {
+        0xC0 | 0xD0 => 1,
+        _ => 2,
+    }
+}
+
+/// Turns a byte stream into channel messages.
+///
+/// Handles running status and ignores system messages, including
+/// real-time bytes interleaved with a message and SysEx payloads.
+#[derive(Debug, Clone, Default)]
+pub struct MidiParser {
+    status: Option<u8>,
+    data: [u8; 2],
+    len: usize,
+    in_sysex: bool,
+}
+
+impl MidiParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds one byte; returns a message when it completes one.
+    pub fn push(&mut self, byte: u8) -> Option<MidiMessage> {
+        match byte {
+            // Real-time messages may appear anywhere and change nothing.
+            0xF8..=0xFF => None,
+            0xF0 => {
+                self.in_sysex = true;
+                self.status = None;
+                None
+            }
+            // End of SysEx and the system common messages cancel running
+            // status; their data bytes are skipped below.
+            0xF1..=0xF7 => {
+                self.in_sysex = false;
+                self.status = None;
+                None
+            }
+            0x80..=0xEF => {
+                self.in_sysex = false;
+                self.status = Some(byte);
+                self.len = 0;
+                None
+            }
+            _ => {
+                let status = self.status.filter(|_| !self.in_sysex)?;
+                self.data[self.len] = byte;
+                self.len += 1;
+                if self.len < data_len(status) {
+                    return None;
+                }
+                self.len = 0;
+                Some(Self::message(status, self.data))
+            }
+        }
+    }
+
+    fn message(status: u8, [a, b]: [u8; 2]) -> MidiMessage {
+        let channel = status & 0x0F;
+        match status & 0xF0 {
+            // Note-on with velocity 0 is a note-off by convention.
+            0x90 if b == 0 => MidiMessage::NoteOff {
+                channel,
+                note: a,
+                velocity: 64,
+            },
+            0x90 => MidiMessage::NoteOn {
+                channel,
+                note: a,
+                velocity: b,
+            },
+            0x80 => MidiMessage::NoteOff {
+                channel,
+                note: a,
+                velocity: b,
+            },
+            0xA0 => MidiMessage::PolyPressure {
+                channel,
+                note: a,
+                pressure: b,
+            },
+            0xB0 => MidiMessage::ControlChange {
+                channel,
+                controller: a,
+                value: b,
+            },
+            0xC0 => MidiMessage::ProgramChange {
+                channel,
+                program: a,
+            },
+            0xD0 => MidiMessage::ChannelPressure {
+                channel,
+                pressure: a,
+            },
+            _ => MidiMessage::PitchBend {
+                channel,
+                value: u16::from(a) | u16::from(b) << 7,
+            },
+        }
+    }
+}
+
+/// What a mapping listens to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MidiSource {
+    Cc {
+        channel: u8,
+        controller: u8,
+    },
+    /// Velocity while held, the bottom of the range once released.
+    Note {
+        channel: u8,
+        note: u8,
+    },
+}
+
+impl MidiSource {
+    /// The source `message` comes from and its value scaled to `0..=1`.
+    fn read(message: MidiMessage) -> Option<(Self, f32)> {
+        match message {
+            MidiMessage::ControlChange {
+                channel,
+                controller,
+                value,
+            } => Some((
+                MidiSource::Cc {
+                    channel,
+                    controller,
+                },
+                f32::from(value) / 127.0,
+            )),
+            MidiMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => Some((
+                MidiSource::Note { channel, note },
+                f32::from(velocity) / 127.0,
+            )),
+            MidiMessage::NoteOff { channel, note, .. } => {
+                Some((MidiSource::Note { channel, note }, 0.0))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// How the controller's travel is spread over the range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Curve {
+    #[default]
+    Linear,
+    /// Fine control at the bottom of the range, e.g. for frequencies.
+    Exponential,
+    /// Fine control at the top of the range.
+    Logarithmic,
+}
+
+impl Curve {
+    pub fn apply(self, x: f32) -> f32 {
+        match self {
+            Curve::Linear => x,
+            Curve::Exponential => x * x * x,
+            Curve::Logarithmic => 1.0 - (1.0 - x).powi(3),
+        }
+    }
+}
+
+/// Soft takeover catches the parameter once the control comes within this
+/// fraction of the range of its current value.
+const PICKUP_WINDOW: f32 = 0.02;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MidiMapping {
+    pub source: MidiSource,
+    /// Index of the effect in the chain.
+    pub effect: usize,
+    pub param: String,
+    /// Value at the bottom of the controller's travel; the parameter's own
+    /// minimum if left out. `min` above `max` inverts the control.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f32>,
+    #[serde(default)]
+    pub curve: Curve,
+    /// Ignore the control until it reaches the parameter's current value,
+    /// so a knob left elsewhere doesn't make the sound jump.
+    #[serde(default)]
+    pub takeover: bool,
+    /// Value this mapping last set. A parameter that no longer holds it was
+    /// moved by something else and has to be picked up again.
+    #[serde(skip)]
+    sent: Option<f32>,
+    /// Where the control was at the last message, in parameter units.
+    #[serde(skip)]
+    previous: Option<f32>,
+}
+
+impl MidiMapping {
+    pub fn new(source: MidiSource, effect: usize, param: &str) -> Self {
+        Self {
+            source,
+            effect,
+            param: param.to_string(),
+            min: None,
+            max: None,
+            curve: Curve::Linear,
+            takeover: false,
+            sent: None,
+            previous: None,
+        }
+    }
+
+    /// The mapped range, or `None` if the target doesn't exist.
+    fn range(&self, effect: &dyn Effect) -> Option<(f32, f32)> {
+        let info = effect.params().iter().find(|p| p.name == self.param)?;
+        Some((self.min.unwrap_or(info.min), self.max.unwrap_or(info.max)))
+    }
+
+    pub fn is_valid_for(&self, chain: &EffectChain) -> bool {
+        chain.get(self.effect).and_then(|e| self.range(e)).is_some()
+    }
+
+    /// Applies a controller position in `0..=1`; returns whether the
+    /// parameter changed.
+    fn apply(&mut self, position: f32, chain: &mut EffectChain) -> bool {
+        let Some(effect) = chain.get_mut(self.effect) else {
+            return false;
+        };
+        let Some((min, max)) = self.range(effect) else {
+            return false;
+        };
+        let target = min + (max - min) * self.curve.apply(position);
+        let current = effect.get_param(&self.param).unwrap_or(target);
+
+        if self.takeover && self.sent != Some(current) {
+            // Pick up near the current value, or when the control passed
+            // through it between two messages as a fast move does.
+            let near = (target - current).abs() <= (max - min).abs() * PICKUP_WINDOW;
+            let crossed = self
+                .previous
+                .is_some_and(|p| (p - current).signum() != (target - current).signum());
+            self.previous = Some(target);
+            if !near && !crossed {
+                return false;
+            }
+        }
+        effect.set_param(&self.param, target);
+        self.sent = effect.get_param(&self.param);
+        self.previous = Some(target);
+        true
+    }
+}
+
+/// The mappings of one processor plus MIDI-learn state.
+#[derive(Debug, Clone, Default)]
+pub struct MidiMap {
+    mappings: Vec<MidiMapping>,
+    parser: MidiParser,
+    learning: Option<(usize, String)>,
+}
+
+impl MidiMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mappings(&self) -> &[MidiMapping] {
+        &self.mappings
+    }
+
+    pub fn set_mappings(&mut self, mappings: Vec<MidiMapping>) {
+        self.mappings = mappings;
+    }
+
+    /// Maps the next CC or note that arrives to `param` of `effect`.
+    pub fn learn(&mut self, effect: usize, param: &str) {
+        self.learning = Some((effect, param.to_string()));
+    }
+
+    pub fn cancel_learn(&mut self) {
+        self.learning = None;
+    }
+
+    pub fn is_learning(&self) -> bool {
+        self.learning.is_some()
+    }
+
+    /// Removes every mapping for `param` of `effect`.
+    pub fn unmap(&mut self, effect: usize, param: &str) -> bool {
+        let before = self.mappings.len();
+        self.mappings
+            .retain(|m| !(m.effect == effect && m.param == param));
+        self.mappings.len() != before
+    }
+
+    /// Parses `bytes` and applies every complete message to `chain`.
+    /// Returns whether any parameter changed or a mapping was learned.
+    pub fn handle(&mut self, bytes: &[u8], chain: &mut EffectChain) -> bool {
+        let mut changed = false;
+        for &byte in bytes {
+            if let Some(message) = self.parser.push(byte) {
+                changed |= self.apply(message, chain);
+            }
+        }
+        changed
+    }
+
+    fn apply(&mut self, message: MidiMessage, chain: &mut EffectChain) -> bool {
+        let Some((source, position)) = MidiSource::read(message) else {
+            return false;
+        };
+        if let Some((effect, param)) = self.learning.take() {
+            // Learn on a press rather than a release so a note-off left over
+            // from before the click doesn't grab the mapping.
+            if matches!(message, MidiMessage::NoteOff { .. }) {
+                self.learning = Some((effect, param));
+                return false;
+            }
+            let mapping = MidiMapping::new(source, effect, &param);
+            if !mapping.is_valid_for(chain) {
+                return false;
+            }
+            // One control drives one parameter.
+            self.mappings.retain(|m| m.source != source);
+            self.mappings.push(mapping);
+            return true;
+        }
+
+        let mut changed = false;
+        for mapping in self.mappings.iter_mut().filter(|m| m.source == source) {
+            changed |= mapping.apply(position, chain);
+        }
+        changed
+    }
+
+    /// Keeps mappings pointing at the same effects after `index` is removed
+    /// from the chain; mappings to the removed effect are dropped.
+    pub fn effect_removed(&mut self, index: usize) {
+        self.mappings.retain_mut(|m| {
+            effects::index_after_removal(m.effect, index)
+                .map(|i| m.effect = i)
+                .is_some()
+        });
+    }
+
+    /// Follows an [`EffectChain::move_effect`] from `from` to `to`.
+    pub fn effect_moved(&mut self, from: usize, to: usize) {
+        for mapping in &mut self.mappings {
+            mapping.effect = effects::index_after_move(mapping.effect, from, to);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::Reverb;
+
+    #[test]
+    fn test_parser_handles_running_status_and_realtime() {
+        let mut parser = MidiParser::new();
+        let bytes = [
+            0xB1, 7, 0xF8, 100, // CC with a clock byte in the middle
+            8, 20, // running status
+            0xF0, 0x7E, 0x01, 0xF7, // SysEx
+            0x90, 60, 0, // note-on at zero velocity
+            0xE0, 0x00, 0x40, // centred pitch bend
+        ];
+        let messages: Vec<_> = bytes.iter().filter_map(|&b| parser.push(b)).collect();
+        assert_eq!(
+            messages,
+            [
+                MidiMessage::ControlChange {
+                    channel: 1,
+                    controller: 7,
+                    value: 100
+                },
+                MidiMessage::ControlChange {
+                    channel: 1,
+                    controller: 8,
+                    value: 20
+                },
+                MidiMessage::NoteOff {
+                    channel: 0,
+                    note: 60,
+                    velocity: 64
+                },
+                MidiMessage::PitchBend {
+                    channel: 0,
+                    value: 8192
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_learn_range_and_takeover() {
+        let mut chain = EffectChain::new();
+        chain.push(Box::new(Reverb::new(48_000.0)));
+        chain.get_mut(0).unwrap().set_param("mix", 0.5);
+        let mut map = MidiMap::new();
+
+        map.learn(0, "mix");
+        assert!(map.handle(&[0xB0, 74, 0], &mut chain));
+        assert!(!map.is_learning());
+        let mapping = &mut map.mappings[0];
+        mapping.min = Some(0.2);
+        mapping.max = Some(0.8);
+        mapping.takeover = true;
+        let mix = |chain: &EffectChain| chain.get(0).unwrap().get_param("mix").unwrap();
+
+        // The knob sits far below 0.5, so it doesn't grab the parameter...
+        assert!(!map.handle(&[0xB0, 74, 10], &mut chain));
+        assert_eq!(mix(&chain), 0.5);
+        // ...until it sweeps past it.
+        assert!(map.handle(&[0xB0, 74, 100], &mut chain));
+        assert!((mix(&chain) - (0.2 + 0.6 * 100.0 / 127.0)).abs() < 1e-6);
+        assert!(map.handle(&[0xB0, 74, 127], &mut chain));
+        assert_eq!(mix(&chain), 0.8);
+
+        // Something else moving the parameter drops the pickup again.
+        chain.get_mut(0).unwrap().set_param("mix", 0.3);
+        assert!(!map.handle(&[0xB0, 74, 120], &mut chain));
+        assert_eq!(mix(&chain), 0.3);
+
+        let json = serde_json::to_string(map.mappings()).unwrap();
+        let reloaded: Vec<MidiMapping> = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            reloaded[0].source,
+            MidiSource::Cc {
+                channel: 0,
+                controller: 74
+            }
+        );
+        assert_eq!(reloaded[0].max, Some(0.8));
+    }
+}
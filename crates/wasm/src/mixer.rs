@@ -0,0 +1,770 @@
+// src/mixer.rs
+use crate::audio_processor::{BUFFER_SIZE, DEFAULT_SAMPLE_RATE, SAMPLE_RATES};
+use crate::dsp::{self, simd, BinauralPanner, EarlyReflections, Fdn, HrirSet, RoomGeometry};
+use crate::effects::{self, EffectChain};
+use std::sync::Arc;
+use wasm_bindgen::prelude::*;
+
+/// Number of effect send buses every mixer has.
+pub const MAX_SENDS: usize = 4;
+
+/// One peer's strip: a mono input buffer plus its fader settings.
+struct Channel {
+    id: String,
+    input: Vec<f32>,
+    gain: f32,
+    pan: f32,
+    mute: bool,
+    solo: bool,
+    sends: [f32; MAX_SENDS],
+    /// Binaural placement; replaces the stereo pan while set.
+    spatial: Option<Box<BinauralPanner>>,
+    /// Ear signals rendered by `spatial` for the current block.
+    rendered: [Vec<f32>; 2],
+    /// Wall reflections, while placed binaurally in a room.
+    early: Option<Box<EarlyReflections>>,
+    /// Reflections rendered by `early` for the current block.
+    reflected: [Vec<f32>; 2],
+    /// Left/right gains used at the end of the previous block, so fader and
+    /// pan moves ramp over one block instead of clicking.
+    current: [f32; 2],
+    /// Post-fader peak of the last block, for the level meter.
+    peak: f32,
+}
+
+impl Channel {
+    fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            input: vec![0.0; BUFFER_SIZE],
+            gain: 1.0,
+            pan: 0.0,
+            mute: false,
+            solo: false,
+            sends: [0.0; MAX_SENDS],
+            spatial: None,
+            rendered: [vec![0.0; BUFFER_SIZE], vec![0.0; BUFFER_SIZE]],
+            early: None,
+            reflected: [vec![0.0; BUFFER_SIZE], vec![0.0; BUFFER_SIZE]],
+            current: [0.0; 2],
+            peak: 0.0,
+        }
+    }
+
+    /// Equal-power pan law applied on top of the fader gain. Binaural
+    /// channels are already placed, so they only get the fader.
+    fn target(&self, audible: bool) -> [f32; 2] {
+        if !audible {
+            return [0.0; 2];
+        }
+        if self.spatial.is_some() {
+            return [self.gain; 2];
+        }
+        let angle = (self.pan + 1.0) * std::f32::consts::FRAC_PI_4;
+        [self.gain * angle.cos(), self.gain * angle.sin()]
+    }
+
+    /// Traces reflections for the peer's current placement in `room`, or
+    /// drops them when there is no room or the peer isn't placed.
+    fn update_reflections(&mut self, room: Option<&Room>, sample_rate: f32) {
+        let (Some(room), Some(panner)) = (room, self.spatial.as_ref()) else {
+            self.early = None;
+            return;
+        };
+        let early = self.early.get_or_insert_with(|| {
+            let mut early = Box::new(EarlyReflections::new(sample_rate, room.geometry));
+            early.set_listener(room.listener);
+            early.set_source(panner.azimuth(), panner.elevation(), panner.distance());
+            early.reset();
+            early
+        });
+        early.set_room(room.geometry);
+        early.set_listener(room.listener);
+        early.set_source(panner.azimuth(), panner.elevation(), panner.distance());
+    }
+}
+
+/// Virtual room the binaural peers share.
+struct Room {
+    geometry: RoomGeometry,
+    listener: [f32; 3],
+}
+
+/// Effect return: peers send into it post-fader, its chain runs once on the
+/// sum and the result is added to the master bus.
+struct SendBus {
+    chain: EffectChain,
+    level: f32,
+    left: Vec<f32>,
+    right: Vec<f32>,
+}
+
+impl SendBus {
+    fn new() -> Self {
+        Self {
+            chain: EffectChain::new(),
+            level: 1.0,
+            left: vec![0.0; BUFFER_SIZE],
+            right: vec![0.0; BUFFER_SIZE],
+        }
+    }
+}
+
+/// Sums any number of peer streams into one stereo bus.
+///
+/// Each peer gets its own input buffer that JS fills every render quantum;
+/// the mixer applies gain, pan, mute/solo and the effect sends, so a whole
+/// group call can run in a single AudioContext and worklet.
+///
+/// The web client doesn't use it yet: calls there are one-to-one, with an
+/// [`AudioProcessor`](crate::AudioProcessor) per call. Wiring the mixer into
+/// a group-call client is follow-up work.
+#[wasm_bindgen]
+pub struct Mixer {
+    channels: Vec<Channel>,
+    sends: Vec<SendBus>,
+    output_buffer: Vec<f32>,
+    output_buffer_right: Vec<f32>,
+    /// Shared room that binaural peers feed according to their distance,
+    /// or with their reflections once a room geometry is set.
+    room: Fdn,
+    geometry: Option<Room>,
+    room_input: Vec<f32>,
+    room_level: f32,
+    /// HRIRs shared by every binaurally placed peer.
+    hrirs: Arc<HrirSet>,
+    master_gain: f32,
+    master_peak: f32,
+    sample_rate: f32,
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl Mixer {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            channels: Vec::new(),
+            sends: (0..MAX_SENDS).map(|_| SendBus::new()).collect(),
+            output_buffer: vec![0.0; BUFFER_SIZE],
+            output_buffer_right: vec![0.0; BUFFER_SIZE],
+            room: Self::build_room(DEFAULT_SAMPLE_RATE),
+            geometry: None,
+            room_input: vec![0.0; BUFFER_SIZE],
+            room_level: 0.5,
+            hrirs: Arc::new(HrirSet::new(DEFAULT_SAMPLE_RATE)),
+            master_gain: 1.0,
+            master_peak: 0.0,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+        }
+    }
+
+    /// Adds a channel for `id`; returns `false` if the peer already has one.
+    #[wasm_bindgen]
+    pub fn add_peer(&mut self, id: &str) -> bool {
+        if self.channel(id).is_some() {
+            return false;
+        }
+        self.channels.push(Channel::new(id));
+        true
+    }
+
+    #[wasm_bindgen]
+    pub fn remove_peer(&mut self, id: &str) -> bool {
+        let before = self.channels.len();
+        self.channels.retain(|c| c.id != id);
+        self.channels.len() != before
+    }
+
+    #[wasm_bindgen]
+    pub fn peer_count(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Pointer to the peer's mono input buffer, or null for unknown peers.
+    #[wasm_bindgen]
+    pub fn get_peer_input_ptr(&mut self, id: &str) -> *mut f32 {
+        self.channel_mut(id)
+            .map_or(std::ptr::null_mut(), |c| c.input.as_mut_ptr())
+    }
+
+    #[wasm_bindgen]
+    pub fn get_output_buffer_ptr(&mut self) -> *mut f32 {
+        self.output_buffer.as_mut_ptr()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_output_right_buffer_ptr(&mut self) -> *mut f32 {
+        self.output_buffer_right.as_mut_ptr()
+    }
+
+    /// Linear fader gain, 0 to 4 (+12 dB).
+    #[wasm_bindgen]
+    pub fn set_peer_gain(&mut self, id: &str, gain: f32) -> bool {
+        self.channel_mut(id)
+            .map(|c| c.gain = gain.clamp(0.0, 4.0))
+            .is_some()
+    }
+
+    /// Stereo position from -1 (left) to 1 (right).
+    #[wasm_bindgen]
+    pub fn set_peer_pan(&mut self, id: &str, pan: f32) -> bool {
+        self.channel_mut(id)
+            .map(|c| c.pan = pan.clamp(-1.0, 1.0))
+            .is_some()
+    }
+
+    /// Places the peer binaurally: azimuth in degrees clockwise from the
+    /// front, elevation in degrees, distance in metres. The first call
+    /// switches the peer from stereo panning to the HRTF panner.
+    #[wasm_bindgen]
+    pub fn set_peer_position(
+        &mut self,
+        id: &str,
+        azimuth: f32,
+        elevation: f32,
+        distance: f32,
+    ) -> bool {
+        let sample_rate = self.sample_rate;
+        let hrirs = &self.hrirs;
+        // Borrowed field by field so the room stays readable below.
+        let Some(channel) = self.channels.iter_mut().find(|c| c.id == id) else {
+            return false;
+        };
+        let panner = channel.spatial.get_or_insert_with(|| {
+            let mut panner = Box::new(BinauralPanner::new(Arc::clone(hrirs)));
+            panner.set_position(azimuth, elevation, distance);
+            panner.reset();
+            panner
+        });
+        panner.set_position(azimuth, elevation, distance);
+        channel.update_reflections(self.geometry.as_ref(), sample_rate);
+        true
+    }
+
+    /// Switches the peer back to plain stereo panning.
+    #[wasm_bindgen]
+    pub fn clear_peer_position(&mut self, id: &str) -> bool {
+        self.channel_mut(id)
+            .map(|c| {
+                c.spatial = None;
+                c.early = None;
+            })
+            .is_some()
+    }
+
+    /// Puts the binaural peers in a shoebox room, sizes in metres and
+    /// `absorption` the share of sound each surface soaks up, 0 to 1.
+    /// Every placed peer gets wall reflections traced from its position,
+    /// and the shared room tail takes its decay from the geometry. The
+    /// listener starts in the middle of the floor at ear height.
+    #[wasm_bindgen]
+    pub fn set_room(&mut self, width: f32, depth: f32, height: f32, absorption: f32) {
+        let geometry = RoomGeometry::new(width, depth, height, absorption);
+        let listener = self
+            .geometry
+            .as_ref()
+            .map_or(geometry.centre(), |room| geometry.clamp(room.listener));
+        geometry.configure_tail(&mut self.room);
+        self.geometry = Some(Room { geometry, listener });
+        self.update_reflections();
+    }
+
+    /// Moves the listener, in metres from the back left corner of the
+    /// room at floor level, facing the front wall. Returns `false` while
+    /// no room is set.
+    #[wasm_bindgen]
+    pub fn set_listener_position(&mut self, x: f32, y: f32, z: f32) -> bool {
+        let Some(room) = self.geometry.as_mut() else {
+            return false;
+        };
+        room.listener = room.geometry.clamp([x, y, z]);
+        self.update_reflections();
+        true
+    }
+
+    /// Leaves the room: no more reflections, and the tail goes back to
+    /// the default distance-driven room.
+    #[wasm_bindgen]
+    pub fn clear_room(&mut self) {
+        self.geometry = None;
+        self.room = Self::build_room(self.sample_rate);
+        self.update_reflections();
+    }
+
+    /// Return level of the shared room reverb.
+    #[wasm_bindgen]
+    pub fn set_room_level(&mut self, level: f32) {
+        self.room_level = level.clamp(0.0, 1.0);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_peer_mute(&mut self, id: &str, mute: bool) -> bool {
+        self.channel_mut(id).map(|c| c.mute = mute).is_some()
+    }
+
+    /// While any peer is soloed, only soloed peers are heard.
+    #[wasm_bindgen]
+    pub fn set_peer_solo(&mut self, id: &str, solo: bool) -> bool {
+        self.channel_mut(id).map(|c| c.solo = solo).is_some()
+    }
+
+    /// Post-fader send level from the peer into send bus `send`.
+    #[wasm_bindgen]
+    pub fn set_peer_send(&mut self, id: &str, send: usize, level: f32) -> bool {
+        if send >= MAX_SENDS {
+            return false;
+        }
+        self.channel_mut(id)
+            .map(|c| c.sends[send] = level.clamp(0.0, 1.0))
+            .is_some()
+    }
+
+    /// Level at which send bus `send` returns into the master bus.
+    #[wasm_bindgen]
+    pub fn set_send_level(&mut self, send: usize, level: f32) -> bool {
+        self.sends
+            .get_mut(send)
+            .map(|s| s.level = level.clamp(0.0, 1.0))
+            .is_some()
+    }
+
+    /// Appends an effect to a send bus; returns `false` for an unknown kind
+    /// or bus.
+    #[wasm_bindgen]
+    pub fn add_send_effect(&mut self, send: usize, kind: &str) -> bool {
+        let Some(bus) = self.sends.get_mut(send) else {
+            return false;
+        };
+        match effects::create(kind, self.sample_rate) {
+            Some(effect) => {
+                bus.chain.push(effect);
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_send_effects(&mut self, send: usize) -> bool {
+        self.sends.get_mut(send).map(|s| s.chain.clear()).is_some()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_send_effect_param(
+        &mut self,
+        send: usize,
+        index: usize,
+        name: &str,
+        value: f32,
+    ) -> bool {
+        self.sends
+            .get_mut(send)
+            .and_then(|s| s.chain.get_mut(index))
+            .is_some_and(|e| e.set_param(name, value))
+    }
+
+    /// Post-fader peak of the peer over the last processed block.
+    #[wasm_bindgen]
+    pub fn peer_level(&self, id: &str) -> f32 {
+        self.channel(id).map_or(0.0, |c| c.peak)
+    }
+
+    /// Peak of the master bus over the last processed block.
+    #[wasm_bindgen]
+    pub fn output_level(&self) -> f32 {
+        self.master_peak
+    }
+
+    #[wasm_bindgen]
+    pub fn set_master_gain(&mut self, gain: f32) {
+        self.master_gain = gain.clamp(0.0, 4.0);
+    }
+
+    /// Rebuilds the send effects for a new sample rate, keeping parameters.
+    #[wasm_bindgen]
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if !SAMPLE_RATES.contains(&sample_rate) || sample_rate == self.sample_rate {
+            return;
+        }
+        self.sample_rate = sample_rate;
+        for bus in self.sends.iter_mut() {
+            bus.chain.rebuild(sample_rate);
+        }
+        self.hrirs = Arc::new(HrirSet::new(sample_rate));
+        for channel in self.channels.iter_mut() {
+            if let Some(old) = channel.spatial.take() {
+                let mut panner = Box::new(BinauralPanner::new(Arc::clone(&self.hrirs)));
+                panner.set_position(old.azimuth(), old.elevation(), old.distance());
+                panner.reset();
+                channel.spatial = Some(panner);
+            }
+            channel.early = None;
+        }
+        self.room = Self::build_room(sample_rate);
+        if let Some(room) = &self.geometry {
+            room.geometry.configure_tail(&mut self.room);
+        }
+        self.update_reflections();
+    }
+
+    /// Mixes `length` samples starting at `offset` of every peer's input
+    /// into the output buffers.
+    #[wasm_bindgen]
+    pub fn process_audio(&mut self, offset: usize, length: usize) {
+        // Both values come straight from JS, so the sum may overflow.
+        let end = match offset.checked_add(length) {
+            Some(end) if end <= BUFFER_SIZE => end,
+            _ => return,
+        };
+        let range = offset..end;
+        let left = &mut self.output_buffer[range.clone()];
+        let right = &mut self.output_buffer_right[range.clone()];
+        left.fill(0.0);
+        right.fill(0.0);
+        for bus in self.sends.iter_mut() {
+            bus.left[range.clone()].fill(0.0);
+            bus.right[range.clone()].fill(0.0);
+        }
+        let room_input = &mut self.room_input[..length];
+        room_input.fill(0.0);
+
+        let any_solo = self.channels.iter().any(|c| c.solo);
+        let step = 1.0 / length.max(1) as f32;
+        for channel in self.channels.iter_mut() {
+            let audible = !channel.mute && (!any_solo || channel.solo);
+            let target = channel.target(audible);
+            let start = channel.current;
+            channel.current = target;
+            if start == [0.0; 2] && target == [0.0; 2] {
+                channel.peak = 0.0;
+                continue;
+            }
+            if start == [0.0; 2] {
+                // Skipped while silent, so the placement's delay lines hold
+                // audio from before; don't replay it.
+                if let Some(panner) = channel.spatial.as_mut() {
+                    panner.reset();
+                }
+                if let Some(early) = channel.early.as_mut() {
+                    early.reset();
+                }
+            }
+
+            dsp::scrub(&mut channel.input[range.clone()]);
+            let input = &channel.input[range.clone()];
+            channel.peak = simd::peak_and_energy(input).0 * target[0].max(target[1]);
+            let room_send = channel.spatial.as_ref().map_or(0.0, |p| p.reverb_send());
+            let (source_left, source_right) = match channel.spatial.as_mut() {
+                Some(panner) => {
+                    let [ear_left, ear_right] = &mut channel.rendered;
+                    panner.process(input, &mut ear_left[..length], &mut ear_right[..length]);
+                    (&ear_left[..length], &ear_right[..length])
+                }
+                None => (input, input),
+            };
+            let reflected = match channel.early.as_mut() {
+                Some(early) => {
+                    let [wall_left, wall_right] = &mut channel.reflected;
+                    early.process(input, &mut wall_left[..length], &mut wall_right[..length]);
+                    Some((&wall_left[..length], &wall_right[..length]))
+                }
+                None => None,
+            };
+
+            for i in 0..length {
+                let t = (i + 1) as f32 * step;
+                let gain_left = start[0] + (target[0] - start[0]) * t;
+                let gain_right = start[1] + (target[1] - start[1]) * t;
+                let mut l = source_left[i] * gain_left;
+                let mut r = source_right[i] * gain_right;
+                // In a room the tail grows out of the reflections instead.
+                match reflected {
+                    Some((wall_left, wall_right)) => {
+                        let (wall_left, wall_right) =
+                            (wall_left[i] * gain_left, wall_right[i] * gain_right);
+                        room_input[i] += (wall_left + wall_right) * 0.5;
+                        l += wall_left;
+                        r += wall_right;
+                    }
+                    None => room_input[i] += (l + r) * 0.5 * room_send,
+                }
+                left[i] += l;
+                right[i] += r;
+                for (bus, &level) in self.sends.iter_mut().zip(channel.sends.iter()) {
+                    if level > 0.0 {
+                        bus.left[offset + i] += l * level;
+                        bus.right[offset + i] += r * level;
+                    }
+                }
+            }
+        }
+
+        for bus in self.sends.iter_mut() {
+            if bus.chain.is_empty() {
+                continue;
+            }
+            let bus_left = &mut bus.left[range.clone()];
+            let bus_right = &mut bus.right[range.clone()];
+            bus.chain.process(bus_left, bus_right);
+            simd::mix_into(left, bus_left, bus.level);
+            simd::mix_into(right, bus_right, bus.level);
+        }
+
+        if self.room_level > 0.0 {
+            for ((l, r), &x) in left.iter_mut().zip(right.iter_mut()).zip(room_input.iter()) {
+                let (wet_left, wet_right) = self.room.process(x);
+                *l += wet_left * self.room_level;
+                *r += wet_right * self.room_level;
+            }
+        }
+
+        simd::scale(left, self.master_gain);
+        simd::scale(right, self.master_gain);
+        if dsp::scrub(left) | dsp::scrub(right) {
+            for bus in self.sends.iter_mut() {
+                bus.chain.reset();
+            }
+            for panner in self.channels.iter_mut().filter_map(|c| c.spatial.as_mut()) {
+                panner.reset();
+            }
+            for early in self.channels.iter_mut().filter_map(|c| c.early.as_mut()) {
+                early.reset();
+            }
+            self.room.reset();
+        }
+        self.master_peak = simd::peak_and_energy(left)
+            .0
+            .max(simd::peak_and_energy(right).0);
+    }
+}
+
+impl Mixer {
+    /// A peer's input block, for native callers.
+    pub fn peer_input_mut(&mut self, id: &str) -> Option<&mut [f32]> {
+        self.channel_mut(id).map(|c| c.input.as_mut_slice())
+    }
+
+    /// Left and right master output blocks.
+    pub fn output(&self) -> (&[f32], &[f32]) {
+        (&self.output_buffer, &self.output_buffer_right)
+    }
+
+    fn build_room(sample_rate: f32) -> Fdn {
+        let mut room = Fdn::new(sample_rate);
+        room.set_size(0.4);
+        room.set_decay(0.8);
+        room.set_damping(0.5);
+        room
+    }
+
+    fn update_reflections(&mut self) {
+        for channel in self.channels.iter_mut() {
+            channel.update_reflections(self.geometry.as_ref(), self.sample_rate);
+        }
+    }
+
+    fn channel(&self, id: &str) -> Option<&Channel> {
+        self.channels.iter().find(|c| c.id == id)
+    }
+
+    fn channel_mut(&mut self, id: &str) -> Option<&mut Channel> {
+        self.channels.iter_mut().find(|c| c.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(mixer: &mut Mixer, id: &str, value: f32) {
+        mixer.channel_mut(id).unwrap().input.fill(value);
+    }
+
+    /// Runs two blocks so gain ramps have settled, then returns the last
+    /// output frame.
+    fn settle(mixer: &mut Mixer) -> (f32, f32) {
+        mixer.process_audio(0, BUFFER_SIZE);
+        mixer.process_audio(0, BUFFER_SIZE);
+        (
+            mixer.output_buffer[BUFFER_SIZE - 1],
+            mixer.output_buffer_right[BUFFER_SIZE - 1],
+        )
+    }
+
+    #[test]
+    fn test_peers_are_summed_and_panned() {
+        let mut mixer = Mixer::new();
+        assert!(mixer.add_peer("a"));
+        assert!(mixer.add_peer("b"));
+        assert!(!mixer.add_peer("a"));
+        feed(&mut mixer, "a", 0.5);
+        feed(&mut mixer, "b", 0.25);
+        mixer.set_peer_pan("a", -1.0);
+        mixer.set_peer_pan("b", 1.0);
+
+        let (left, right) = settle(&mut mixer);
+        assert!((left - 0.5).abs() < 1e-6);
+        assert!((right - 0.25).abs() < 1e-6);
+        assert!((mixer.peer_level("a") - 0.5).abs() < 1e-6);
+        assert!((mixer.output_level() - 0.5).abs() < 1e-6);
+
+        assert!(mixer.remove_peer("a"));
+        let (left, _) = settle(&mut mixer);
+        assert!(left.abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_mute_and_solo() {
+        let mut mixer = Mixer::new();
+        for id in ["a", "b", "c"] {
+            mixer.add_peer(id);
+            feed(&mut mixer, id, 0.1);
+        }
+        let centre = std::f32::consts::FRAC_1_SQRT_2 * 0.1;
+
+        mixer.set_peer_mute("a", true);
+        let (left, _) = settle(&mut mixer);
+        assert!((left - 2.0 * centre).abs() < 1e-6);
+
+        // A muted peer stays silent even when soloed.
+        mixer.set_peer_solo("a", true);
+        mixer.set_peer_solo("b", true);
+        let (left, _) = settle(&mut mixer);
+        assert!((left - centre).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_send_bus_returns_processed_signal() {
+        let mut mixer = Mixer::new();
+        mixer.add_peer("a");
+        feed(&mut mixer, "a", 0.5);
+        mixer.set_peer_gain("a", 0.0);
+        assert!(mixer.set_peer_send("a", 0, 1.0));
+        assert!(!mixer.set_peer_send("a", MAX_SENDS, 1.0));
+        assert!(mixer.add_send_effect(0, "ring_mod"));
+        assert!(!mixer.add_send_effect(0, "unknown"));
+
+        // With the fader down nothing reaches the post-fader send either.
+        let (left, _) = settle(&mut mixer);
+        assert_eq!(left, 0.0);
+
+        mixer.set_peer_gain("a", 1.0);
+        mixer.process_audio(0, BUFFER_SIZE);
+        mixer.process_audio(0, BUFFER_SIZE);
+        let dry = std::f32::consts::FRAC_1_SQRT_2 * 0.5;
+        let wet = mixer
+            .output_buffer
+            .iter()
+            .map(|s| (s - dry).abs())
+            .fold(0.0, f32::max);
+        assert!(wet > 0.1, "send made no difference: {wet}");
+    }
+
+    #[test]
+    fn test_binaural_peer_is_placed_and_feeds_the_room() {
+        let mut mixer = Mixer::new();
+        mixer.add_peer("a");
+        assert!(mixer.set_peer_position("a", 90.0, 0.0, 1.0));
+        assert!(!mixer.set_peer_position("nobody", 0.0, 0.0, 1.0));
+        mixer.set_room_level(0.0);
+
+        let energy = |s: &[f32]| s.iter().map(|x| x * x).sum::<f32>();
+        let (mut left, mut right) = (0.0, 0.0);
+        for block in 0..8 {
+            let input = &mut mixer.channel_mut("a").unwrap().input;
+            for (i, x) in input.iter_mut().enumerate() {
+                *x = ((block * BUFFER_SIZE + i) as f32 * 0.3).sin() * 0.5;
+            }
+            mixer.process_audio(0, BUFFER_SIZE);
+            left += energy(&mixer.output_buffer);
+            right += energy(&mixer.output_buffer_right);
+        }
+        assert!(right > 2.0 * left, "left {left} right {right}");
+
+        // Far away and silent: only the room tail is left ringing.
+        mixer.set_peer_position("a", 0.0, 0.0, 30.0);
+        mixer.set_room_level(1.0);
+        mixer.process_audio(0, BUFFER_SIZE);
+        mixer.channel_mut("a").unwrap().input.fill(0.0);
+        for _ in 0..20 {
+            mixer.process_audio(0, BUFFER_SIZE);
+        }
+        assert!(energy(&mixer.output_buffer) > 0.0);
+    }
+
+    #[test]
+    fn test_room_adds_reflections_to_placed_peers() {
+        // Energy after an impulse, once the direct sound has died away.
+        let late_energy = |mixer: &mut Mixer| {
+            let input = mixer.peer_input_mut("a").unwrap();
+            input.fill(0.0);
+            input[0] = 1.0;
+            mixer.process_audio(0, BUFFER_SIZE);
+            mixer.peer_input_mut("a").unwrap().fill(0.0);
+            (0..16)
+                .map(|_| {
+                    mixer.process_audio(0, BUFFER_SIZE);
+                    let (left, right) = mixer.output();
+                    left.iter().chain(right).map(|s| s * s).sum::<f32>()
+                })
+                .sum::<f32>()
+        };
+
+        let mut mixer = Mixer::new();
+        mixer.add_peer("a");
+        mixer.set_room_level(0.0);
+        assert!(!mixer.set_listener_position(1.0, 1.0, 1.0));
+        mixer.set_peer_position("a", 30.0, 0.0, 2.0);
+        assert_eq!(late_energy(&mut mixer), 0.0);
+
+        mixer.set_room(6.0, 5.0, 3.0, 0.2);
+        assert!(mixer.set_listener_position(2.0, 1.5, 1.6));
+        let bare = late_energy(&mut mixer);
+        assert!(bare > 1e-4, "{bare}");
+
+        // Soft walls give back less.
+        mixer.set_room(6.0, 5.0, 3.0, 0.9);
+        let soft = late_energy(&mut mixer);
+        assert!(soft < bare * 0.5, "bare {bare}, soft {soft}");
+
+        mixer.clear_room();
+        assert_eq!(late_energy(&mut mixer), 0.0);
+    }
+
+    #[test]
+    fn test_unmuted_peer_does_not_replay_audio_from_before_the_mute() {
+        let mut mixer = Mixer::new();
+        mixer.add_peer("a");
+        mixer.set_room_level(0.0);
+        mixer.set_room(6.0, 5.0, 3.0, 0.2);
+        mixer.set_peer_position("a", 60.0, 0.0, 3.0);
+
+        mixer.peer_input_mut("a").unwrap().fill(0.5);
+        mixer.process_audio(0, BUFFER_SIZE);
+        assert!(mixer.set_peer_mute("a", true));
+        mixer.process_audio(0, BUFFER_SIZE);
+        mixer.process_audio(0, BUFFER_SIZE);
+
+        mixer.peer_input_mut("a").unwrap().fill(0.0);
+        mixer.set_peer_mute("a", false);
+        mixer.process_audio(0, BUFFER_SIZE);
+        let (left, right) = mixer.output();
+        assert!(left.iter().chain(right).all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_out_of_range_blocks_are_ignored() {
+        let mut mixer = Mixer::new();
+        mixer.add_peer("a");
+        mixer.peer_input_mut("a").unwrap().fill(0.5);
+        mixer.process_audio(usize::MAX, 2);
+        mixer.process_audio(BUFFER_SIZE, 1);
+        assert!(mixer.output().0.iter().all(|&s| s == 0.0));
+    }
+}
@@ -0,0 +1,257 @@
+//! Connection quality driving effect parameters.
+//!
+//! The page polls WebRTC `getStats()` and forwards round-trip time, jitter,
+//! packet loss and bitrate as [`NetworkStats`]. Each [`NetworkMapping`]
+//! reads one of those metrics across a range of values and sets an effect
+//! parameter from it, so a worse connection can audibly decay the voice
+//! further. Mappings are saved in the `network` list of a preset:
+//!
+//! ```json
+//! { "metric": "loss", "from": 0.0, "to": 0.2,
+//!   "effect": 0, "param": "bitrate_kbps", "min": 64, "max": 8,
+//!   "curve": "logarithmic", "smoothing": 0.5 }
+//! ```
+//!
+//! `from` maps to the bottom of the parameter range and `to` to the top;
+//! metric values outside `from..to` are clamped.
+
+use crate::effects::{self, Effect, EffectChain};
+use crate::midi::Curve;
+use serde::{Deserialize, Serialize};
+
+/// One reading of the connection, as polled from the browser.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NetworkStats {
+    pub rtt_ms: f32,
+    pub jitter_ms: f32,
+    /// Fraction of packets lost since the previous reading, `0..=1`.
+    pub loss: f32,
+    pub bitrate_kbps: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkMetric {
+    Rtt,
+    Jitter,
+    Loss,
+    Bitrate,
+}
+
+impl NetworkMetric {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rtt" => Some(NetworkMetric::Rtt),
+            "jitter" => Some(NetworkMetric::Jitter),
+            "loss" => Some(NetworkMetric::Loss),
+            "bitrate" => Some(NetworkMetric::Bitrate),
+            _ => None,
+        }
+    }
+
+    pub fn read(self, stats: &NetworkStats) -> f32 {
+        match self {
+            NetworkMetric::Rtt => stats.rtt_ms,
+            NetworkMetric::Jitter => stats.jitter_ms,
+            NetworkMetric::Loss => stats.loss,
+            NetworkMetric::Bitrate => stats.bitrate_kbps,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkMapping {
+    pub metric: NetworkMetric,
+    /// Metric value that maps to the bottom of the range. May be above
+    /// `to`, e.g. for bitrate, where lower is worse.
+    pub from: f32,
+    pub to: f32,
+    /// Index of the effect in the chain.
+    pub effect: usize,
+    pub param: String,
+    /// Parameter value at `from`; the parameter's own minimum if left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f32>,
+    #[serde(default)]
+    pub curve: Curve,
+    /// Fraction of the previous reading kept at each update, `0..1`, to
+    /// ride out single bad readings.
+    #[serde(default)]
+    pub smoothing: f32,
+    #[serde(skip)]
+    smoothed: Option<f32>,
+}
+
+impl NetworkMapping {
+    pub fn new(metric: NetworkMetric, from: f32, to: f32, effect: usize, param: &str) -> Self {
+        Self {
+            metric,
+            from,
+            to,
+            effect,
+            param: param.to_string(),
+            min: None,
+            max: None,
+            curve: Curve::Linear,
+            smoothing: 0.0,
+            smoothed: None,
+        }
+    }
+
+    fn range(&self, effect: &dyn Effect) -> Option<(f32, f32)> {
+        let info = effect.params().iter().find(|p| p.name == self.param)?;
+        Some((self.min.unwrap_or(info.min), self.max.unwrap_or(info.max)))
+    }
+
+    pub fn is_valid_for(&self, chain: &EffectChain) -> bool {
+        chain.get(self.effect).and_then(|e| self.range(e)).is_some()
+    }
+
+    /// Applies a new reading; returns whether the parameter changed.
+    fn apply(&mut self, stats: &NetworkStats, chain: &mut EffectChain) -> bool {
+        let value = self.metric.read(stats);
+        if !value.is_finite() {
+            return false;
+        }
+        let keep = self.smoothing.clamp(0.0, 0.99);
+        let value = match self.smoothed {
+            Some(previous) => previous + (value - previous) * (1.0 - keep),
+            None => value,
+        };
+        self.smoothed = Some(value);
+
+        let Some(effect) = chain.get_mut(self.effect) else {
+            return false;
+        };
+        let Some((min, max)) = self.range(effect) else {
+            return false;
+        };
+        let span = self.to - self.from;
+        let position = if span == 0.0 {
+            if value >= self.to {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            ((value - self.from) / span).clamp(0.0, 1.0)
+        };
+        let target = min + (max - min) * self.curve.apply(position);
+        let before = effect.get_param(&self.param);
+        effect.set_param(&self.param, target);
+        effect.get_param(&self.param) != before
+    }
+}
+
+/// The network mappings of one processor and the latest reading.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkMap {
+    mappings: Vec<NetworkMapping>,
+    stats: Option<NetworkStats>,
+}
+
+impl NetworkMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mappings(&self) -> &[NetworkMapping] {
+        &self.mappings
+    }
+
+    pub fn set_mappings(&mut self, mappings: Vec<NetworkMapping>) {
+        self.mappings = mappings;
+    }
+
+    /// The most recent reading, if any has arrived.
+    pub fn stats(&self) -> Option<NetworkStats> {
+        self.stats
+    }
+
+    /// Adds `mapping`, replacing any other mapping of the same parameter:
+    /// one metric drives one parameter.
+    pub fn map(&mut self, mapping: NetworkMapping) {
+        self.unmap(mapping.effect, &mapping.param);
+        self.mappings.push(mapping);
+    }
+
+    pub fn unmap(&mut self, effect: usize, param: &str) -> bool {
+        let before = self.mappings.len();
+        self.mappings
+            .retain(|m| !(m.effect == effect && m.param == param));
+        self.mappings.len() != before
+    }
+
+    /// Applies a new reading to every mapping. Returns whether any
+    /// parameter changed.
+    pub fn update(&mut self, stats: NetworkStats, chain: &mut EffectChain) -> bool {
+        self.stats = Some(stats);
+        let mut changed = false;
+        for mapping in &mut self.mappings {
+            changed |= mapping.apply(&stats, chain);
+        }
+        changed
+    }
+
+    /// Keeps mappings pointing at the same effects after `index` is removed
+    /// from the chain; mappings to the removed effect are dropped.
+    pub fn effect_removed(&mut self, index: usize) {
+        self.mappings.retain_mut(|m| {
+            effects::index_after_removal(m.effect, index)
+                .map(|i| m.effect = i)
+                .is_some()
+        });
+    }
+
+    /// Follows an [`EffectChain::move_effect`] from `from` to `to`.
+    pub fn effect_moved(&mut self, from: usize, to: usize) {
+        for mapping in &mut self.mappings {
+            mapping.effect = effects::index_after_move(mapping.effect, from, to);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::Transmission;
+
+    #[test]
+    fn test_worse_connection_moves_the_parameter() {
+        let mut chain = EffectChain::new();
+        chain.push(Box::new(Transmission::new(48_000.0)));
+        let mut map = NetworkMap::new();
+        let mut mapping = NetworkMapping::new(NetworkMetric::Bitrate, 64.0, 8.0, 0, "loss");
+        mapping.min = Some(0.0);
+        mapping.max = Some(0.4);
+        mapping.smoothing = 0.5;
+        map.map(mapping);
+        let loss = |chain: &EffectChain| chain.get(0).unwrap().get_param("loss").unwrap();
+
+        let good = NetworkStats {
+            bitrate_kbps: 64.0,
+            ..Default::default()
+        };
+        assert!(map.update(good, &mut chain));
+        assert_eq!(loss(&chain), 0.0);
+
+        // Smoothing only goes half way to the new reading, 36 kbps, which is
+        // half way along the mapped range.
+        let bad = NetworkStats {
+            bitrate_kbps: 8.0,
+            ..Default::default()
+        };
+        assert!(map.update(bad, &mut chain));
+        assert!((loss(&chain) - 0.2).abs() < 1e-6, "{}", loss(&chain));
+
+        let json = serde_json::to_string(map.mappings()).unwrap();
+        let reloaded: Vec<NetworkMapping> = serde_json::from_str(&json).unwrap();
+        assert_eq!(reloaded[0].metric, NetworkMetric::Bitrate);
+        assert_eq!(reloaded[0].max, Some(0.4));
+
+        map.effect_removed(0);
+        assert!(map.mappings().is_empty());
+    }
+}
@@ -0,0 +1,245 @@
+//! JSON presets: an ordered list of effects and their parameter values.
+//!
+//! ```json
+//! {
+//!   "name": "Cave",
+//!   "effects": [
+//!     { "kind": "pitch_shift", "params": { "semitones": -5 } },
+//!     { "kind": "reverb", "params": { "mix": 0.6 } }
+//!   ]
+//! }
+//! ```
+//!
+//! Parameters left out of a preset keep their defaults. The same files are
+//! read by the browser processor and by the native renderer, so a preset
+//! auditioned in one sounds the same in the other.
+
+use crate::age::Age;
+use crate::effects::{self, EffectChain};
+use crate::midi::MidiMapping;
+use crate::network::NetworkMapping;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(default)]
+    pub effects: Vec<EffectPreset>,
+    /// Hardware controls mapped onto the effects above.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub midi: Vec<MidiMapping>,
+    /// Connection metrics mapped onto the effects above.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub network: Vec<NetworkMapping>,
+    /// Where the effects above drift to over a session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age: Option<Age>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EffectPreset {
+    pub kind: String,
+    #[serde(default)]
+    pub params: BTreeMap<String, f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PresetError {
+    Json(String),
+    UnknownKind(String),
+    UnknownParam {
+        kind: String,
+        name: String,
+    },
+    BadMidiTarget {
+        effect: usize,
+        param: String,
+    },
+    BadNetworkTarget {
+        effect: usize,
+        param: String,
+    },
+    /// The decayed effect at `index` isn't the preset's effect there.
+    AgeMismatch {
+        index: usize,
+    },
+}
+
+impl fmt::Display for PresetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresetError::Json(message) => write!(f, "invalid preset JSON: {message}"),
+            PresetError::UnknownKind(kind) => write!(f, "unknown effect kind `{kind}`"),
+            PresetError::UnknownParam { kind, name } => {
+                write!(f, "effect `{kind}` has no parameter `{name}`")
+            }
+            PresetError::BadMidiTarget { effect, param } => {
+                write!(
+                    f,
+                    "MIDI mapping targets missing parameter `{param}` of effect {effect}"
+                )
+            }
+            PresetError::BadNetworkTarget { effect, param } => {
+                write!(
+                    f,
+                    "network mapping targets missing parameter `{param}` of effect {effect}"
+                )
+            }
+            PresetError::AgeMismatch { index } => {
+                write!(
+                    f,
+                    "decayed effect {index} does not match the preset's effect"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PresetError {}
+
+impl Preset {
+    pub fn from_json(json: &str) -> Result<Self, PresetError> {
+        serde_json::from_str(json).map_err(|e| PresetError::Json(e.to_string()))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("presets always serialize")
+    }
+
+    /// Captures the current kinds and parameter values of `chain`.
+    pub fn from_chain(chain: &EffectChain) -> Self {
+        let effects = chain
+            .iter()
+            .map(|effect| EffectPreset {
+                kind: effect.kind().to_string(),
+                params: effect
+                    .params()
+                    .iter()
+                    .filter_map(|info| Some((info.name.to_string(), effect.get_param(info.name)?)))
+                    .collect(),
+            })
+            .collect();
+        Self {
+            name: String::new(),
+            effects,
+            midi: Vec::new(),
+            network: Vec::new(),
+            age: None,
+        }
+    }
+
+    /// Builds a fresh chain for `sample_rate`. Fails on the first unknown
+    /// kind or parameter rather than quietly dropping part of the sound.
+    pub fn build(&self, sample_rate: f32) -> Result<EffectChain, PresetError> {
+        let mut chain = EffectChain::new();
+        for entry in &self.effects {
+            let mut effect = effects::create(&entry.kind, sample_rate)
+                .ok_or_else(|| PresetError::UnknownKind(entry.kind.clone()))?;
+            for (name, &value) in &entry.params {
+                if !effect.set_param(name, value) {
+                    return Err(PresetError::UnknownParam {
+                        kind: entry.kind.clone(),
+                        name: name.clone(),
+                    });
+                }
+            }
+            chain.push(effect);
+        }
+        if let Some(mapping) = self.midi.iter().find(|m| !m.is_valid_for(&chain)) {
+            return Err(PresetError::BadMidiTarget {
+                effect: mapping.effect,
+                param: mapping.param.clone(),
+            });
+        }
+        if let Some(mapping) = self.network.iter().find(|m| !m.is_valid_for(&chain)) {
+            return Err(PresetError::BadNetworkTarget {
+                effect: mapping.effect,
+                param: mapping.param.clone(),
+            });
+        }
+        if let Some(age) = &self.age {
+            if age.decayed.len() != self.effects.len() {
+                let index = age.decayed.len().min(self.effects.len());
+                return Err(PresetError::AgeMismatch { index });
+            }
+            for (index, (aged, effect)) in age.decayed.iter().zip(chain.iter()).enumerate() {
+                if aged.kind != effect.kind() {
+                    return Err(PresetError::AgeMismatch { index });
+                }
+                if let Some(name) = aged
+                    .params
+                    .keys()
+                    .find(|&name| effect.get_param(name).is_none())
+                {
+                    return Err(PresetError::UnknownParam {
+                        kind: aged.kind.clone(),
+                        name: name.clone(),
+                    });
+                }
+            }
+        }
+        Ok(chain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_through_chain() {
+        let json = r#"{
+            "name": "Cave",
+            "effects": [
+                { "kind": "pitch_shift", "params": { "semitones": -5 } },
+                { "kind": "reverb" }
+            ]
+        }"#;
+        let preset = Preset::from_json(json).unwrap();
+        let chain = preset.build(48_000.0).unwrap();
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain.get(0).unwrap().get_param("semitones"), Some(-5.0));
+
+        let captured = Preset::from_chain(&chain);
+        assert_eq!(captured.effects[0].params["semitones"], -5.0);
+        let reloaded = Preset::from_json(&captured.to_json()).unwrap();
+        assert_eq!(reloaded, captured);
+    }
+
+    #[test]
+    fn test_errors_name_the_problem() {
+        let unknown_kind = r#"{ "effects": [{ "kind": "flanger" }] }"#;
+        assert_eq!(
+            Preset::from_json(unknown_kind)
+                .unwrap()
+                .build(48_000.0)
+                .err(),
+            Some(PresetError::UnknownKind("flanger".into()))
+        );
+        let unknown_param = r#"{ "effects": [{ "kind": "reverb", "params": { "size2": 1 } }] }"#;
+        assert!(matches!(
+            Preset::from_json(unknown_param).unwrap().build(48_000.0),
+            Err(PresetError::UnknownParam { .. })
+        ));
+        assert!(matches!(Preset::from_json("{"), Err(PresetError::Json(_))));
+        let bad_midi = r#"{ "effects": [{ "kind": "reverb" }], "midi": [
+            { "source": { "cc": { "channel": 0, "controller": 1 } }, "effect": 1, "param": "mix" }
+        ] }"#;
+        assert_eq!(
+            Preset::from_json(bad_midi).unwrap().build(48_000.0).err(),
+            Some(PresetError::BadMidiTarget {
+                effect: 1,
+                param: "mix".into()
+            })
+        );
+        let bad_age = r#"{ "effects": [{ "kind": "reverb" }, { "kind": "ring_mod" }],
+            "age": { "duration_s": 60, "decayed": [{ "kind": "reverb" }, { "kind": "reverb" }] } }"#;
+        assert_eq!(
+            Preset::from_json(bad_age).unwrap().build(48_000.0).err(),
+            Some(PresetError::AgeMismatch { index: 1 })
+        );
+    }
+}
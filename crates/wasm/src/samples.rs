@@ -0,0 +1,286 @@
+//! Decoding audio files handed over from JS, and the pool they live in.
+//!
+//! Impulse responses, looper imports and granular sources all arrive as the
+//! raw bytes of a WAV (16/24-bit PCM or float) or FLAC file. [`decode`]
+//! turns those into planar float channels, and [`SamplePool`] resamples them
+//! to the processor's rate once, on load, so nothing on the audio thread has
+//! to care what rate a file was recorded at.
+
+use crate::audio_processor::SAMPLE_RATES;
+use crate::dsp;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Cursor;
+use std::sync::Arc;
+
+/// Files with more channels than this are rejected.
+pub const MAX_CHANNELS: usize = 8;
+
+/// Decoded audio, one `Vec` per channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub sample_rate: f32,
+    pub channels: Vec<Vec<f32>>,
+}
+
+impl Sample {
+    pub fn frames(&self) -> usize {
+        self.channels.first().map_or(0, Vec::len)
+    }
+
+    fn from_interleaved(samples: Vec<f32>, channels: usize, sample_rate: u32) -> Self {
+        let mut planar = vec![Vec::with_capacity(samples.len() / channels); channels];
+        for frame in samples.chunks_exact(channels) {
+            for (channel, &sample) in planar.iter_mut().zip(frame) {
+                channel.push(sample);
+            }
+        }
+        Self {
+            sample_rate: sample_rate as f32,
+            channels: planar,
+        }
+    }
+
+    /// The same audio at `sample_rate`.
+    pub fn resampled(&self, sample_rate: f32) -> Self {
+        if sample_rate == self.sample_rate {
+            return self.clone();
+        }
+        Self {
+            sample_rate,
+            channels: self
+                .channels
+                .iter()
+                .map(|channel| dsp::resample(channel, self.sample_rate, sample_rate))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    /// Neither a RIFF/WAVE nor a FLAC stream.
+    UnknownFormat,
+    Wav(String),
+    Flac(String),
+    Unsupported(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnknownFormat => write!(f, "not a WAV or FLAC file"),
+            DecodeError::Wav(message) => write!(f, "invalid WAV file: {message}"),
+            DecodeError::Flac(message) => write!(f, "invalid FLAC file: {message}"),
+            DecodeError::Unsupported(message) => write!(f, "unsupported audio: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+fn int_scale(bits: u32) -> f32 {
+    1.0 / (1u64 << (bits - 1)) as f32
+}
+
+/// Decodes a whole WAV or FLAC file, telling them apart by their magic
+/// bytes rather than trusting a file name.
+pub fn decode(bytes: &[u8]) -> Result<Sample, DecodeError> {
+    let sample = if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WAVE") {
+        decode_wav(bytes)?
+    } else if bytes.starts_with(b"fLaC") {
+        decode_flac(bytes)?
+    } else {
+        return Err(DecodeError::UnknownFormat);
+    };
+    if !SAMPLE_RATES.contains(&sample.sample_rate) {
+        return Err(DecodeError::Unsupported(format!(
+            "sample rate {} Hz",
+            sample.sample_rate
+        )));
+    }
+    Ok(sample)
+}
+
+fn check_channels(channels: usize) -> Result<usize, DecodeError> {
+    if (1..=MAX_CHANNELS).contains(&channels) {
+        Ok(channels)
+    } else {
+        Err(DecodeError::Unsupported(format!("{channels} channels")))
+    }
+}
+
+fn decode_wav(bytes: &[u8]) -> Result<Sample, DecodeError> {
+    let wav_error = |e: hound::Error| DecodeError::Wav(e.to_string());
+    let mut reader = hound::WavReader::new(Cursor::new(bytes)).map_err(wav_error)?;
+    let spec = reader.spec();
+    let channels = check_channels(spec.channels as usize)?;
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(wav_error)?,
+        hound::SampleFormat::Int => {
+            let scale = int_scale(spec.bits_per_sample as u32);
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 * scale))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(wav_error)?
+        }
+    };
+    Ok(Sample::from_interleaved(
+        samples,
+        channels,
+        spec.sample_rate,
+    ))
+}
+
+fn decode_flac(bytes: &[u8]) -> Result<Sample, DecodeError> {
+    let flac_error = |e: claxon::Error| DecodeError::Flac(e.to_string());
+    let mut reader = claxon::FlacReader::new(Cursor::new(bytes)).map_err(flac_error)?;
+    let info = reader.streaminfo();
+    let channels = check_channels(info.channels as usize)?;
+    let scale = int_scale(info.bits_per_sample);
+    let samples = reader
+        .samples()
+        .map(|s| s.map(|s| s as f32 * scale))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(flac_error)?;
+    Ok(Sample::from_interleaved(
+        samples,
+        channels,
+        info.sample_rate,
+    ))
+}
+
+/// Named samples, all at the pool's sample rate.
+///
+/// Samples are handed out as `Arc`s so an effect can keep playing one after
+/// it has been replaced or removed from the pool.
+pub struct SamplePool {
+    sample_rate: f32,
+    samples: BTreeMap<String, Arc<Sample>>,
+}
+
+impl SamplePool {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            samples: BTreeMap::new(),
+        }
+    }
+
+    /// Decodes `bytes` and stores the result under `name`, replacing any
+    /// sample already there.
+    pub fn load(&mut self, name: &str, bytes: &[u8]) -> Result<Arc<Sample>, DecodeError> {
+        let sample = Arc::new(decode(bytes)?.resampled(self.sample_rate));
+        self.samples.insert(name.to_string(), Arc::clone(&sample));
+        Ok(sample)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<Sample>> {
+        self.samples.get(name).cloned()
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.samples.remove(name).is_some()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.samples.keys().map(String::as_str)
+    }
+
+    /// Resamples everything in the pool. Each change costs a little
+    /// quality, so callers should settle the rate before loading.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        for sample in self.samples.values_mut() {
+            *sample = Arc::new(sample.resampled(sample_rate));
+        }
+        self.sample_rate = sample_rate;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(spec: hound::WavSpec, frames: &[[f32; 2]]) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut bytes, spec).unwrap();
+        for sample in frames.iter().flatten() {
+            match spec.sample_format {
+                hound::SampleFormat::Float => writer.write_sample(*sample).unwrap(),
+                hound::SampleFormat::Int => {
+                    let full_scale = (1i32 << (spec.bits_per_sample - 1)) as f32;
+                    writer.write_sample((sample * full_scale) as i32).unwrap()
+                }
+            }
+        }
+        writer.finalize().unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_decodes_wav_and_flac() {
+        let frames = [[0.0, 0.5], [-0.5, 0.25], [0.125, -1.0]];
+        for (bits, sample_format) in [
+            (16, hound::SampleFormat::Int),
+            (24, hound::SampleFormat::Int),
+            (32, hound::SampleFormat::Float),
+        ] {
+            let spec = hound::WavSpec {
+                channels: 2,
+                sample_rate: 44_100,
+                bits_per_sample: bits,
+                sample_format,
+            };
+            let sample = decode(&wav(spec, &frames)).unwrap();
+            assert_eq!(sample.sample_rate, 44_100.0);
+            assert_eq!(sample.channels[0], [0.0, -0.5, 0.125], "{bits} bits");
+            assert_eq!(sample.channels[1], [0.5, 0.25, -1.0], "{bits} bits");
+        }
+
+        // A stereo ramp, 64 frames of 16-bit verbatim FLAC at 48 kHz.
+        let flac = decode(include_bytes!("../tests/fixtures/ramp.flac")).unwrap();
+        assert_eq!(flac.sample_rate, 48_000.0);
+        assert_eq!(flac.frames(), 64);
+        assert_eq!(flac.channels[0][63], 63.0 * 256.0 / 32768.0);
+        assert_eq!(flac.channels[1][63], -63.0 * 128.0 / 32768.0);
+
+        assert_eq!(
+            decode(b"ID3\x04 not audio"),
+            Err(DecodeError::UnknownFormat)
+        );
+        assert!(matches!(decode(b"fLaC\0\0"), Err(DecodeError::Flac(_))));
+    }
+
+    #[test]
+    fn test_pool_resamples_on_load() {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 24_000,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut bytes = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut bytes, spec).unwrap();
+        for i in 0..2400 {
+            writer.write_sample((i as f32 * 0.1).sin() * 0.5).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let mut pool = SamplePool::new(48_000.0);
+        let sample = pool.load("ir", bytes.get_ref()).unwrap();
+        assert_eq!(sample.channels.len(), 1);
+        assert_eq!(sample.frames(), 4800);
+        assert_eq!(pool.names().collect::<Vec<_>>(), ["ir"]);
+
+        pool.set_sample_rate(96_000.0);
+        assert_eq!(pool.get("ir").unwrap().frames(), 9600);
+        // The handle taken before the change still holds the old audio.
+        assert_eq!(sample.frames(), 4800);
+        assert!(pool.remove("ir"));
+        assert!(pool.get("ir").is_none());
+    }
+}
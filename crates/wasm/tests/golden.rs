@@ -0,0 +1,299 @@
+//! Golden-file regression tests.
+//!
+//! Every effect kind (at default parameters) and every preset in
+//! `tests/golden/presets` is rendered with a set of deterministic test
+//! signals and compared with the reference WAV files in `tests/golden`.
+//! A render passes if it is within [`MAX_ABS_ERROR`] of the reference
+//! sample by sample and within [`MAX_SPECTRAL_DB`] on average across a
+//! short-time spectrum, so an intentional change in sound shows up as a
+//! failure naming the case and both numbers.
+//!
+//! After a deliberate change, regenerate the references and listen to them:
+//!
+//! ```sh
+//! DECAY_BLESS=1 cargo test -p decay-wasm --test golden
+//! ```
+
+use decay_wasm::effects::KINDS;
+use decay_wasm::preset::{EffectPreset, Preset};
+use std::f32::consts::TAU;
+use std::path::{Path, PathBuf};
+
+const SAMPLE_RATE: u32 = 48_000;
+const FRAMES: usize = 2048;
+const BLOCK: usize = 128;
+/// Where the impulse sits. Not at 0, where anything multiplying the input
+/// by an oscillator starting at phase 0 would turn it into silence.
+const IMPULSE_AT: usize = 100;
+/// Anything quieter than this throughout counts as a silent render.
+const SILENCE: f32 = 1e-6;
+
+const MAX_ABS_ERROR: f32 = 1e-4;
+const MAX_SPECTRAL_DB: f32 = 0.1;
+
+const FFT_SIZE: usize = 256;
+const HOP: usize = 128;
+/// Bins quieter than this in both renders are ignored by the spectral
+/// metric, so noise-floor wobble doesn't dominate it.
+const FLOOR_DB: f32 = -60.0;
+
+type Stereo = [Vec<f32>; 2];
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn impulse() -> Stereo {
+    let mut samples = vec![0.0; FRAMES];
+    samples[IMPULSE_AT] = 1.0;
+    [samples.clone(), samples]
+}
+
+/// Exponential sine sweep from 20 Hz to 20 kHz at half scale.
+fn sweep() -> Stereo {
+    let (start, end) = (20.0f32, 20_000.0f32);
+    let duration = FRAMES as f32 / SAMPLE_RATE as f32;
+    let k = (end / start).ln();
+    let samples: Vec<f32> = (0..FRAMES)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let phase = TAU * start * duration / k * ((t / duration * k).exp() - 1.0);
+            0.5 * phase.sin()
+        })
+        .collect();
+    [samples.clone(), samples]
+}
+
+/// Uncorrelated white noise on each side, from a fixed LCG seed.
+fn noise() -> Stereo {
+    let mut seed = 0x5eed_u32;
+    let mut side = || -> Vec<f32> {
+        (0..FRAMES)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                ((seed >> 8) as f32 / (1 << 24) as f32 - 0.5) * 0.5
+            })
+            .collect()
+    };
+    [side(), side()]
+}
+
+fn signals() -> [(&'static str, Stereo); 3] {
+    [
+        ("impulse", impulse()),
+        ("sweep", sweep()),
+        ("noise", noise()),
+    ]
+}
+
+/// Single-effect presets for every kind, then the checked-in presets.
+fn cases() -> Vec<(String, Preset)> {
+    let mut cases: Vec<(String, Preset)> = KINDS
+        .iter()
+        .map(|kind| {
+            let preset = Preset {
+                name: kind.to_string(),
+                effects: vec![EffectPreset {
+                    kind: kind.to_string(),
+                    params: Default::default(),
+                }],
+                midi: Vec::new(),
+                network: Vec::new(),
+                age: None,
+            };
+            (kind.to_string(), preset)
+        })
+        .collect();
+
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(golden_dir().join("presets"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "json"))
+        .collect();
+    paths.sort();
+    for path in paths {
+        let json = std::fs::read_to_string(&path).unwrap();
+        let preset = Preset::from_json(&json).unwrap_or_else(|e| panic!("{path:?}: {e}"));
+        let name = format!("preset_{}", path.file_stem().unwrap().to_string_lossy());
+        cases.push((name, preset));
+    }
+    cases
+}
+
+/// Renders in 128-frame blocks like the worklet, running on past the end
+/// of the input for the chain's latency so delayed effects are heard in
+/// full. The chain gets a `record` event on the first block and `play`
+/// halfway through, so the looper has something to do; other effects
+/// ignore both.
+fn render(preset: &Preset, input: &Stereo) -> Stereo {
+    let mut chain = preset.build(SAMPLE_RATE as f32).unwrap();
+    let [mut left, mut right] = input.clone();
+    left.resize(FRAMES + chain.latency(), 0.0);
+    right.resize(FRAMES + chain.latency(), 0.0);
+    for (block, (l, r)) in left
+        .chunks_mut(BLOCK)
+        .zip(right.chunks_mut(BLOCK))
+        .enumerate()
+    {
+        let event = match block {
+            0 => Some("record"),
+            8 => Some("play"),
+            _ => None,
+        };
+        if let Some(event) = event {
+            for index in 0..chain.len() {
+                chain.get_mut(index).unwrap().trigger(event);
+            }
+        }
+        chain.process(l, r);
+    }
+    [left, right]
+}
+
+fn read_reference(path: &Path) -> Option<Stereo> {
+    let mut reader = hound::WavReader::open(path).ok()?;
+    let samples: Vec<f32> = reader.samples::<f32>().map(Result::unwrap).collect();
+    let left = samples.iter().step_by(2).copied().collect();
+    let right = samples.iter().skip(1).step_by(2).copied().collect();
+    Some([left, right])
+}
+
+fn write_reference(path: &Path, audio: &Stereo) {
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).unwrap();
+    for (l, r) in audio[0].iter().zip(&audio[1]) {
+        writer.write_sample(*l).unwrap();
+        writer.write_sample(*r).unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+fn max_abs_error(a: &[f32], b: &[f32]) -> f32 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y).abs())
+        .fold(0.0, f32::max)
+}
+
+/// Magnitude spectra in dB of Hann-windowed frames. A plain DFT: the
+/// signals are short and clarity beats speed here.
+fn spectrogram(signal: &[f32]) -> Vec<Vec<f32>> {
+    let window: Vec<f32> = (0..FFT_SIZE)
+        .map(|i| 0.5 - 0.5 * (TAU * i as f32 / FFT_SIZE as f32).cos())
+        .collect();
+    (0..=signal.len() - FFT_SIZE)
+        .step_by(HOP)
+        .map(|start| {
+            let frame = &signal[start..start + FFT_SIZE];
+            (0..=FFT_SIZE / 2)
+                .map(|bin| {
+                    let (mut re, mut im) = (0.0f32, 0.0f32);
+                    for (i, (&x, &w)) in frame.iter().zip(&window).enumerate() {
+                        let phase = TAU * (bin * i % FFT_SIZE) as f32 / FFT_SIZE as f32;
+                        re += x * w * phase.cos();
+                        im -= x * w * phase.sin();
+                    }
+                    let magnitude = (re * re + im * im).sqrt() * 2.0 / FFT_SIZE as f32;
+                    20.0 * magnitude.max(1e-10).log10()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Mean absolute difference in dB over the bins that are above
+/// [`FLOOR_DB`] in either signal.
+fn spectral_difference(a: &[f32], b: &[f32]) -> f32 {
+    let (mut total, mut count) = (0.0, 0);
+    for (frame_a, frame_b) in spectrogram(a).iter().zip(&spectrogram(b)) {
+        for (&x, &y) in frame_a.iter().zip(frame_b) {
+            if x.max(y) > FLOOR_DB {
+                total += (x.max(FLOOR_DB) - y.max(FLOOR_DB)).abs();
+                count += 1;
+            }
+        }
+    }
+    if count == 0 {
+        0.0
+    } else {
+        total / count as f32
+    }
+}
+
+#[test]
+fn renders_match_golden_files() {
+    let bless = std::env::var_os("DECAY_BLESS").is_some();
+    let mut failures = Vec::new();
+
+    for (case, preset) in cases() {
+        for (signal, input) in signals() {
+            let output = render(&preset, &input);
+            let name = format!("{case}-{signal}");
+            let path = golden_dir().join(format!("{name}.wav"));
+            assert!(
+                output.iter().flatten().all(|s| s.is_finite()),
+                "{name}: non-finite output"
+            );
+            // A silent reference passes against any implementation.
+            if output.iter().flatten().all(|s| s.abs() < SILENCE) {
+                failures.push(format!("{name}: silent render"));
+                continue;
+            }
+
+            if bless {
+                write_reference(&path, &output);
+                continue;
+            }
+            let Some(reference) = read_reference(&path) else {
+                failures.push(format!("{name}: no reference, run with DECAY_BLESS=1"));
+                continue;
+            };
+
+            for (side, (actual, expected)) in
+                ["left", "right"].iter().zip(output.iter().zip(&reference))
+            {
+                if actual.len() != expected.len() {
+                    failures.push(format!(
+                        "{name} {side}: {} frames, reference has {}",
+                        actual.len(),
+                        expected.len()
+                    ));
+                    continue;
+                }
+                let error = max_abs_error(actual, expected);
+                let spectral = spectral_difference(actual, expected);
+                if error > MAX_ABS_ERROR || spectral > MAX_SPECTRAL_DB {
+                    failures.push(format!(
+                        "{name} {side}: max abs error {error:.2e}, spectral difference {spectral:.3} dB"
+                    ));
+                }
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn metrics_notice_small_changes() {
+    let [reference, _] = sweep();
+    assert_eq!(max_abs_error(&reference, &reference), 0.0);
+    assert_eq!(spectral_difference(&reference, &reference), 0.0);
+
+    // 1 dB quieter: well inside what a listener notices, and far outside
+    // both tolerances.
+    let quieter: Vec<f32> = reference.iter().map(|s| s * 0.891).collect();
+    assert!(max_abs_error(&reference, &quieter) > MAX_ABS_ERROR);
+    assert!(spectral_difference(&reference, &quieter) > MAX_SPECTRAL_DB);
+
+    // A one-sample click barely moves the spectrum but trips the sample
+    // comparison.
+    let mut clicked = reference.clone();
+    clicked[1000] += 0.01;
+    assert!(max_abs_error(&reference, &clicked) > MAX_ABS_ERROR);
+}
@@ -0,0 +1,135 @@
+//! Real-time safety checks for the audio path.
+//!
+//! A counting global allocator watches every `process_audio` call once the
+//! processor has been prepared (built, configured and run for one block).
+//! Any allocation or panic on the audio path fails the test. Locks are kept
+//! out statically instead: `clippy.toml` disallows the std lock types in
+//! this crate.
+
+use decay_wasm::effects::KINDS;
+use decay_wasm::{AudioProcessor, Mixer};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ARMED: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn note_allocation() {
+    // `try_with` because the allocator also runs while thread locals are
+    // being torn down.
+    let _ = ARMED.try_with(|armed| {
+        if armed.get() {
+            ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        }
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        note_allocation();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        note_allocation();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        note_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        note_allocation();
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Runs `f` with allocation counting on and returns how many allocator
+/// calls it made, or `Err` if it panicked.
+fn audio_thread<F: FnOnce()>(f: F) -> Result<usize, String> {
+    ALLOCATIONS.with(|count| count.set(0));
+    ARMED.with(|armed| armed.set(true));
+    let result = catch_unwind(AssertUnwindSafe(f));
+    ARMED.with(|armed| armed.set(false));
+    match result {
+        Ok(()) => Ok(ALLOCATIONS.with(Cell::get)),
+        Err(_) => Err("panicked".to_string()),
+    }
+}
+
+fn fill_noise(buffer: &mut [f32], seed: &mut u32) {
+    for sample in buffer.iter_mut() {
+        *seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        *sample = (*seed >> 8) as f32 / (1 << 24) as f32 - 0.5;
+    }
+}
+
+#[test]
+fn every_effect_processes_without_allocating_or_panicking() {
+    for kind in KINDS {
+        let mut processor = AudioProcessor::new();
+        processor.clear_effects();
+        assert!(processor.add_effect(kind));
+        processor.trigger_effect(0, "record");
+        let mut seed = 1;
+        fill_noise(processor.input_mut(), &mut seed);
+        processor.process_audio(0, 128);
+
+        for block in 0..200 {
+            fill_noise(processor.input_mut(), &mut seed);
+            if block == 100 {
+                processor.trigger_effect(0, "play");
+            }
+            // Uneven sub-blocks, as the worklet may hand over.
+            let result = audio_thread(|| {
+                processor.process_audio(0, 50);
+                processor.process_audio(50, 78);
+            });
+            assert_eq!(result, Ok(0), "{kind} on block {block}");
+        }
+
+        let (left, right) = processor.output();
+        assert!(left.iter().chain(right).all(|s| s.is_finite()), "{kind}");
+    }
+}
+
+#[test]
+fn mixer_processes_without_allocating_or_panicking() {
+    let mut mixer = Mixer::new();
+    for id in ["a", "b", "c"] {
+        mixer.add_peer(id);
+        mixer.set_peer_send(id, 0, 0.5);
+    }
+    mixer.set_peer_position("b", 45.0, 10.0, 4.0);
+    mixer.set_room(8.0, 6.0, 3.0, 0.3);
+    mixer.add_send_effect(0, "reverb");
+    mixer.process_audio(0, 128);
+
+    let mut seed = 7;
+    for block in 0..100 {
+        for id in ["a", "b", "c"] {
+            fill_noise(mixer.peer_input_mut(id).unwrap(), &mut seed);
+        }
+        mixer.set_peer_position("b", block as f32 * 3.0, 0.0, 2.0);
+        mixer.set_listener_position(2.0 + block as f32 * 0.05, 3.0, 1.6);
+        let result = audio_thread(|| mixer.process_audio(0, 128));
+        assert_eq!(result, Ok(0), "block {block}");
+    }
+}
+
+#[test]
+fn harness_notices_allocations() {
+    let result = audio_thread(|| drop(vec![0u8; 16]));
+    assert_eq!(result, Ok(2));
+    assert!(audio_thread(|| panic!("boom")).is_err());
+}
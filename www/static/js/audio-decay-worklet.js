@@ -21,7 +21,7 @@ class AudioDecayProcessor extends AudioWorkletProcessor {
         throw new Error("No processor options provided");
       }
 
-      const { wasmMemory, inputPtr, outputPtr, constants } =
+      const { wasmMemory, inputPtr, outputPtr, outputRightPtr, constants } =
         options.processorOptions;
 
       // Validate required parameters
@@ -38,6 +38,7 @@ class AudioDecayProcessor extends AudioWorkletProcessor {
       this.wasmMemoryBuffer = wasmMemory.buffer;
       this.inputPtr = inputPtr;
       this.outputPtr = outputPtr;
+      this.outputRightPtr = outputRightPtr;
 
       // Create WASM memory views using constants
       this.inputView = new Float32Array(
@@ -50,6 +51,13 @@ class AudioDecayProcessor extends AudioWorkletProcessor {
         this.outputPtr,
         this.bufferSize,
       );
+      this.outputRightView = outputRightPtr
+        ? new Float32Array(
+            this.wasmMemoryBuffer,
+            this.outputRightPtr,
+            this.bufferSize,
+          )
+        : null;
 
       console.log(
         "[AudioDecayProcessor] Initialized with buffer size:",
@@ -91,7 +99,7 @@ class AudioDecayProcessor extends AudioWorkletProcessor {
       outputChannel.set(this.outputView);
 
       if (output[1]) {
-        output[1].set(outputChannel);
+        output[1].set(this.outputRightView ?? outputChannel);
       }
 
       return true;
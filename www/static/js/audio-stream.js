@@ -41,6 +41,7 @@ class WasmAudioProcessor {
     this.wasmProcessor = null;
     this.wasmMemory = null;
     this.sourceNode = null;
+    this.sessionStart = null;
   }
 
   setAudioContext(context) {
@@ -82,6 +83,11 @@ class WasmAudioProcessor {
       // Then initialize WASM
       if (!this.wasmProcessor) {
         this.wasmProcessor = await initWasmProcessor();
+        // Effects are built for 48 kHz; retune them to the device rate
+        this.wasmProcessor.set_sample_rate(this.audioContext.sampleRate);
+        if (this.sessionStart !== null) {
+          this.wasmProcessor.set_session_start(this.sessionStart);
+        }
       }
 
       // Create worklet node
@@ -96,6 +102,7 @@ class WasmAudioProcessor {
             wasmMemory: this.wasmMemory,
             inputPtr: this.wasmProcessor.get_input_buffer_ptr(),
             outputPtr: this.wasmProcessor.get_output_buffer_ptr(),
+            outputRightPtr: this.wasmProcessor.get_output_right_buffer_ptr(),
             constants: AUDIO_CONSTANTS,
           },
         },
@@ -112,6 +119,20 @@ class WasmAudioProcessor {
     }
   }
 
+  updateNetworkStats({ rttMs, jitterMs, loss, bitrateKbps }) {
+    this.wasmProcessor?.network_stats(rttMs, jitterMs, loss, bitrateKbps);
+  }
+
+  setSessionStart(startMs) {
+    // Kept for a processor created after the session starts
+    this.sessionStart = startMs;
+    this.wasmProcessor?.set_session_start(startMs);
+  }
+
+  updateSessionTime(nowMs) {
+    return this.wasmProcessor?.session_time(nowMs);
+  }
+
   async cleanup() {
     try {
       if (this.sourceNode) {
@@ -194,6 +215,18 @@ export class AudioStreamManager {
     }
   }
 
+  updateNetworkStats(stats) {
+    this.wasmProcessor?.updateNetworkStats(stats);
+  }
+
+  setSessionStart(startMs) {
+    this.wasmProcessor?.setSessionStart(startMs);
+  }
+
+  updateSessionTime(nowMs) {
+    return this.wasmProcessor?.updateSessionTime(nowMs);
+  }
+
   startInputMonitoring() {
     if (!this.audioContext || !this.analyser) return;
 
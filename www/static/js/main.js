@@ -317,12 +317,18 @@ class AudioDecayClient {
         });
         await peerConnection.setLocalDescription(offer);
 
+        // The offerer picks the session start and the answerer adopts it,
+        // so both sides age the patch from the same moment
+        const sessionStart = Date.now();
+        audioManager.setSessionStart(sessionStart);
+
         this.ws.send(
           JSON.stringify({
             type: "RTCOffer",
             from_id: this.userId,
             to_id: message.from_id,
             offer: JSON.stringify(offer),
+            session_start: sessionStart,
           }),
         );
       } catch (error) {
@@ -402,11 +408,17 @@ class AudioDecayClient {
 
       await peerConnection.setLocalDescription(offer);
 
+      // The offerer picks the session start and the answerer adopts it,
+      // so both sides age the patch from the same moment
+      const sessionStart = Date.now();
+      audioManager.setSessionStart(sessionStart);
+
       const offerMsg = {
         type: "RTCOffer",
         from_id: this.userId,
         to_id: peerId,
         offer: JSON.stringify(offer),
+        session_start: sessionStart,
       };
 
       this.ws.send(JSON.stringify(offerMsg));
@@ -441,6 +453,10 @@ class AudioDecayClient {
         },
       );
 
+      // Age from the offerer's start rather than our own, unless it's a
+      // client that doesn't send one
+      audioManager.setSessionStart(message.session_start ?? Date.now());
+
       // Set remote description first
       const offer = JSON.parse(message.offer);
       await peerConnection.setRemoteDescription(
@@ -466,6 +482,9 @@ class AudioDecayClient {
       // Set active connection AFTER successful setup
       this.activeConnection = message.from_id;
       this.updateUserList([...this.users.values()]);
+
+      // The answerer ages too, so it needs the stats loop as well
+      await this.monitorConnection(message.from_id);
     } catch (error) {
       console.error("Failed to handle offer:", error);
       await this.cleanupConnection(message.from_id);
@@ -614,18 +633,45 @@ class AudioDecayClient {
     if (!audioManager?.peerConnection || !connectionState) return;
 
     const startTime = Date.now();
-    let lastBytes = 0;
-    let lastTimestamp = startTime;
+    // Bytes and timestamps per direction, so received and sent counters
+    // never get subtracted from each other
+    const lastBytes = { "inbound-rtp": 0, "outbound-rtp": 0 };
+    const lastTimestamp = {
+      "inbound-rtp": startTime,
+      "outbound-rtp": startTime,
+    };
+    let lastPacketsLost = 0;
+    let lastPacketsReceived = 0;
 
     const updateStats = async () => {
       if (this.activeConnection !== peerId) return;
 
       try {
         const stats = await audioManager.peerConnection.getStats();
-        let bitrate = 0;
+        const bitrates = { "inbound-rtp": 0, "outbound-rtp": 0 };
         let audioLevel = 0;
+        let rttMs = 0;
+        let jitterMs = 0;
+        let loss = 0;
 
         stats.forEach((report) => {
+          if (report.type === "candidate-pair" && report.nominated) {
+            rttMs = (report.currentRoundTripTime ?? 0) * 1000;
+          }
+
+          if (report.type === "inbound-rtp" && report.kind === "audio") {
+            jitterMs = (report.jitter ?? 0) * 1000;
+
+            // Loss over the last interval rather than the whole call
+            const lost = report.packetsLost - lastPacketsLost;
+            const received = report.packetsReceived - lastPacketsReceived;
+            if (lost + received > 0) {
+              loss = Math.max(0, lost) / (lost + received);
+            }
+            lastPacketsLost = report.packetsLost;
+            lastPacketsReceived = report.packetsReceived;
+          }
+
           if (
             (report.type === "inbound-rtp" || report.type === "outbound-rtp") &&
             report.kind === "audio"
@@ -635,16 +681,16 @@ class AudioDecayClient {
               report.type === "inbound-rtp"
                 ? report.bytesReceived
                 : report.bytesSent;
-            const timeDiff = (now - lastTimestamp) / 1000;
+            const timeDiff = (now - lastTimestamp[report.type]) / 1000;
 
-            if (lastBytes > 0 && timeDiff > 0) {
-              bitrate = Math.round(
-                ((bytes - lastBytes) * 8) / (timeDiff * 1000),
+            if (lastBytes[report.type] > 0 && timeDiff > 0) {
+              bitrates[report.type] = Math.round(
+                ((bytes - lastBytes[report.type]) * 8) / (timeDiff * 1000),
               );
             }
 
-            lastBytes = bytes;
-            lastTimestamp = now;
+            lastBytes[report.type] = bytes;
+            lastTimestamp[report.type] = now;
 
             // Also monitor audio levels if available
             if (report.audioLevel) {
@@ -653,11 +699,22 @@ class AudioDecayClient {
           }
         });
 
+        // Show what we receive, or what we send before anything arrives
+        const inbound = bitrates["inbound-rtp"];
         connectionState.updateStats({
-          bitrate,
+          bitrate: inbound || bitrates["outbound-rtp"],
           audioLevel,
           elapsedTime: (Date.now() - startTime) / 1000,
         });
+
+        // Let a worse connection decay the voice further
+        audioManager.updateNetworkStats({
+          rttMs,
+          jitterMs,
+          loss,
+          bitrateKbps: inbound,
+        });
+        audioManager.updateSessionTime(Date.now());
       } catch (error) {
         console.warn("Failed to get connection stats:", error);
       }